@@ -4,7 +4,7 @@ use std::time::Duration;
 use async_lock::RwLock;
 use simple_error::SimpleResult;
 use async_executor::{with_thread_pool, Executor};
-use tradingview_common::{TradingViewClientConfig, TradingViewIndicators, TradingViewSymbols};
+use tradingview_common::{Adjustment, BuiltinStudy, ConnectionOptions, MacdInputs, MovingAverageInputs, RsiInputs, SymbolSession, SymbolSpec, Timeframe, TimeframeUnit, TradingViewClientConfig, TradingViewIndicators};
 use tradingview_client::{LoggingMessageProcessor, TradingViewClient};
 
 async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
@@ -30,29 +30,44 @@ async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
       51,
       21
     );
+    let spy_regular = SymbolSpec::new("AMEX:SPY")
+        .with_adjustment(Adjustment::Splits)
+        .with_currency_id("USD")
+        .with_session(SymbolSession::Regular);
+    let spy_extended = spy_regular.clone().with_session(SymbolSession::Extended);
     let clients = vec![
         TradingViewClient::new(TradingViewClientConfig {
             name: "SPY5REG".to_string(),
             auth_token: auth_token.clone(),
-            chart_symbols: vec![TradingViewSymbols::build_symbol("splits", Some("USD"), "regular", "AMEX:SPY")],
-            quote_symbols: vec![TradingViewSymbols::build_symbol("splits", Some("USD"), "regular", "AMEX:SPY")],
+            anonymous: false,
+            chart_symbols: vec![spy_regular.to_string()],
+            quote_symbols: vec![spy_regular.to_string()],
             indicators: vec![
-              vwap_mvwap_ema_crossover.clone()
+              vwap_mvwap_ema_crossover.to_indicator(),
+              BuiltinStudy::Rsi(RsiInputs::default()).to_indicator(),
+              BuiltinStudy::Macd(MacdInputs::default()).to_indicator(),
+              // EMA of the RSI above, indicators are st2, st3, ... in order
+              BuiltinStudy::Ema(MovingAverageInputs { length: 9, source: "st3:RSI".to_string() }).to_indicator()
             ],
-            timeframe: Some("5".to_string()),
+            timeframe: Some(Timeframe::new(5, TimeframeUnit::Minutes)),
             range: Some(300),
+            capture_path: None,
+            connection: ConnectionOptions::default(),
         }, message_processor1),
 
         TradingViewClient::new(TradingViewClientConfig {
             name: "SPY5EXT".to_string(),
             auth_token: auth_token.clone(),
-            chart_symbols: vec![TradingViewSymbols::build_symbol("splits", Some("USD"), "extended", "AMEX:SPY")],
-            quote_symbols: vec![TradingViewSymbols::build_symbol("splits", Some("USD"), "extended", "AMEX:SPY")],
+            anonymous: false,
+            chart_symbols: vec![spy_extended.to_string()],
+            quote_symbols: vec![spy_extended.to_string()],
             indicators: vec![
-              vwap_mvwap_ema_crossover.clone()
+              vwap_mvwap_ema_crossover.to_indicator()
             ],
-            timeframe: Some("5".to_string()),
+            timeframe: Some(Timeframe::new(5, TimeframeUnit::Minutes)),
             range: Some(300),
+            capture_path: None,
+            connection: ConnectionOptions::default(),
         }, message_processor2),
     ];
 
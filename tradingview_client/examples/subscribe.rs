@@ -3,7 +3,7 @@ use std::{sync::Arc, time::Duration};
 use async_lock::RwLock;
 use simple_error::SimpleResult;
 use async_executor::{with_thread_pool, Executor};
-use tradingview_common::{TradingViewClientConfig, TradingViewIndicators};
+use tradingview_common::{ConnectionOptions, Timeframe, TimeframeUnit, TradingViewClientConfig, TradingViewIndicators};
 use tradingview_client::{StatefulMessageProcessor, TradingViewClient, TradingViewMessageProcessor};
 
 async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
@@ -37,6 +37,7 @@ async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
     let config = TradingViewClientConfig {
         name: symbol.to_string(),
         auth_token: auth_token.clone(),
+        anonymous: false,
         chart_symbols: vec![
             symbol.to_string()
         ],
@@ -44,10 +45,12 @@ async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
             symbol.to_string()
         ],
         indicators: vec![
-            vwap_mvwap_ema_crossover.to_string()
+            vwap_mvwap_ema_crossover.to_indicator()
         ],
-        timeframe: Some("5".to_string()),
+        timeframe: Some(Timeframe::new(5, TimeframeUnit::Minutes)),
         range: Some(1),
+        capture_path: None,
+        connection: ConnectionOptions::default(),
     };
 
     // build client
@@ -0,0 +1,287 @@
+use base64::Engine;
+use http::{Method, Request};
+use miniserde::json::{Number, Object, Value};
+use simple_error::{box_err, SimpleResult};
+use tradingview_common::utilities::url_encode;
+use tradingview_common::ProxyOptions;
+
+use crate::http_utilities;
+
+#[derive(Debug, Clone)]
+pub enum AuthCredentials {
+    /// Account login through `/accounts/signin/`.
+    Password { username: String, password: String },
+    /// Browser session cookies; the token is scraped from the logged in homepage.
+    SessionId { sessionid: String, sessionid_sign: Option<String> },
+}
+
+#[derive(Debug, Clone)]
+pub struct TradingViewAuthOptions {
+    pub credentials: AuthCredentials,
+    pub cache_path: Option<String>, // token is persisted here between runs
+    pub base_url: String, // https://www.tradingview.com
+    pub refresh_margin_secs: u64, // refresh this long before the token expires
+    pub proxy: Option<ProxyOptions>,
+}
+
+impl TradingViewAuthOptions {
+    pub fn new(credentials: AuthCredentials) -> Self {
+        Self {
+            credentials,
+            cache_path: None,
+            base_url: "https://www.tradingview.com".to_string(),
+            refresh_margin_secs: 300,
+            proxy: None,
+        }
+    }
+}
+
+/// Obtains, caches and refreshes auth tokens for `set_auth_token`.
+pub struct TradingViewAuthenticator {
+    options: TradingViewAuthOptions,
+    auth_token: Option<String>,
+}
+
+impl TradingViewAuthenticator {
+    pub fn new(options: TradingViewAuthOptions) -> Self {
+        Self {
+            options,
+            auth_token: None,
+        }
+    }
+
+    /// Returns a token that is not about to expire, from memory, the disk cache or a fresh login.
+    pub async fn token(&mut self) -> SimpleResult<String> {
+        // memory
+        if let Some(auth_token) = &self.auth_token {
+            if !self.is_expiring(auth_token)? {
+                return Ok(auth_token.clone());
+            }
+        }
+
+        // disk cache
+        if let Some(cache_path) = &self.options.cache_path {
+            if let Ok(cached) = async_fs::read_to_string(cache_path).await {
+                let cached = cached.trim().to_string();
+                if !cached.is_empty() && !self.is_expiring(&cached)? {
+                    log::debug!("using cached auth token from {cache_path}");
+                    self.auth_token = Some(cached.clone());
+                    return Ok(cached);
+                }
+            }
+        }
+
+        self.refresh().await
+    }
+
+    /// Fetches a new token regardless of the current one (e.g. after the server rejected it).
+    pub async fn refresh(&mut self) -> SimpleResult<String> {
+        log::info!("refreshing auth token");
+        let auth_token = match &self.options.credentials {
+            AuthCredentials::Password { username, password } => self.sign_in(username, password).await?,
+            AuthCredentials::SessionId { sessionid, sessionid_sign } => self.fetch_with_session(sessionid, sessionid_sign.as_deref()).await?,
+        };
+
+        // persist
+        if let Some(cache_path) = &self.options.cache_path {
+            async_fs::write(cache_path, &auth_token).await?;
+        }
+        self.auth_token = Some(auth_token.clone());
+        Ok(auth_token)
+    }
+
+    /// After the server rejected `rejected_token`: fetches a new token, unless another connection sharing this
+    /// authenticator already replaced it, then that one is returned.
+    pub async fn refresh_rejected(&mut self, rejected_token: &str) -> SimpleResult<String> {
+        match &self.auth_token {
+            Some(auth_token) if auth_token != rejected_token => Ok(auth_token.clone()),
+            _ => self.refresh().await,
+        }
+    }
+
+    /// True when the current token expires within the refresh margin.
+    pub fn needs_refresh(&self) -> SimpleResult<bool> {
+        match &self.auth_token {
+            Some(auth_token) => self.is_expiring(auth_token),
+            None => Ok(true),
+        }
+    }
+
+    fn is_expiring(&self, auth_token: &str) -> SimpleResult<bool> {
+        match token_expiry(auth_token) {
+            Some(expires_at) => Ok(tradingview_common::utilities::now()? + self.options.refresh_margin_secs >= expires_at),
+            // opaque tokens are kept until the server rejects them
+            None => Ok(false),
+        }
+    }
+
+    async fn sign_in(&self, username: &str, password: &str) -> SimpleResult<String> {
+        let body = format!("username={}&password={}&remember=on", url_encode(username), url_encode(password));
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("{}/accounts/signin/", self.options.base_url))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Origin", self.options.base_url.as_str())
+            .header("Referer", format!("{}/", self.options.base_url))
+            .header("Accept", "application/json")
+            .body(body.into_bytes())?;
+        let response = http_utilities::send_request(&request, self.options.proxy.as_ref()).await?;
+        if response.status_code != 200 {
+            return Err(box_err!(format!("signin failed with status {}", response.status_code)));
+        }
+
+        // {"error":"","user":{...,"auth_token":"..."}}
+        let body: Object = miniserde::json::from_str(&response.text()?)?;
+        if let Some(Value::String(error)) = body.get("error") {
+            if !error.is_empty() {
+                return Err(box_err!(format!("signin failed: {error}")));
+            }
+        }
+        match body.get("user") {
+            Some(Value::Object(user)) => match user.get("auth_token") {
+                Some(Value::String(auth_token)) => Ok(auth_token.clone()),
+                _ => Err(box_err!("signin response has no auth_token")),
+            },
+            _ => Err(box_err!("signin response has no user")),
+        }
+    }
+
+    async fn fetch_with_session(&self, sessionid: &str, sessionid_sign: Option<&str>) -> SimpleResult<String> {
+        let cookie = match sessionid_sign {
+            Some(sessionid_sign) => format!("sessionid={sessionid}; sessionid_sign={sessionid_sign}"),
+            None => format!("sessionid={sessionid}"),
+        };
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(format!("{}/", self.options.base_url))
+            .header("Cookie", cookie)
+            .header("Accept", "text/html")
+            .body(vec![])?;
+        let response = http_utilities::send_request(&request, self.options.proxy.as_ref()).await?;
+        if response.status_code != 200 {
+            return Err(box_err!(format!("session fetch failed with status {}", response.status_code)));
+        }
+
+        // the homepage embeds the user object: ..."auth_token":"<token>"...
+        let html = response.text()?;
+        let start = html.find(r#""auth_token":""#).ok_or(box_err!("sessionid is not logged in (no auth_token in page)"))? + r#""auth_token":""#.len();
+        let end = html[start..].find('"').ok_or(box_err!("unterminated auth_token"))? + start;
+        Ok(html[start..end].to_string())
+    }
+}
+
+/// Reads `exp` (unix seconds) from a JWT auth token without verifying it.
+pub fn token_expiry(auth_token: &str) -> Option<u64> {
+    let payload = auth_token.split('.').nth(1)?;
+    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let payload: Object = miniserde::json::from_str(std::str::from_utf8(&payload).ok()?).ok()?;
+    match payload.get("exp")? {
+        Value::Number(Number::U64(exp)) => Some(*exp),
+        Value::Number(Number::I64(exp)) => Some(*exp as u64),
+        Value::Number(Number::F64(exp)) => Some(*exp as u64),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    use super::*;
+
+    fn jwt(exp: u64) -> String {
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(format!(r#"{{"user_id":1,"exp":{exp}}}"#));
+        format!("eyJhbGciOiJSUzUxMiJ9.{payload}.signature")
+    }
+
+    // answers each signin with the next token, returns the request bodies
+    fn fake_signin(tokens: Vec<&'static str>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let handle = std::thread::spawn(move || {
+            let mut bodies = vec![];
+            for token in tokens {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = vec![];
+                let mut byte = [0u8; 1];
+                while !request.ends_with(b"\r\n\r\n") {
+                    stream.read_exact(&mut byte).unwrap();
+                    request.push(byte[0]);
+                }
+                let head = String::from_utf8(request).unwrap();
+                let content_length = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .map(|value| value.parse::<usize>().unwrap())
+                    .unwrap_or(0);
+                let mut body = vec![0u8; content_length];
+                stream.read_exact(&mut body).unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+                let json = format!(r#"{{"error":"","user":{{"username":"user","auth_token":"{token}"}}}}"#);
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{json}", json.len()).unwrap();
+            }
+            bodies
+        });
+        (base_url, handle)
+    }
+
+    fn password_options(base_url: &str) -> TradingViewAuthOptions {
+        let mut options = TradingViewAuthOptions::new(AuthCredentials::Password {
+            username: "user@example.com".to_string(),
+            password: "p&ss word".to_string(),
+        });
+        options.base_url = base_url.to_string();
+        options
+    }
+
+    #[test]
+    fn token_expiry_reads_the_jwt_payload() {
+        assert_eq!(token_expiry(&jwt(1_700_000_000)), Some(1_700_000_000));
+        assert_eq!(token_expiry("opaque-token"), None);
+        assert_eq!(token_expiry("a.not-base64!.c"), None);
+    }
+
+    #[test]
+    fn refresh_rejected_refreshes_once_per_rejected_token() {
+        let (base_url, server) = fake_signin(vec!["first", "second"]);
+        let mut authenticator = TradingViewAuthenticator::new(password_options(&base_url));
+        async_io::block_on(async {
+            assert_eq!(authenticator.token().await.unwrap(), "first");
+            // opaque tokens are kept until rejected
+            assert_eq!(authenticator.token().await.unwrap(), "first");
+            // another connection already replaced the rejected token
+            assert_eq!(authenticator.refresh_rejected("stale").await.unwrap(), "first");
+            assert_eq!(authenticator.refresh_rejected("first").await.unwrap(), "second");
+        });
+        let bodies = server.join().unwrap();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0], "username=user%40example.com&password=p%26ss%20word&remember=on");
+    }
+
+    #[test]
+    fn expiring_tokens_are_refreshed_and_cached() {
+        let cache_path = std::env::temp_dir().join(format!("tradingview_auth_test_{}", std::process::id()));
+        let cache_path = cache_path.to_str().unwrap().to_string();
+        std::fs::write(&cache_path, jwt(1)).unwrap();
+        let (base_url, server) = fake_signin(vec!["fresh"]);
+        let mut options = password_options(&base_url);
+        options.cache_path = Some(cache_path.clone());
+        let mut authenticator = TradingViewAuthenticator::new(options);
+        assert!(authenticator.needs_refresh().unwrap());
+        assert_eq!(async_io::block_on(authenticator.token()).unwrap(), "fresh");
+        assert!(!authenticator.needs_refresh().unwrap());
+        assert_eq!(std::fs::read_to_string(&cache_path).unwrap(), "fresh");
+        assert_eq!(server.join().unwrap().len(), 1);
+
+        // a cached token that is still valid needs no login
+        let valid = jwt(tradingview_common::utilities::now().unwrap() + 3600);
+        std::fs::write(&cache_path, &valid).unwrap();
+        let mut options = password_options("http://127.0.0.1:1");
+        options.cache_path = Some(cache_path.clone());
+        assert_eq!(async_io::block_on(TradingViewAuthenticator::new(options).token()).unwrap(), valid);
+        std::fs::remove_file(&cache_path).unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+use async_fs::File;
+use futures_lite::AsyncWriteExt;
+use miniserde::{Deserialize, Serialize};
+use simple_error::SimpleResult;
+use tradingview_codec::TradingViewMessageWrapper;
+
+/// One line of a capture file (JSON lines): the raw payload plus when it was received.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedMessage {
+    pub timestamp: u64, // milliseconds
+    pub payload: String,
+}
+
+/// Writes every inbound message of a session to a capture file for later replay.
+///
+/// One file holds one session: an existing file is overwritten, replaying two sessions back to back would see a
+/// second server hello and sleep across the gap between them.
+pub struct TradingViewCaptureWriter {
+    file: File,
+}
+
+impl TradingViewCaptureWriter {
+    pub async fn create(path: &str) -> SimpleResult<Self> {
+        let file = async_fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .await?;
+        Ok(Self { file })
+    }
+
+    pub async fn write_message(&mut self, message: &TradingViewMessageWrapper) -> SimpleResult<()> {
+        let captured_message = CapturedMessage {
+            timestamp: tradingview_common::utilities::now_millis()?,
+            payload: message.payload.clone(),
+        };
+        let line = format!("{}\n", miniserde::json::to_string(&captured_message));
+        self.file.write_all(line.as_bytes()).await?;
+        self.file.flush().await?;
+        Ok(())
+    }
+}
@@ -1,53 +1,140 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
 
 use async_executor::Executor;
-use async_lock::RwLock;
+use async_lock::{Mutex, RwLock};
 use futures_lite::AsyncRead;
 use futures_lite::AsyncWrite;
 use http::{Request, Uri, Version};
 
-use simple_error::SimpleResult;
-use websocket_client::WebSocketClient;
+use simple_error::{box_err, SimpleResult};
+use websocket_client::{WebSocketClient, WebSocketReader, WebSocketWriter};
 use tradingview_codec::TradingViewMessageWrapper;
 use tradingview_common::{
+    Clock,
     DataUpdateMessage, 
     ParsedTradingViewMessage, 
+    PinePlotDefinition,
+    ProxyOptions,
     QuoteCompletedMessage, 
     QuoteSeriesDataMessage, 
     SeriesCompletedMessage, 
     SeriesLoadingMessage,
     ServerHelloMessage, 
     StudyCompletedMessage, 
+    StudyGraph,
     StudyLoadingMessage, 
+    StudyNode,
     SymbolResolvedMessage, 
     TimescaleUpdatedMessage, 
     TradingViewClientConfig, 
-    TradingViewScrapeResult
+    TradingViewScrapeResult,
+    is_unauthorized_error,
 };
 
+use crate::auth::TradingViewAuthenticator;
+use crate::capture::TradingViewCaptureWriter;
+use crate::client_handle::{StudyModificationTracker, TradingViewClientCommand, TradingViewClientHandle};
 use crate::client_utilities;
 use crate::message_utilities;
+use crate::proxy;
 use crate::reader::TradingViewReader;
+use crate::replay::{ReplayClockGuard, ReplaySpeed, TradingViewReplayReader, TradingViewReplaySink};
+use crate::transport::{TradingViewMessageSink, TradingViewMessageSource};
+use crate::websocket_handshake;
 use crate::writer::TradingViewWriter;
 use crate::message_processor::TradingViewMessageProcessor;
+use crate::events::{BarCloseTracker, ContractRollTracker, MarketStatusTracker};
+
+// every chart's studies hang off the sessions study
+const STUDY_SESSION_ID: &str = "st1";
+
+enum SessionInput {
+    Message(Option<Box<TradingViewMessageWrapper>>),
+    Command(Option<TradingViewClientCommand>),
+}
 
 pub struct TradingViewClient {
     config: TradingViewClientConfig,
-    message_processor: Arc<RwLock<dyn TradingViewMessageProcessor + Send + Sync>>
+    message_processor: Arc<RwLock<dyn TradingViewMessageProcessor + Send + Sync>>,
+    authenticator: Option<Arc<Mutex<TradingViewAuthenticator>>>,
+    commands: Arc<RwLock<Vec<TradingViewClientCommand>>>,
+    clock: Clock
 }
 
 impl TradingViewClient {
     pub fn new(config: TradingViewClientConfig, message_processor: Arc<RwLock<dyn TradingViewMessageProcessor + Send + Sync>>) -> Self {
         Self {
             config,
-            message_processor
+            message_processor,
+            authenticator: None,
+            commands: Arc::new(RwLock::new(vec![])),
+            clock: Clock::default()
+        }
+    }
+
+    /// Shares `clock` with whatever reads this client's data (message processors, scrapers); `replay` drives it
+    /// from the capture timestamps.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> Clock {
+        self.clock.clone()
+    }
+
+    /// Handle for changing the session while `subscribe` runs; commands queue up until setup is done.
+    pub fn handle(&self) -> TradingViewClientHandle {
+        TradingViewClientHandle {
+            commands: self.commands.clone(),
+            timeout: self.config.connection.study_timeout(),
+        }
+    }
+
+    /// Chart session of the n-th (from 0) entry in `chart_symbols`.
+    pub fn chart_session_id(chart_index: usize) -> String {
+        format!("cs_{:012}", chart_index + 1)
+    }
+
+    /// Uses `authenticator` instead of `config.auth_token` and re-sends `set_auth_token` whenever its token changes.
+    ///
+    /// The authenticator can be shared between clients so a refresh is done once; every connection sends the new
+    /// token on its next heartbeat.
+    pub fn with_authenticator(mut self, authenticator: Arc<Mutex<TradingViewAuthenticator>>) -> Self {
+        self.authenticator = Some(authenticator);
+        self
+    }
+
+    /// Sends the authenticator's token if it differs from `sent_auth_token`, the one this connection last sent.
+    /// `rejected` fetches a new one unless another connection already did.
+    async fn refresh_auth_token<S: TradingViewMessageSink + Send>(
+        &self,
+        tv_writer: &mut TradingViewWriter<S>,
+        authenticator: &Arc<Mutex<TradingViewAuthenticator>>,
+        sent_auth_token: &mut String,
+        rejected: bool
+    ) -> SimpleResult<()> {
+        let mut authenticator = authenticator.lock().await;
+        let auth_token = if rejected {
+            authenticator.refresh_rejected(sent_auth_token).await?
+        } else {
+            authenticator.token().await?
+        };
+        drop(authenticator);
+        if auth_token == *sent_auth_token {
+            return Ok(());
         }
+        log::info!("[{}] re-sending refreshed auth token", self.config.name);
+        tv_writer.set_auth_token(&auth_token).await?;
+        *sent_auth_token = auth_token;
+        Ok(())
     }
 
-    async fn handle_quote_symbols<W: AsyncWrite + Unpin>(
+    async fn handle_quote_symbols<S: TradingViewMessageSink + Send>(
         &self, 
-        tv_writer: &mut TradingViewWriter<W>, 
+        tv_writer: &mut TradingViewWriter<S>, 
         buffer_arc: &Arc<RwLock<Vec<TradingViewMessageWrapper>>>, 
         scrape_result: &mut TradingViewScrapeResult
     ) -> SimpleResult<()> {
@@ -68,7 +155,7 @@ impl TradingViewClient {
 
             // wait for quote completed message
             let quote_completed_message: QuoteCompletedMessage = client_utilities::wait_for_typed_message_with_timeout(
-                Duration::from_secs(2),
+                self.config.connection.response_timeout(),
                 buffer_arc.clone(),
                 |message| message.payload.contains("quote_completed")
             ).await?;
@@ -77,7 +164,7 @@ impl TradingViewClient {
 
             // wait for quote last price
             let quote_last_price_message: QuoteSeriesDataMessage = client_utilities::wait_for_typed_message_with_timeout(
-                Duration::from_secs(2),
+                self.config.connection.response_timeout(),
                 buffer_arc.clone(),
                 |message| {
                     match &message.parsed_message {
@@ -97,16 +184,18 @@ impl TradingViewClient {
         Ok(())
     }
 
-    async fn handle_chart_symbols<W: AsyncWrite + Unpin>(
+    async fn handle_chart_symbols<S: TradingViewMessageSink + Send>(
         &self, 
-        tv_writer: &mut TradingViewWriter<W>, 
+        tv_writer: &mut TradingViewWriter<S>, 
         buffer_arc: &Arc<RwLock<Vec<TradingViewMessageWrapper>>>, 
-        scrape_result: &mut TradingViewScrapeResult
+        scrape_result: &mut TradingViewScrapeResult,
+        study_graph: &StudyGraph,
+        study_plots: &mut BTreeMap<(String, String), Vec<PinePlotDefinition>>
     ) -> SimpleResult<()> {
         let mut index = 1;
         for chart_symbol in &self.config.chart_symbols {
             // create chart session
-            let chart_session_id = format!("cs_{index:012}");
+            let chart_session_id = Self::chart_session_id(index - 1);
 
             // create chart session
             tv_writer.chart_create_session(&chart_session_id).await?;
@@ -117,7 +206,7 @@ impl TradingViewClient {
 
             // wait for symbol resolved message
             let symbol_resolved_message: SymbolResolvedMessage = client_utilities::wait_for_typed_message_with_timeout(
-                Duration::from_secs(2),
+                self.config.connection.response_timeout(),
                 buffer_arc.clone(),
                 |message| message.payload.contains("symbol_resolved")
             ).await?;
@@ -126,14 +215,14 @@ impl TradingViewClient {
 
             // add symbol to chart session as series
             let series_id = "sds_1";
-            tv_writer.create_series(&chart_session_id, series_id, "s1",  symbol_id, &self.config.timeframe.as_ref().unwrap(), self.config.range.unwrap()).await?;
+            tv_writer.create_series(&chart_session_id, series_id, "s1",  symbol_id, &self.config.timeframe.as_ref().unwrap().to_string(), self.config.range.unwrap()).await?;
 
             // switch chart timezone
             tv_writer.switch_timezone(&chart_session_id, "exchange").await?;
 
             // wait for series loading message
             let series_loading_message: SeriesLoadingMessage = client_utilities::wait_for_typed_message_with_timeout(
-                Duration::from_secs(2),
+                self.config.connection.response_timeout(),
                 buffer_arc.clone(),
                 |message| message.payload.contains("series_loading")
             ).await?;
@@ -142,7 +231,7 @@ impl TradingViewClient {
 
             // wait for timescale update message
             let timescale_update_message: TimescaleUpdatedMessage = client_utilities::wait_for_typed_message_with_timeout(
-                Duration::from_secs(2),
+                self.config.connection.response_timeout(),
                 buffer_arc.clone(),
                 |message| message.payload.contains("timescale_update")
             ).await?;
@@ -151,7 +240,7 @@ impl TradingViewClient {
 
             // wait for series completed message
             let series_completed_message: SeriesCompletedMessage = client_utilities::wait_for_typed_message_with_timeout(
-                Duration::from_secs(2),
+                self.config.connection.response_timeout(),
                 buffer_arc.clone(),
                 |message| message.payload.contains("series_completed")
             ).await?;
@@ -160,12 +249,12 @@ impl TradingViewClient {
 
             // optionally create study session
             if self.config.indicators.len() > 0 {
-                let study_session_id = "st1";
+                let study_session_id = STUDY_SESSION_ID;
                 tv_writer.create_study(&chart_session_id, study_session_id, "sessions_1", series_id, "Sessions@tv-basicstudies-241", "{}").await?;
 
                 // wait for study loading message
                 let study_loading_message: StudyLoadingMessage = client_utilities::wait_for_typed_message_with_timeout(
-                    Duration::from_secs(10), // not ideal
+                    self.config.connection.study_session_timeout(),
                     buffer_arc.clone(),
                     |message| message.payload.contains("study_loading")
                 ).await?;
@@ -174,58 +263,27 @@ impl TradingViewClient {
 
                 // wait for study completed message
                 let study_completed_message: StudyCompletedMessage= client_utilities::wait_for_typed_message_with_timeout(
-                    Duration::from_secs(3),
+                    self.config.connection.study_timeout(),
                     buffer_arc.clone(),
                     |message| message.payload.contains("study_completed")
                 ).await?;
                 log::debug!("study_completed_message = {study_completed_message:?}");
                 scrape_result.study_completed_messages.push(study_completed_message.clone());
 
-                let mut index = 2;
-                for indciator in &self.config.indicators {
-                    let study_value = indciator;
-                    let study_id = format!("st{index}");
-                    tv_writer.create_study(&chart_session_id, &study_id, study_session_id, series_id, "Script@tv-scripting-101!", study_value).await?;
-                    index += 1;
-
-                    // wait for study loading message
-                    let study_loading_message: StudyLoadingMessage = client_utilities::wait_for_typed_message_with_timeout(
-                        Duration::from_secs(3),
-                        buffer_arc.clone(),
-                        |message| message.payload.contains("study_loading")
-                    ).await?;
-                    log::debug!("study_loading_message = {study_loading_message:?}");
-                    scrape_result.study_loading_messages.push(study_loading_message.clone());
-
-                    // wait for study completed message
-                    let study_completed_message: StudyCompletedMessage = client_utilities::wait_for_typed_message_with_timeout(
-                        Duration::from_secs(3),
-                        buffer_arc.clone(),
-                        |message| message.payload.contains("study_completed")
-                    ).await?;
-                    log::debug!("study_completed_message = {study_completed_message:?}");
-                    scrape_result.study_completed_messages.push(study_completed_message.clone());
-
-                    // wait for study data update
-                    let study_data_update_message: DataUpdateMessage = client_utilities::wait_for_typed_message_with_timeout(
-                        Duration::from_secs(3),
-                        buffer_arc.clone(),
-                        |message| {
-                            match &message.parsed_message {
-                                ParsedTradingViewMessage::DataUpdate(data_update_message) => {
-                                    match &data_update_message.study_updates {
-                                        Some(study_updates) => {
-                                            return study_updates.len() > 0
-                                        },
-                                        None => return false
-                                    }
-                                },
-                                _ => false
-                            }
+                // studies applied to other studies' outputs are created after their parents
+                let mut created_study_ids: Vec<&str> = vec![];
+                for study_node in study_graph.creation_order() {
+                    let parent_id = study_node.parent_id.as_deref().unwrap_or(series_id);
+                    tv_writer.create_study(&chart_session_id, &study_node.study_id, study_session_id, parent_id, &study_node.indicator.name, &study_node.indicator.value).await?;
+                    if let Err(err) = self.wait_for_study(buffer_arc, scrape_result, study_plots, study_node).await {
+                        // the failed study may exist on the server too
+                        created_study_ids.push(&study_node.study_id);
+                        if let Err(remove_err) = remove_studies(tv_writer, study_graph, &chart_session_id, &created_study_ids).await {
+                            log::warn!("[{}] failed to remove studies after setup failure: {remove_err:?}", self.config.name);
                         }
-                    ).await?;
-                    log::debug!("study_data_update_message = {study_data_update_message:?}");
-                    scrape_result.study_data_update_messages.push(study_data_update_message.clone());
+                        return Err(err);
+                    }
+                    created_study_ids.push(&study_node.study_id);
                 }
             }
 
@@ -235,18 +293,80 @@ impl TradingViewClient {
         Ok(())
     }
 
-    pub async fn connect(&self) -> SimpleResult<(TradingViewReader<impl AsyncRead>, TradingViewWriter<impl AsyncWrite>)> {
+    /// Waits for a created study's loading, completed and first data messages.
+    async fn wait_for_study(
+        &self,
+        buffer_arc: &Arc<RwLock<Vec<TradingViewMessageWrapper>>>,
+        scrape_result: &mut TradingViewScrapeResult,
+        study_plots: &mut BTreeMap<(String, String), Vec<PinePlotDefinition>>,
+        study_node: &StudyNode
+    ) -> SimpleResult<()> {
+        // wait for study loading message
+        let study_loading_message: StudyLoadingMessage = client_utilities::wait_for_typed_message_with_timeout(
+            self.config.connection.study_timeout(),
+            buffer_arc.clone(),
+            |message| message.payload.contains("study_loading")
+        ).await?;
+        log::debug!("study_loading_message = {study_loading_message:?}");
+        scrape_result.study_loading_messages.push(study_loading_message.clone());
+
+        // updates for this study get its plot layout
+        let study_key = (study_loading_message.chart_session_id.clone(), study_loading_message.study_id.clone());
+        study_plots.insert(study_key, study_node.indicator.plots.clone());
+
+        // wait for study completed message
+        let study_completed_message: StudyCompletedMessage = client_utilities::wait_for_typed_message_with_timeout(
+            self.config.connection.study_timeout(),
+            buffer_arc.clone(),
+            |message| message.payload.contains("study_completed")
+        ).await?;
+        log::debug!("study_completed_message = {study_completed_message:?}");
+        scrape_result.study_completed_messages.push(study_completed_message.clone());
+
+        // wait for study data update
+        let study_data_update_message: DataUpdateMessage = client_utilities::wait_for_typed_message_with_timeout(
+            self.config.connection.study_timeout(),
+            buffer_arc.clone(),
+            |message| {
+                match &message.parsed_message {
+                    ParsedTradingViewMessage::DataUpdate(data_update_message) => {
+                        match &data_update_message.study_updates {
+                            Some(study_updates) => {
+                                return study_updates.len() > 0
+                            },
+                            None => return false
+                        }
+                    },
+                    _ => false
+                }
+            }
+        ).await?;
+        log::debug!("study_data_update_message = {study_data_update_message:?}");
+        scrape_result.study_data_update_messages.push(study_data_update_message.clone());
+        Ok(())
+    }
+
+    fn build_request(&self) -> SimpleResult<Request<Vec<u8>>> {
         // Build the GET request
-        let uri: Uri = "wss://data.tradingview.com/socket.io/websocket?type=chart".parse()?;
-        let request = Request::builder()
+        let connection = &self.config.connection;
+        let uri: Uri = connection.endpoint()?.parse()?;
+        let host = uri.authority().map(|authority| authority.to_string()).ok_or(box_err!("endpoint has no host"))?;
+        let mut request = Request::builder()
             .method("GET")
             .version(Version::HTTP_11)
             .uri(uri)
-            .header("User-Agent", "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36")
-            .header("Host", "data.tradingview.com")
-            .header("Origin", "https://www.tradingview.com")            
-            .body(vec![])?;
-        let (ws_reader, ws_writer) = WebSocketClient::open(request).await?;
+            .header("Host", host);
+        for (name, value) in &connection.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        Ok(request.body(vec![])?)
+    }
+
+    pub async fn connect(&self) -> SimpleResult<(TradingViewReader<impl AsyncRead + Unpin + Send + 'static>, TradingViewWriter<WebSocketWriter<impl AsyncWrite + Unpin + Send>>)> {
+        let request = self.build_request()?;
+        let (ws_reader, ws_writer) = message_utilities::run_with_timeout(self.config.connection.connect_timeout(), Box::pin(WebSocketClient::open(request)))
+            .await
+            .ok_or(box_err!("timed out connecting"))??;
 
         // Create the TradingViewClient
         let tv_reader = TradingViewReader::new(ws_reader);
@@ -255,24 +375,85 @@ impl TradingViewClient {
         Ok((tv_reader, tv_writer))
     }
 
+    /// Same as `connect`, but tunnels through `proxy` before tls and the websocket upgrade.
+    pub async fn connect_via_proxy(&self, proxy: &ProxyOptions) -> SimpleResult<(TradingViewReader<impl AsyncRead + Unpin + Send + 'static>, TradingViewWriter<WebSocketWriter<impl AsyncWrite + Unpin + Send>>)> {
+        let request = self.build_request()?;
+        let open = async {
+            let mut stream = proxy::open_stream(request.uri(), Some(proxy)).await?;
+            websocket_handshake::upgrade(&mut stream, &request).await?;
+            SimpleResult::Ok(stream)
+        };
+        let stream = message_utilities::run_with_timeout(self.config.connection.connect_timeout(), Box::pin(open))
+            .await
+            .ok_or(box_err!("timed out connecting through proxy"))??;
+        let (reader, writer) = futures_lite::io::split(stream);
+
+        // Create the TradingViewClient
+        let tv_reader = TradingViewReader::new(WebSocketReader::new(reader));
+        let tv_writer = TradingViewWriter::new(WebSocketWriter::new(writer));
+
+        Ok((tv_reader, tv_writer))
+    }
+
     pub async fn subscribe(&self, executor: Arc<Executor<'static>>) -> SimpleResult<()> {
         // connect
-        let (mut tv_reader, mut tv_writer) = self.connect().await?;
+        match &self.config.connection.proxy {
+            Some(proxy) => {
+                let (tv_reader, tv_writer) = self.connect_via_proxy(proxy).await?;
+                self.run(executor, tv_reader, tv_writer, self.authenticator.as_ref(), self.config.capture_path.as_deref()).await
+            }
+            None => {
+                let (tv_reader, tv_writer) = self.connect().await?;
+                self.run(executor, tv_reader, tv_writer, self.authenticator.as_ref(), self.config.capture_path.as_deref()).await
+            }
+        }
+    }
+
+    /// Runs the same session as `subscribe` against a capture file instead of the network.
+    ///
+    /// The client's clock follows the capture until the replay ends. `config.capture_path` is ignored, a replay never
+    /// records, so replaying from the capture directory leaves the recording intact.
+    pub async fn replay(&self, executor: Arc<Executor<'static>>, capture_path: &str, speed: ReplaySpeed) -> SimpleResult<()> {
+        let _clock_guard = ReplayClockGuard::new(self.clock.clone());
+        let tv_reader = TradingViewReplayReader::open(capture_path, speed, self.clock.clone()).await?;
+        let tv_writer = TradingViewWriter::new(TradingViewReplaySink);
+        self.run(executor, tv_reader, tv_writer, None, None).await
+    }
 
+    async fn run<R, S>(&self, executor: Arc<Executor<'static>>, mut tv_reader: R, mut tv_writer: TradingViewWriter<S>, authenticator: Option<&Arc<Mutex<TradingViewAuthenticator>>>, capture_path: Option<&str>) -> SimpleResult<()>
+    where
+        R: TradingViewMessageSource + Send + 'static,
+        S: TradingViewMessageSink + Send,
+    {
         // prepare buffer + references + scrape result
         let buffer: Vec<TradingViewMessageWrapper> = Vec::new();
         let buffer = RwLock::new(buffer);
         let buffer_arc = Arc::new(buffer);
+        let closed_arc = Arc::new(AtomicBool::new(false));
         let mut scrape_result = TradingViewScrapeResult::new();
 
+        // optionally capture every inbound message
+        let mut capture_writer = match capture_path {
+            Some(capture_path) => Some(TradingViewCaptureWriter::create(capture_path).await?),
+            None => None,
+        };
+
         // Spawn the reader task
         let reader_handle_buffer_ref = buffer_arc.clone();
+        let reader_handle_closed_ref = closed_arc.clone();
         let _reader_handle = executor.spawn(async move {
             loop {
                 match tv_reader.read_message().await {
                     Ok(result) => {
                         match result {
                             Some(message) => {
+                                // capture message
+                                if let Some(capture_writer) = &mut capture_writer {
+                                    if let Err(err) = capture_writer.write_message(&message).await {
+                                        log::error!("failed to capture message: {err:?}");
+                                    }
+                                }
+
                                 // add message to buffer
                                 let mut write_lock = reader_handle_buffer_ref.write().await;
                                 write_lock.push(message);
@@ -287,11 +468,12 @@ impl TradingViewClient {
                     Err(err) => panic!("{err:?}"),
                 }
             }
+            reader_handle_closed_ref.store(true, Ordering::SeqCst);
         });
         
         // Wait for server hello message with timeout
         let server_hello_message: ServerHelloMessage = client_utilities::wait_for_typed_message_with_timeout(
-            Duration::from_secs(5),
+            self.config.connection.handshake_timeout(),
             buffer_arc.clone(),
             |message| message.payload.contains("javastudies")
         ).await?;
@@ -299,17 +481,52 @@ impl TradingViewClient {
         scrape_result.server_hello_messages.push(server_hello_message.clone());
 
         // set auth token
-        tv_writer.set_auth_token(&self.config.auth_token).await?;
+        let (mut sent_auth_token, authenticator) = match (self.config.anonymous, authenticator) {
+            (true, _) => ("unauthorized_user_token".to_string(), None),
+            (false, Some(authenticator)) => (authenticator.lock().await.token().await?, Some(authenticator)),
+            (false, None) => (self.config.auth_token.clone(), None),
+        };
+        tv_writer.set_auth_token(&sent_auth_token).await?;
         
         // set locale
         tv_writer.set_locale("en", "US").await?;
 
         // handle chart symbols
-        self.handle_chart_symbols(&mut tv_writer, &buffer_arc, &mut scrape_result).await?;
+        let study_graph = StudyGraph::new(&self.config.indicators)?;
+        let mut study_plots = BTreeMap::new();
+        self.handle_chart_symbols(&mut tv_writer, &buffer_arc, &mut scrape_result, &study_graph, &mut study_plots).await?;
 
         // handle quote symbols
         self.handle_quote_symbols(&mut tv_writer, &buffer_arc, &mut scrape_result).await?;
 
+        // remember the front contracts, forming bars and market sessions we started with
+        let mut contract_roll_tracker = ContractRollTracker::default();
+        let mut bar_close_tracker = BarCloseTracker::new(self.config.timeframe);
+        let mut market_status_tracker = MarketStatusTracker::default();
+        for symbol_resolved_message in &scrape_result.symbol_resolved_messages {
+            contract_roll_tracker.observe(symbol_resolved_message);
+            bar_close_tracker.observe_symbol(symbol_resolved_message);
+        }
+        for timescale_update_message in &scrape_result.timescale_update_messages {
+            bar_close_tracker.observe_history(timescale_update_message);
+        }
+        for quote_last_price_message in &scrape_result.quote_last_price_messages {
+            market_status_tracker.observe(quote_last_price_message);
+        }
+
+        // setup consumed the messages carrying symbol info, update_mode, the bar history and the first study values (strategy reports), hand them to the processor
+        let setup_messages = scrape_result.symbol_resolved_messages.iter().cloned().map(ParsedTradingViewMessage::SymbolResolved)
+            .chain(scrape_result.series_completed_messages.iter().cloned().map(ParsedTradingViewMessage::SeriesCompleted))
+            .chain(scrape_result.timescale_update_messages.iter().cloned().map(ParsedTradingViewMessage::TimescaleUpdate))
+            .chain(scrape_result.quote_last_price_messages.iter().cloned().map(ParsedTradingViewMessage::QuoteSeriesData))
+            .chain(scrape_result.study_data_update_messages.iter().cloned().map(ParsedTradingViewMessage::DataUpdate));
+        for mut setup_message in setup_messages {
+            attach_study_plots(&mut setup_message, &study_plots);
+            let mut message_processor = self.message_processor.write().await;
+            message_processor.process_message(self.config.name.clone(), setup_message).await?;
+            drop(message_processor);
+        }
+
         // request more data from series?
         /*for _ in 0..20 {
             tv_writer.request_more_data(chart_session_id1, series_id, 1000).await?;
@@ -319,29 +536,132 @@ impl TradingViewClient {
             async_io::Timer::after(Duration::from_secs(1)).await;
         }*/
 
-        // read all messages
+        // studies being recomputed for handle.modify_study
+        let mut study_modification_tracker = StudyModificationTracker::new(self.config.connection.study_timeout());
+
+        // read all messages, carrying out handle commands in between
         loop {
-            let result = message_utilities::wait_for_message(buffer_arc.clone(), |_| true).await;
+            let next = futures_lite::future::or(
+                async { SessionInput::Message(message_utilities::wait_for_message_until_closed(buffer_arc.clone(), closed_arc.clone(), |_| true).await.map(Box::new)) },
+                async { SessionInput::Command(message_utilities::wait_for_message(self.commands.clone(), |_| true).await) },
+            ).await;
+            let result = match next {
+                SessionInput::Message(result) => result,
+                SessionInput::Command(Some(TradingViewClientCommand::ModifyStudy { chart_session_id, study_id, indicator, reply })) => {
+                    // st<N>:plot sources go out in the wire format, as in create_study
+                    let indicator = match study_graph.resolve(&study_id, &indicator) {
+                        Ok(indicator) => indicator,
+                        Err(err) => {
+                            reply.write().await.push(Err(format!("modify_study failed: {err}")));
+                            continue;
+                        }
+                    };
+                    study_plots.insert((chart_session_id.clone(), study_id.clone()), indicator.plots.clone());
+                    study_modification_tracker.start(&chart_session_id, &study_id, reply).await;
+                    if let Err(err) = tv_writer.modify_study(&chart_session_id, &study_id, STUDY_SESSION_ID, &indicator.value).await {
+                        // the connection is gone, answer the handle before the session ends
+                        study_modification_tracker.fail(&chart_session_id, &study_id, format!("modify_study failed: {err}")).await;
+                        return Err(err);
+                    }
+                    continue;
+                }
+                SessionInput::Command(Some(TradingViewClientCommand::Shutdown)) => {
+                    log::info!("[{}] shutting down", self.config.name);
+                    let study_ids = study_graph.creation_order().iter().map(|study_node| study_node.study_id.as_str()).collect::<Vec<_>>();
+                    for chart_index in 0..self.config.chart_symbols.len() {
+                        remove_studies(&mut tv_writer, &study_graph, &Self::chart_session_id(chart_index), &study_ids).await?;
+                    }
+                    return Ok(());
+                }
+                SessionInput::Command(None) => continue,
+            };
             match result {
                 Some(message) => {
                     // parse message
-                    let parsed_message = ParsedTradingViewMessage::from_string(&message.payload)?;
+                    let mut parsed_message = ParsedTradingViewMessage::from_string(&message.payload)?;
+
+                    // attach plot layouts to study updates
+                    attach_study_plots(&mut parsed_message, &study_plots);
+
+                    // answer modify_study once its recompute is done
+                    study_modification_tracker.observe(&parsed_message).await;
 
                     // respond to ping
                     match &parsed_message {
                         ParsedTradingViewMessage::Ping(nonce) => {
                             log::debug!("ping nonce = {nonce}");
                             tv_writer.pong(*nonce).await?;
+
+                            // refresh ahead of expiry on the heartbeat cadence, and pick up tokens other clients refreshed
+                            if let Some(authenticator) = authenticator {
+                                self.refresh_auth_token(&mut tv_writer, authenticator, &mut sent_auth_token, false).await?;
+                            }
+                        },
+                        ParsedTradingViewMessage::CriticalError(critical_error_message) if is_unauthorized_error(&critical_error_message.params) => {
+                            log::warn!("[{}] auth token rejected: {critical_error_message:?}", self.config.name);
+                            if let Some(authenticator) = authenticator {
+                                self.refresh_auth_token(&mut tv_writer, authenticator, &mut sent_auth_token, true).await?;
+                            }
+                        },
+                        ParsedTradingViewMessage::ProtocolError(protocol_error_message) if is_unauthorized_error(&protocol_error_message.params) => {
+                            log::warn!("[{}] auth token rejected: {protocol_error_message:?}", self.config.name);
+                            if let Some(authenticator) = authenticator {
+                                self.refresh_auth_token(&mut tv_writer, authenticator, &mut sent_auth_token, true).await?;
+                            }
                         },
                         _ => ()
                     }
 
+                    // derive events, bar closes are also checked on heartbeats for quiet symbols
+                    let now = self.clock.now()?;
+                    let events = match &parsed_message {
+                        ParsedTradingViewMessage::SymbolResolved(symbol_resolved_message) => {
+                            bar_close_tracker.observe_symbol(symbol_resolved_message);
+                            contract_roll_tracker.observe(symbol_resolved_message).into_iter().collect()
+                        },
+                        ParsedTradingViewMessage::DataUpdate(data_update_message) => bar_close_tracker.observe(data_update_message, now),
+                        ParsedTradingViewMessage::QuoteSeriesData(quote_series_data_message) => {
+                            market_status_tracker.observe(quote_series_data_message).into_iter().chain(bar_close_tracker.observe_time(now)).collect()
+                        },
+                        _ => bar_close_tracker.observe_time(now),
+                    };
+
                     // process message
                     let mut message_processor = self.message_processor.write().await;
                     message_processor.process_message(self.config.name.clone(), parsed_message).await?;
+                    for event in events {
+                        message_processor.process_event(self.config.name.clone(), event).await?;
+                    }
                     drop(message_processor);
                 },
-                None => panic!("closed")
+                None => {
+                    log::warn!("[{}] connection closed", self.config.name);
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Removes `study_ids` from a chart in `StudyGraph::teardown_order`, so no study outlives its parent.
+async fn remove_studies<S: TradingViewMessageSink + Send>(
+    tv_writer: &mut TradingViewWriter<S>,
+    study_graph: &StudyGraph,
+    chart_session_id: &str,
+    study_ids: &[&str]
+) -> SimpleResult<()> {
+    for study_node in study_graph.teardown_order().filter(|study_node| study_ids.contains(&study_node.study_id.as_str())) {
+        tv_writer.remove_study(chart_session_id, &study_node.study_id).await?;
+    }
+    Ok(())
+}
+
+fn attach_study_plots(parsed_message: &mut ParsedTradingViewMessage, study_plots: &BTreeMap<(String, String), Vec<PinePlotDefinition>>) {
+    if let ParsedTradingViewMessage::DataUpdate(data_update_message) = parsed_message {
+        let chart_session_id = &data_update_message.chart_session_id;
+        for study_update in data_update_message.study_updates.iter_mut().flatten() {
+            if let Some(plots) = study_plots.get(&(chart_session_id.clone(), study_update.study_id.clone())) {
+                study_update.plots = plots.clone();
             }
         }
     }
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_lock::RwLock;
+use simple_error::{box_err, SimpleResult};
+use tradingview_common::{IndicatorConfig, ParsedTradingViewMessage, StudyUpdate};
+
+use crate::message_utilities;
+
+type StudyReply = Arc<RwLock<Vec<Result<Vec<StudyUpdate>, String>>>>;
+
+/// Request from a `TradingViewClientHandle`, carried out by the running session.
+pub(crate) enum TradingViewClientCommand {
+    ModifyStudy {
+        chart_session_id: String,
+        study_id: String,
+        indicator: IndicatorConfig,
+        reply: StudyReply,
+    },
+    Shutdown,
+}
+
+/// Drives a running `TradingViewClient` session from other tasks, see `TradingViewClient::handle`.
+#[derive(Clone)]
+pub struct TradingViewClientHandle {
+    pub(crate) commands: Arc<RwLock<Vec<TradingViewClientCommand>>>,
+    pub(crate) timeout: Duration,
+}
+
+impl TradingViewClientHandle {
+    /// Replaces a study's inputs on the live chart and waits for the recompute (`study_loading` to `study_completed`).
+    ///
+    /// Returns the recomputed study updates, with `indicator.plots` attached: the ones received between `study_loading`
+    /// and `study_completed` or, when the server sends none before `study_completed`, only the first one after it.
+    /// Later updates reach the message processor as usual. Study ids come from `StudyGraph::study_id`, chart session ids
+    /// from `TradingViewClient::chart_session_id`. A newer `modify_study` of the same study makes a pending one return
+    /// an error.
+    pub async fn modify_study(&self, chart_session_id: &str, study_id: &str, indicator: &IndicatorConfig) -> SimpleResult<Vec<StudyUpdate>> {
+        let reply: StudyReply = Arc::new(RwLock::new(vec![]));
+        self.commands.write().await.push(TradingViewClientCommand::ModifyStudy {
+            chart_session_id: chart_session_id.to_string(),
+            study_id: study_id.to_string(),
+            indicator: indicator.clone(),
+            reply: reply.clone(),
+        });
+        message_utilities::wait_for_message_with_timeout(self.timeout, reply, |_| true)
+            .await?
+            .map_err(|err| box_err!(err))
+    }
+
+    /// Removes the session's studies, those applied to others first, and ends `subscribe` / `replay`.
+    pub async fn shutdown(&self) {
+        self.commands.write().await.push(TradingViewClientCommand::Shutdown);
+    }
+}
+
+struct PendingStudyModification {
+    reply: StudyReply,
+    deadline: Instant, // the handle has given up after this
+    loading: bool,
+    completed: bool,
+    study_updates: Vec<StudyUpdate>,
+}
+
+/// Follows `modify_study` requests through the message stream and answers them once the recompute is done.
+pub(crate) struct StudyModificationTracker {
+    pending: BTreeMap<(String, String), PendingStudyModification>,
+    timeout: Duration, // same as the handle's
+}
+
+impl StudyModificationTracker {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            timeout,
+        }
+    }
+
+    /// A newer modification of the same study replaces a pending one, whose recompute the server will not finish.
+    pub(crate) async fn start(&mut self, chart_session_id: &str, study_id: &str, reply: StudyReply) {
+        let key = (chart_session_id.to_string(), study_id.to_string());
+        let superseded = self.pending.insert(key, PendingStudyModification {
+            reply,
+            deadline: Instant::now() + self.timeout,
+            loading: false,
+            completed: false,
+            study_updates: vec![],
+        });
+        if let Some(superseded) = superseded {
+            superseded.reply.write().await.push(Err(format!("modify_study of {chart_session_id} {study_id} superseded by a newer one")));
+        }
+    }
+
+    /// Answers a modification whose request never reached the server.
+    pub(crate) async fn fail(&mut self, chart_session_id: &str, study_id: &str, err: String) {
+        if let Some(pending) = self.pending.remove(&(chart_session_id.to_string(), study_id.to_string())) {
+            pending.reply.write().await.push(Err(err));
+        }
+    }
+
+    pub(crate) async fn observe(&mut self, parsed_message: &ParsedTradingViewMessage) {
+        // nobody waits for modifications the handle timed out on
+        let now = Instant::now();
+        self.pending.retain(|(chart_session_id, study_id), pending| {
+            if pending.deadline <= now {
+                log::warn!("modify_study of {chart_session_id} {study_id} timed out");
+                return false;
+            }
+            true
+        });
+
+        let (key, result) = match parsed_message {
+            ParsedTradingViewMessage::StudyLoading(study_loading_message) => {
+                let key = (study_loading_message.chart_session_id.clone(), study_loading_message.study_id.clone());
+                if let Some(pending) = self.pending.get_mut(&key) {
+                    pending.loading = true;
+                    pending.study_updates.clear();
+                }
+                return;
+            }
+            ParsedTradingViewMessage::DataUpdate(data_update_message) => {
+                let key = (data_update_message.chart_session_id.clone(), data_update_message.update_key.clone());
+                match (self.pending.get_mut(&key), &data_update_message.study_updates) {
+                    (Some(pending), Some(study_updates)) if pending.loading => {
+                        pending.study_updates.extend(study_updates.iter().cloned());
+                        (key, None)
+                    }
+                    _ => return,
+                }
+            }
+            ParsedTradingViewMessage::StudyCompleted(study_completed_message) => {
+                let key = (study_completed_message.chart_session_id.clone(), study_completed_message.study_id.clone());
+                match self.pending.get_mut(&key) {
+                    Some(pending) if pending.loading => {
+                        pending.completed = true;
+                        (key, None)
+                    }
+                    _ => return,
+                }
+            }
+            ParsedTradingViewMessage::StudyError(study_error_message) => {
+                let key = (study_error_message.chart_session_id.clone(), study_error_message.study_id.clone());
+                let reason = study_error_message.reason.clone().unwrap_or_default();
+                (key, Some(Err(format!("modify_study failed: {reason}"))))
+            }
+            _ => return,
+        };
+
+        // the values can arrive on either side of study_completed
+        let result = match result {
+            Some(result) => Some(result),
+            None => match self.pending.get(&key) {
+                Some(pending) if pending.completed && !pending.study_updates.is_empty() => Some(Ok(pending.study_updates.clone())),
+                _ => None,
+            },
+        };
+        if let Some(result) = result {
+            if let Some(pending) = self.pending.remove(&key) {
+                pending.reply.write().await.push(result);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tradingview_common::{DataUpdateMessage, StudyCompletedMessage, StudyLoadingMessage};
+
+    use super::*;
+
+    fn study_update(chart_session_id: &str, value: f64) -> ParsedTradingViewMessage {
+        ParsedTradingViewMessage::DataUpdate(DataUpdateMessage {
+            chart_session_id: chart_session_id.to_string(),
+            update_key: "st2".to_string(),
+            series_updates: None,
+            study_updates: Some(vec![StudyUpdate {
+                study_id: "st2".to_string(),
+                index: 0,
+                values: vec![1700000000.0, value],
+                plots: vec![],
+            }]),
+            strategy_report: None,
+            graphics: None,
+            bar_close_time: None,
+        })
+    }
+
+    fn study_loading(chart_session_id: &str) -> ParsedTradingViewMessage {
+        ParsedTradingViewMessage::StudyLoading(StudyLoadingMessage {
+            chart_session_id: chart_session_id.to_string(),
+            study_id: "st2".to_string(),
+        })
+    }
+
+    fn study_completed(chart_session_id: &str) -> ParsedTradingViewMessage {
+        ParsedTradingViewMessage::StudyCompleted(StudyCompletedMessage {
+            chart_session_id: chart_session_id.to_string(),
+            study_id: "st2".to_string(),
+        })
+    }
+
+    #[test]
+    fn answers_with_the_recomputed_updates() {
+        async_io::block_on(async {
+            let mut tracker = StudyModificationTracker::new(Duration::from_secs(60));
+            let reply: StudyReply = Arc::new(RwLock::new(vec![]));
+            tracker.start("cs_000000000001", "st2", reply.clone()).await;
+            tracker.observe(&study_update("cs_000000000001", 1.0)).await; // before study_loading, old inputs
+            tracker.observe(&study_loading("cs_000000000001")).await;
+            tracker.observe(&study_update("cs_000000000002", 2.0)).await; // other chart
+            tracker.observe(&study_update("cs_000000000001", 3.0)).await;
+            assert!(reply.read().await.is_empty());
+            tracker.observe(&study_completed("cs_000000000001")).await;
+            let reply = reply.read().await;
+            let study_updates = reply[0].as_ref().unwrap();
+            assert_eq!(study_updates.iter().map(|study_update| study_update.values[1]).collect::<Vec<_>>(), vec![3.0]);
+            assert!(tracker.pending.is_empty());
+        });
+    }
+
+    #[test]
+    fn drops_timed_out_modifications() {
+        async_io::block_on(async {
+            let mut tracker = StudyModificationTracker::new(Duration::ZERO);
+            let reply: StudyReply = Arc::new(RwLock::new(vec![]));
+            tracker.start("cs_000000000001", "st2", reply.clone()).await;
+            tracker.observe(&ParsedTradingViewMessage::Ping(1)).await;
+            assert!(tracker.pending.is_empty());
+            assert!(reply.read().await.is_empty());
+        });
+    }
+
+    #[test]
+    fn answers_superseded_modifications() {
+        async_io::block_on(async {
+            let mut tracker = StudyModificationTracker::new(Duration::from_secs(60));
+            let first: StudyReply = Arc::new(RwLock::new(vec![]));
+            let second: StudyReply = Arc::new(RwLock::new(vec![]));
+            tracker.start("cs_000000000001", "st2", first.clone()).await;
+            tracker.start("cs_000000000001", "st2", second.clone()).await;
+            assert!(first.read().await[0].as_ref().is_err_and(|err| err.contains("superseded")));
+            tracker.observe(&study_loading("cs_000000000001")).await;
+            tracker.observe(&study_update("cs_000000000001", 3.0)).await;
+            tracker.observe(&study_completed("cs_000000000001")).await;
+            assert_eq!(first.read().await.len(), 1);
+            assert!(second.read().await[0].is_ok());
+        });
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+
+use tradingview_common::{DataUpdateMessage, MarketSession, QuoteSeriesDataMessage, SeriesUpdate, SessionCalendar, SymbolResolvedMessage, Timeframe, TimescaleUpdatedMessage};
+
+// late trades for a bar can arrive shortly after its close time
+const BAR_CLOSE_GRACE_SECS: u64 = 2;
+
+/// Higher level events the client derives from the raw message stream.
+#[derive(Debug, Clone)]
+pub enum TradingViewClientEvent {
+    /// The contract behind a continuous future (e.g. `CME_MINI:ES1!`) changed.
+    ContractRolled {
+        chart_session_id: String,
+        symbol: String,
+        previous_contract: String,
+        front_contract: String,
+    },
+    /// The forming bar of a chart is final; reported once per bar.
+    BarClosed {
+        chart_session_id: String,
+        bar: SeriesUpdate,
+    },
+    /// A quoted symbol's exchange moved between sessions (`current_session`), e.g. pre-market to regular.
+    MarketStatusChanged {
+        quote_session_id: String,
+        symbol: String,
+        previous_session: MarketSession,
+        market_session: MarketSession,
+    },
+}
+
+/// Remembers the front contract per chart session and reports when it changes.
+#[derive(Debug, Default)]
+pub struct ContractRollTracker {
+    front_contracts: BTreeMap<String, String>,
+}
+
+impl ContractRollTracker {
+    pub fn observe(&mut self, symbol_resolved_message: &SymbolResolvedMessage) -> Option<TradingViewClientEvent> {
+        let front_contract = symbol_resolved_message.front_contract.as_ref()?;
+        let chart_session_id = &symbol_resolved_message.chart_session_id;
+        let previous_contract = self.front_contracts.insert(chart_session_id.clone(), front_contract.clone())?;
+        if previous_contract == *front_contract {
+            return None;
+        }
+        Some(TradingViewClientEvent::ContractRolled {
+            chart_session_id: chart_session_id.clone(),
+            symbol: symbol_resolved_message.pro_name.clone().unwrap_or_default(),
+            previous_contract,
+            front_contract: front_contract.clone(),
+        })
+    }
+}
+
+/// Remembers the `current_session` per quoted symbol and reports when it changes.
+#[derive(Debug, Default)]
+pub struct MarketStatusTracker {
+    market_sessions: BTreeMap<(String, String), MarketSession>,
+}
+
+impl MarketStatusTracker {
+    pub fn observe(&mut self, quote_series_data_message: &QuoteSeriesDataMessage) -> Option<TradingViewClientEvent> {
+        let market_session = quote_series_data_message.quote_update.market_session()?;
+        let quote_session_id = &quote_series_data_message.quote_session_id;
+        let symbol = &quote_series_data_message.quote_update.symbol;
+        let previous_session = self.market_sessions.insert((quote_session_id.clone(), symbol.clone()), market_session)?;
+        if previous_session == market_session {
+            return None;
+        }
+        Some(TradingViewClientEvent::MarketStatusChanged {
+            quote_session_id: quote_session_id.clone(),
+            symbol: symbol.clone(),
+            previous_session,
+            market_session,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct ChartBars {
+    symbol: Option<String>,
+    calendar: Option<SessionCalendar>, // the chart's hours, for bars without lbs
+    forming: Option<SeriesUpdate>,
+    bar_close_time: Option<u64>, // lbs of the forming bar
+    last_closed_index: Option<u64>,
+}
+
+/// Decides when each chart's forming bar has closed: the next bar index shows up in a `du`, or the
+/// `lbs` bar close time (else the timeframe boundary) passed.
+#[derive(Debug)]
+pub struct BarCloseTracker {
+    timeframe: Option<Timeframe>,
+    charts: BTreeMap<String, ChartBars>,
+}
+
+impl BarCloseTracker {
+    pub fn new(timeframe: Option<Timeframe>) -> Self {
+        Self {
+            timeframe,
+            charts: BTreeMap::new(),
+        }
+    }
+
+    pub fn observe_symbol(&mut self, symbol_resolved_message: &SymbolResolvedMessage) {
+        let chart = self.charts.entry(symbol_resolved_message.chart_session_id.clone()).or_default();
+        chart.symbol = symbol_resolved_message.pro_name.clone();
+        chart.calendar = symbol_resolved_message.chart_calendar().ok();
+    }
+
+    /// Starts from the last bar of the history; everything before it is closed.
+    pub fn observe_history(&mut self, timescale_updated_message: &TimescaleUpdatedMessage) {
+        let last = match timescale_updated_message.updates.as_ref().and_then(|updates| updates.last()) {
+            Some(last) => last,
+            None => return,
+        };
+        let chart = self.charts.entry(timescale_updated_message.chart_session_id.clone()).or_default();
+        chart.forming = Some(last.clone());
+        chart.bar_close_time = None;
+        chart.last_closed_index = last.index.checked_sub(1);
+    }
+
+    pub fn observe(&mut self, data_update_message: &DataUpdateMessage, now: u64) -> Vec<TradingViewClientEvent> {
+        let mut events = vec![];
+        let series_updates = match &data_update_message.series_updates {
+            Some(series_updates) => series_updates,
+            None => return events,
+        };
+        let chart_session_id = &data_update_message.chart_session_id;
+        let chart = self.charts.entry(chart_session_id.clone()).or_default();
+        for series_update in series_updates {
+            if chart.forming.as_ref().is_some_and(|forming| series_update.index > forming.index) {
+                // next bar started, the forming one is final
+                events.extend(close_forming(chart_session_id, chart));
+            }
+            if chart.last_closed_index.is_some_and(|last_closed_index| series_update.index <= last_closed_index) {
+                // late corrections don't reopen a bar that was already reported
+                log::debug!("[{chart_session_id}] ignoring update for closed bar {}", series_update.index);
+                continue;
+            }
+            chart.forming = Some(series_update.clone());
+            chart.bar_close_time = data_update_message.bar_close_time;
+        }
+        events.extend(self.observe_time(now));
+        events
+    }
+
+    /// Closes bars whose close time passed without the next bar showing up (quiet symbols, session close).
+    pub fn observe_time(&mut self, now: u64) -> Vec<TradingViewClientEvent> {
+        let mut events = vec![];
+        for (chart_session_id, chart) in self.charts.iter_mut() {
+            let forming = match &chart.forming {
+                Some(forming) if chart.last_closed_index.is_none_or(|last_closed_index| forming.index > last_closed_index) => forming,
+                _ => continue,
+            };
+            let close_time = match (chart.bar_close_time, &self.timeframe, &chart.calendar) {
+                (Some(bar_close_time), _, _) => Some(bar_close_time),
+                (None, Some(timeframe), Some(calendar)) => timeframe.bar_end(forming.timestamp, calendar).ok().map(|bar_end| bar_end + 1),
+                (None, Some(timeframe), None) => timeframe.seconds().map(|seconds| forming.timestamp + seconds),
+                (None, None, _) => None,
+            };
+            if close_time.is_some_and(|close_time| now >= close_time + BAR_CLOSE_GRACE_SECS) {
+                events.extend(close_forming(chart_session_id, chart));
+            }
+        }
+        events
+    }
+}
+
+fn close_forming(chart_session_id: &str, chart: &mut ChartBars) -> Option<TradingViewClientEvent> {
+    let forming = chart.forming.as_ref()?;
+    if chart.last_closed_index.is_some_and(|last_closed_index| forming.index <= last_closed_index) {
+        return None;
+    }
+    chart.last_closed_index = Some(forming.index);
+    let mut bar = forming.clone();
+    bar.symbol = chart.symbol.clone();
+    Some(TradingViewClientEvent::BarClosed {
+        chart_session_id: chart_session_id.to_string(),
+        bar,
+    })
+}
@@ -0,0 +1,125 @@
+use futures_lite::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use http::Request;
+use simple_error::{box_err, SimpleResult};
+use tradingview_common::ProxyOptions;
+
+use crate::proxy;
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn text(&self) -> SimpleResult<String> {
+        Ok(String::from_utf8(self.body.clone())?)
+    }
+}
+
+/// Sends a single request over a fresh connection (`Connection: close`) and reads the whole response.
+pub async fn send_request(request: &Request<Vec<u8>>, proxy: Option<&ProxyOptions>) -> SimpleResult<HttpResponse> {
+    let uri = request.uri();
+    let host = uri.authority().ok_or(box_err!("request has no host"))?.to_string();
+    let mut stream = proxy::open_stream(uri, proxy).await?;
+
+    // write request
+    let target = uri.path_and_query().map(|path_and_query| path_and_query.as_str()).unwrap_or("/");
+    let mut head = format!("{} {target} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\nContent-Length: {}\r\n", request.method(), request.body().len());
+    for (name, value) in request.headers() {
+        head.push_str(&format!("{}: {}\r\n", name.as_str(), value.to_str()?));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(request.body()).await?;
+    stream.flush().await?;
+
+    // read response
+    let head = read_response_head(&mut stream).await?;
+    let status_code = parse_status_code(&head)?;
+    let chunked = find_header(&head, "transfer-encoding").map(|value| value.eq_ignore_ascii_case("chunked")).unwrap_or(false);
+    let content_length = find_header(&head, "content-length").and_then(|value| value.parse::<usize>().ok());
+    let body = if chunked {
+        read_chunked_body(&mut stream).await?
+    } else if let Some(content_length) = content_length {
+        let mut body = vec![0u8; content_length];
+        stream.read_exact(&mut body).await?;
+        body
+    } else {
+        let mut body = Vec::new();
+        match stream.read_to_end(&mut body).await {
+            Ok(_) => (),
+            // some servers skip tls close_notify
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => (),
+            Err(err) => return Err(err.into()),
+        }
+        body
+    };
+    Ok(HttpResponse {
+        status_code,
+        body,
+    })
+}
+
+async fn read_chunked_body<S: AsyncRead + Unpin>(stream: &mut S) -> SimpleResult<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        // chunk size line, extensions after ';' are ignored
+        let size_line = read_line(stream).await?;
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_hex, 16)?;
+        if size == 0 {
+            // skip trailers
+            while !read_line(stream).await?.is_empty() {}
+            return Ok(body);
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        stream.read_exact(&mut body[start..]).await?;
+        read_line(stream).await?;
+    }
+}
+
+async fn read_line<S: AsyncRead + Unpin>(stream: &mut S) -> SimpleResult<String> {
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+    while !line.ends_with(b"\r\n") {
+        if stream.read(&mut byte).await? == 0 {
+            return Err(box_err!("connection closed while reading chunked body"));
+        }
+        line.push(byte[0]);
+    }
+    line.truncate(line.len() - 2);
+    Ok(String::from_utf8(line)?)
+}
+
+/// Reads an HTTP response head (status line + headers) without consuming anything after the blank line.
+pub async fn read_response_head<S: AsyncRead + Unpin>(stream: &mut S) -> SimpleResult<String> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        let read = stream.read(&mut byte).await?;
+        if read == 0 {
+            return Err(box_err!("connection closed while reading response head"));
+        }
+        head.push(byte[0]);
+        if head.len() > 64 * 1024 {
+            return Err(box_err!("response head too large"));
+        }
+    }
+    Ok(String::from_utf8(head)?)
+}
+
+pub fn parse_status_code(head: &str) -> SimpleResult<u16> {
+    let status_line = head.lines().next().ok_or(box_err!("missing status line"))?;
+    let status_code = status_line.split(' ').nth(1).ok_or(box_err!("missing status code"))?;
+    Ok(status_code.parse()?)
+}
+
+pub fn find_header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .find(|(header_name, _)| header_name.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
@@ -1,15 +1,33 @@
 mod reader;
 mod writer;
+mod transport;
+mod http_utilities;
+mod proxy;
+mod websocket_handshake;
+mod capture;
+mod auth;
+mod pine_facade;
+mod replay;
 mod message_utilities;
 mod client;
+mod client_handle;
 mod client_utilities;
 mod message_processor;
+mod events;
 mod logging_message_processor;
 mod stateful_message_processor;
 
 pub use reader::*;
 pub use writer::*;
+pub use transport::*;
+pub use proxy::*;
+pub use capture::*;
+pub use auth::*;
+pub use pine_facade::*;
+pub use replay::*;
 pub use client::*;
+pub use client_handle::*;
 pub use message_processor::*;
+pub use events::*;
 pub use logging_message_processor::*;
 pub use stateful_message_processor::*;
@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use simple_error::SimpleResult;
 use tradingview_common::ParsedTradingViewMessage;
 
+use crate::events::TradingViewClientEvent;
 use crate::message_processor::TradingViewMessageProcessor;
 
 pub struct LoggingMessageProcessor;
@@ -88,4 +89,9 @@ impl TradingViewMessageProcessor for LoggingMessageProcessor {
         // return
         Ok(())
     }
+
+    async fn process_event(&mut self, name: String, event: TradingViewClientEvent) -> SimpleResult<()> {
+        log::info!("[{name}] event = {event:?}");
+        Ok(())
+    }
 }
@@ -2,7 +2,14 @@ use async_trait::async_trait;
 use simple_error::SimpleResult;
 use tradingview_common::ParsedTradingViewMessage;
 
+use crate::events::TradingViewClientEvent;
+
 #[async_trait]
 pub trait TradingViewMessageProcessor {
     async fn process_message(&mut self, name: String, message: ParsedTradingViewMessage) -> SimpleResult<()>;
+
+    /// Called after the message that triggered the event was processed.
+    async fn process_event(&mut self, _name: String, _event: TradingViewClientEvent) -> SimpleResult<()> {
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{sync::Arc, time::Duration};
 
 use async_io::Timer;
@@ -40,6 +41,31 @@ where
     }
 }
 
+pub async fn wait_for_message_until_closed<F, T>(
+    buffer: Arc<RwLock<Vec<T>>>,
+    closed: Arc<AtomicBool>,
+    condition: F,
+) -> Option<T>
+where
+    F: Fn(&T) -> bool,
+{
+    loop {
+        // check before scanning so a message pushed right before close is not lost
+        let is_closed = closed.load(Ordering::SeqCst);
+        let read_lock = buffer.read().await;
+        if let Some(index) = read_lock.iter().position(&condition) {
+            drop(read_lock);
+            let mut write_lock = buffer.write().await;
+            return Some(write_lock.remove(index));
+        }
+        drop(read_lock);
+        if is_closed {
+            return None;
+        }
+        Timer::after(Duration::from_millis(1)).await;
+    }
+}
+
 pub async fn wait_for_message_with_timeout<F, T>(
     duration: Duration,
     buffer: Arc<RwLock<Vec<T>>>,
@@ -0,0 +1,57 @@
+use http::{Method, Request};
+use simple_error::{box_err, SimpleResult};
+use tradingview_common::{PineScriptMetadata, ProxyOptions};
+
+use tradingview_common::utilities::url_encode;
+use crate::http_utilities;
+
+#[derive(Debug, Clone)]
+pub struct PineFacadeOptions {
+    pub base_url: String, // https://pine-facade.tradingview.com
+    pub proxy: Option<ProxyOptions>,
+}
+
+impl Default for PineFacadeOptions {
+    fn default() -> Self {
+        Self {
+            base_url: "https://pine-facade.tradingview.com".to_string(),
+            proxy: None,
+        }
+    }
+}
+
+/// Fetches script metadata (inputs, plots, compiled study) so `PineStudy` values need not be built by hand.
+pub struct PineFacadeClient {
+    options: PineFacadeOptions,
+}
+
+impl PineFacadeClient {
+    pub fn new(options: PineFacadeOptions) -> Self {
+        Self { options }
+    }
+
+    /// `pine_version` is a version like `1.0` or `last`.
+    pub async fn fetch_metadata(&self, pine_id: &str, pine_version: &str) -> SimpleResult<PineScriptMetadata> {
+        let mut metadata = PineScriptMetadata::from_translate_response(&self.fetch_translate_response(pine_id, pine_version).await?)?;
+        // "last" resolves to whatever the server reports, keep the requested one otherwise
+        if pine_version != "last" {
+            metadata.pine_version = pine_version.to_string();
+        }
+        Ok(metadata)
+    }
+
+    /// The raw translate body, for callers that keep it around (see `PineScriptMetadata::from_translate_response`).
+    pub async fn fetch_translate_response(&self, pine_id: &str, pine_version: &str) -> SimpleResult<String> {
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(format!("{}/pine-facade/translate/{}/{}", self.options.base_url, url_encode(pine_id), url_encode(pine_version)))
+            .header("Accept", "application/json")
+            .header("Origin", "https://www.tradingview.com")
+            .body(vec![])?;
+        let response = http_utilities::send_request(&request, self.options.proxy.as_ref()).await?;
+        if response.status_code != 200 {
+            return Err(box_err!(format!("pine-facade translate of {pine_id} failed with status {}", response.status_code)));
+        }
+        response.text()
+    }
+}
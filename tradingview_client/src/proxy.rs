@@ -0,0 +1,238 @@
+use async_net::TcpStream;
+use base64::Engine;
+use futures_lite::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use http::Uri;
+use simple_error::{box_err, SimpleResult};
+use tradingview_common::{ProxyKind, ProxyOptions};
+
+use crate::http_utilities;
+
+/// Byte stream produced once tcp, the optional proxy tunnel and optional tls are set up.
+pub trait NetworkStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> NetworkStream for S {}
+
+/// Opens a stream to the host of `uri`, optionally through `proxy`, with tls for `wss` / `https`.
+pub async fn open_stream(uri: &Uri, proxy: Option<&ProxyOptions>) -> SimpleResult<Box<dyn NetworkStream>> {
+    let host = uri.host().ok_or(box_err!("uri has no host"))?;
+    let secure = matches!(uri.scheme_str(), Some("wss") | Some("https"));
+    let port = uri.port_u16().unwrap_or(if secure { 443 } else { 80 });
+
+    // open tcp connection or tunnel
+    let stream = match proxy {
+        Some(proxy) => open_proxy_tunnel(proxy, host, port).await?,
+        None => TcpStream::connect((host, port)).await?,
+    };
+
+    // tls inside the tunnel
+    if secure {
+        Ok(Box::new(async_tls::TlsConnector::default().connect(host, stream).await?))
+    } else {
+        Ok(Box::new(stream))
+    }
+}
+
+/// Opens a TCP connection to `host:port` through the configured proxy.
+pub async fn open_proxy_tunnel(proxy: &ProxyOptions, host: &str, port: u16) -> SimpleResult<TcpStream> {
+    let mut stream = TcpStream::connect((proxy.host.as_str(), proxy.port)).await?;
+    match proxy.kind {
+        ProxyKind::HttpConnect => http_connect(&mut stream, proxy, host, port).await?,
+        ProxyKind::Socks5 => socks5_connect(&mut stream, proxy, host, port).await?,
+    }
+    log::debug!("proxy tunnel to {host}:{port} via {}:{} established", proxy.host, proxy.port);
+    Ok(stream)
+}
+
+async fn http_connect<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, proxy: &ProxyOptions, host: &str, port: u16) -> SimpleResult<()> {
+    // build CONNECT request
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if let Some(username) = &proxy.username {
+        let password = proxy.password.as_deref().unwrap_or("");
+        let credentials = base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+        request.push_str(&format!("Proxy-Authorization: Basic {credentials}\r\n"));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    // any 2xx means the tunnel is open
+    let head = http_utilities::read_response_head(stream).await?;
+    let status_code = http_utilities::parse_status_code(&head)?;
+    if !(200..300).contains(&status_code) {
+        return Err(box_err!(format!("proxy CONNECT failed with status {status_code}")));
+    }
+    Ok(())
+}
+
+async fn socks5_connect<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, proxy: &ProxyOptions, host: &str, port: u16) -> SimpleResult<()> {
+    // greeting: offer no auth, plus username/password when configured
+    let greeting: &[u8] = if proxy.username.is_some() { &[0x05, 0x02, 0x00, 0x02] } else { &[0x05, 0x01, 0x00] };
+    stream.write_all(greeting).await?;
+    let mut method = [0u8; 2];
+    stream.read_exact(&mut method).await?;
+    if method[0] != 0x05 {
+        return Err(box_err!("proxy is not a SOCKS5 server"));
+    }
+    match method[1] {
+        0x00 => (),
+        0x02 => {
+            let username = proxy.username.as_deref().ok_or(box_err!("SOCKS5 proxy requires a username"))?;
+            let password = proxy.password.as_deref().unwrap_or("");
+            if username.len() > 255 || password.len() > 255 {
+                return Err(box_err!("SOCKS5 credentials too long"));
+            }
+            let mut auth = vec![0x01, username.len() as u8];
+            auth.extend_from_slice(username.as_bytes());
+            auth.push(password.len() as u8);
+            auth.extend_from_slice(password.as_bytes());
+            stream.write_all(&auth).await?;
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status).await?;
+            if status[1] != 0x00 {
+                return Err(box_err!("SOCKS5 authentication failed"));
+            }
+        }
+        _ => return Err(box_err!("SOCKS5 proxy rejected all authentication methods")),
+    }
+
+    // connect by domain name so dns is resolved on the proxy side
+    if host.len() > 255 {
+        return Err(box_err!("SOCKS5 host too long"));
+    }
+    let mut request = vec![0x05, 0x01, 0x00, 0x03, host.len() as u8];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    // reply: version, status, reserved, bound address type + address + port
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(box_err!(format!("SOCKS5 connect failed with status {}", reply[1])));
+    }
+    let address_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            len[0] as usize
+        }
+        address_type => return Err(box_err!(format!("SOCKS5 reply has unknown address type {address_type}"))),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    use super::*;
+
+    // one connection proxy on a loopback port, returns what the client sent
+    fn fake_proxy(serve: fn(&mut std::net::TcpStream) -> Vec<u8>) -> (u16, JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            serve(&mut stream)
+        });
+        (port, handle)
+    }
+
+    fn proxy_options(kind: ProxyKind, port: u16, username: Option<&str>) -> ProxyOptions {
+        ProxyOptions {
+            kind,
+            host: "127.0.0.1".to_string(),
+            port,
+            username: username.map(str::to_string),
+            password: username.map(|_| "secret".to_string()),
+        }
+    }
+
+    fn read_head(stream: &mut std::net::TcpStream) -> Vec<u8> {
+        let mut head = vec![];
+        let mut byte = [0u8; 1];
+        while !head.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            head.push(byte[0]);
+        }
+        head
+    }
+
+    fn read_n(stream: &mut std::net::TcpStream, len: usize) -> Vec<u8> {
+        let mut buffer = vec![0u8; len];
+        stream.read_exact(&mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn http_connect_sends_credentials() {
+        let (port, proxy) = fake_proxy(|stream| {
+            let head = read_head(stream);
+            stream.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").unwrap();
+            head
+        });
+        let options = proxy_options(ProxyKind::HttpConnect, port, Some("user"));
+        async_io::block_on(open_proxy_tunnel(&options, "data.tradingview.com", 443)).unwrap();
+        let head = String::from_utf8(proxy.join().unwrap()).unwrap();
+        assert!(head.starts_with("CONNECT data.tradingview.com:443 HTTP/1.1\r\n"));
+        // base64("user:secret")
+        assert!(head.contains("Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n"));
+    }
+
+    #[test]
+    fn http_connect_fails_on_non_2xx() {
+        let (port, proxy) = fake_proxy(|stream| {
+            let head = read_head(stream);
+            stream.write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n").unwrap();
+            head
+        });
+        let options = proxy_options(ProxyKind::HttpConnect, port, None);
+        assert!(async_io::block_on(open_proxy_tunnel(&options, "data.tradingview.com", 443)).is_err());
+        assert!(!String::from_utf8(proxy.join().unwrap()).unwrap().contains("Proxy-Authorization"));
+    }
+
+    #[test]
+    fn socks5_authenticates_and_connects_by_domain() {
+        let (port, proxy) = fake_proxy(|stream| {
+            let mut sent = read_n(stream, 4);
+            stream.write_all(&[0x05, 0x02]).unwrap();
+            // version, username, password
+            sent.extend(read_n(stream, 2 + 4 + 1 + 6));
+            stream.write_all(&[0x01, 0x00]).unwrap();
+            // connect request with a 20 byte domain
+            sent.extend(read_n(stream, 5 + 20 + 2));
+            stream.write_all(&[0x05, 0x00, 0x00, 0x01, 127, 0, 0, 1, 0x01, 0xbb]).unwrap();
+            sent
+        });
+        let options = proxy_options(ProxyKind::Socks5, port, Some("user"));
+        async_io::block_on(open_proxy_tunnel(&options, "data.tradingview.com", 443)).unwrap();
+        let sent = proxy.join().unwrap();
+        assert_eq!(&sent[0..4], &[0x05, 0x02, 0x00, 0x02]);
+        assert_eq!(&sent[4..17], b"\x01\x04user\x06secret");
+        assert_eq!(&sent[17..22], &[0x05, 0x01, 0x00, 0x03, 20]);
+        assert_eq!(&sent[22..42], b"data.tradingview.com");
+        assert_eq!(&sent[42..44], &443u16.to_be_bytes());
+    }
+
+    #[test]
+    fn socks5_reports_connect_failures() {
+        let (port, proxy) = fake_proxy(|stream| {
+            let sent = read_n(stream, 3);
+            stream.write_all(&[0x05, 0x00]).unwrap();
+            read_n(stream, 5 + 20 + 2);
+            // connection refused
+            stream.write_all(&[0x05, 0x05, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).unwrap();
+            sent
+        });
+        let options = proxy_options(ProxyKind::Socks5, port, None);
+        let err = async_io::block_on(open_proxy_tunnel(&options, "data.tradingview.com", 443)).unwrap_err();
+        assert!(err.to_string().contains("status 5"));
+        assert_eq!(proxy.join().unwrap(), vec![0x05, 0x01, 0x00]);
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use async_io::Timer;
+use async_trait::async_trait;
+use simple_error::{box_err, SimpleResult};
+use tradingview_codec::TradingViewMessageWrapper;
+use tradingview_common::{Clock, ParsedTradingViewMessage};
+
+use crate::capture::CapturedMessage;
+use crate::transport::{TradingViewMessageSink, TradingViewMessageSource};
+
+#[derive(Debug, Clone, Copy)]
+pub enum ReplaySpeed {
+    RealTime,
+    Accelerated(f64),
+    AsFastAsPossible,
+}
+
+impl ReplaySpeed {
+    /// Parses `realtime`, `max` or a multiplier such as `10x`.
+    pub fn from_string(value: &str) -> SimpleResult<Self> {
+        match value {
+            "realtime" | "1x" => Ok(ReplaySpeed::RealTime),
+            "max" => Ok(ReplaySpeed::AsFastAsPossible),
+            _ => {
+                let multiplier = value.strip_suffix('x').ok_or(box_err!("invalid replay speed"))?;
+                let multiplier = multiplier.parse::<f64>().map_err(|_| box_err!("invalid replay speed"))?;
+                if multiplier <= 0.0 {
+                    return Err(box_err!("replay speed must be positive"));
+                }
+                Ok(ReplaySpeed::Accelerated(multiplier))
+            }
+        }
+    }
+
+    fn scale(&self, elapsed_millis: u64) -> Option<Duration> {
+        match self {
+            ReplaySpeed::RealTime => Some(Duration::from_millis(elapsed_millis)),
+            ReplaySpeed::Accelerated(multiplier) => Some(Duration::from_secs_f64(elapsed_millis as f64 / 1000.0 / multiplier)),
+            ReplaySpeed::AsFastAsPossible => None,
+        }
+    }
+}
+
+/// Feeds a capture file back as if it were arriving from the websocket.
+///
+/// `clock` follows the capture timestamps while replaying; `TradingViewClient::replay` puts it back on the wall
+/// clock when the replay ends.
+pub struct TradingViewReplayReader {
+    messages: VecDeque<CapturedMessage>,
+    speed: ReplaySpeed,
+    clock: Clock,
+    last_timestamp: Option<u64>,
+}
+
+impl TradingViewReplayReader {
+    pub async fn open(path: &str, speed: ReplaySpeed, clock: Clock) -> SimpleResult<Self> {
+        let contents = async_fs::read_to_string(path).await?;
+        let mut messages = VecDeque::new();
+        for line in contents.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let captured_message: CapturedMessage = miniserde::json::from_str(line)?;
+            messages.push_back(captured_message);
+        }
+        log::info!("loaded {} captured messages from {path}", messages.len());
+        Ok(Self {
+            messages,
+            speed,
+            clock,
+            last_timestamp: None,
+        })
+    }
+}
+
+#[async_trait]
+impl TradingViewMessageSource for TradingViewReplayReader {
+    async fn read_message(&mut self) -> SimpleResult<Option<TradingViewMessageWrapper>> {
+        let captured_message = match self.messages.pop_front() {
+            Some(captured_message) => captured_message,
+            None => return Ok(None),
+        };
+
+        // pace relative to the previous message
+        if let Some(last_timestamp) = self.last_timestamp {
+            let elapsed_millis = captured_message.timestamp.saturating_sub(last_timestamp);
+            if let Some(delay) = self.speed.scale(elapsed_millis) {
+                Timer::after(delay).await;
+            }
+        }
+        self.last_timestamp = Some(captured_message.timestamp);
+        self.clock.set_replay_time(Some(captured_message.timestamp));
+
+        let parsed_message = ParsedTradingViewMessage::from_string(&captured_message.payload)?;
+        Ok(Some(TradingViewMessageWrapper {
+            payload: captured_message.payload,
+            parsed_message,
+        }))
+    }
+}
+
+/// Puts a replay's clock back on the wall clock when dropped, also when the replay fails.
+pub(crate) struct ReplayClockGuard {
+    clock: Clock,
+}
+
+impl ReplayClockGuard {
+    pub(crate) fn new(clock: Clock) -> Self {
+        Self { clock }
+    }
+}
+
+impl Drop for ReplayClockGuard {
+    fn drop(&mut self) {
+        self.clock.set_replay_time(None);
+    }
+}
+
+/// Swallows outbound messages during a replay; there is nobody on the other end.
+#[derive(Default)]
+pub struct TradingViewReplaySink;
+
+#[async_trait]
+impl TradingViewMessageSink for TradingViewReplaySink {
+    async fn write_text_message(&mut self, message: &str) -> SimpleResult<()> {
+        log::trace!("replay: dropping outbound message {message}");
+        Ok(())
+    }
+
+    async fn write_close_message(&mut self) -> SimpleResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::TradingViewCaptureWriter;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("tradingview_{name}_{}.jsonl", std::process::id()));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn replay_speed_parses_multipliers() {
+        assert!(matches!(ReplaySpeed::from_string("realtime").unwrap(), ReplaySpeed::RealTime));
+        assert!(matches!(ReplaySpeed::from_string("max").unwrap(), ReplaySpeed::AsFastAsPossible));
+        assert_eq!(ReplaySpeed::from_string("10x").unwrap().scale(5000), Some(Duration::from_millis(500)));
+        assert_eq!(ReplaySpeed::AsFastAsPossible.scale(5000), None);
+        assert!(ReplaySpeed::from_string("0x").is_err());
+        assert!(ReplaySpeed::from_string("fast").is_err());
+    }
+
+    #[test]
+    fn capture_round_trips_through_replay() {
+        let path = temp_path("round_trip");
+        let payloads = ["~h~1", r#"{"m":"quote_completed","p":["qs_1","BINANCE:BTCUSDT"]}"#, "~h~2"];
+        async_io::block_on(async {
+            let mut writer = TradingViewCaptureWriter::create(&path).await.unwrap();
+            for payload in payloads {
+                let parsed_message = ParsedTradingViewMessage::from_string(payload).unwrap();
+                writer.write_message(&TradingViewMessageWrapper { payload: payload.to_string(), parsed_message }).await.unwrap();
+            }
+
+            let clock = Clock::default();
+            let mut reader = TradingViewReplayReader::open(&path, ReplaySpeed::AsFastAsPossible, clock.clone()).await.unwrap();
+            for payload in payloads {
+                let message = reader.read_message().await.unwrap().unwrap();
+                assert_eq!(message.payload, payload);
+            }
+            assert!(reader.read_message().await.unwrap().is_none());
+        });
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn replay_moves_the_clock_until_the_guard_drops() {
+        let path = temp_path("clock");
+        std::fs::write(&path, "{\"timestamp\":1700000000000,\"payload\":\"~h~1\"}\n\n{\"timestamp\":1700000001500,\"payload\":\"~h~2\"}\n").unwrap();
+        let clock = Clock::default();
+        async_io::block_on(async {
+            let guard = ReplayClockGuard::new(clock.clone());
+            let mut reader = TradingViewReplayReader::open(&path, ReplaySpeed::AsFastAsPossible, clock.clone()).await.unwrap();
+            reader.read_message().await.unwrap().unwrap();
+            assert_eq!(clock.now().unwrap(), 1_700_000_000);
+            reader.read_message().await.unwrap().unwrap();
+            assert_eq!(clock.now_millis().unwrap(), 1_700_000_001_500);
+            drop(guard);
+        });
+        assert!(clock.now().unwrap() > 1_700_000_001);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,9 +1,15 @@
+use std::collections::{BTreeMap, VecDeque};
+
 use async_trait::async_trait;
 use simple_error::SimpleResult;
-use tradingview_common::{ParsedTradingViewMessage, QuoteSeriesDataUpdate, SeriesUpdate, StudyUpdate, TimescaleUpdate};
+use tradingview_common::{BarHistory, Clock, DataFreshness, DEFAULT_BAR_HISTORY_CAPACITY, ParsedTradingViewMessage, QuoteSeriesDataUpdate, SeriesUpdate, StrategyReport, StudyGraphics, StudyUpdate, SymbolResolvedMessage, TimescaleUpdate};
 
+use crate::events::TradingViewClientEvent;
 use crate::message_processor::TradingViewMessageProcessor;
 
+// closed bars kept for take_closed_bars when nobody takes them
+const MAX_CLOSED_BARS: usize = 1024;
+
 #[derive(Debug)]
 pub struct StatefulMessageProcessor {
     // quote
@@ -15,10 +21,30 @@ pub struct StatefulMessageProcessor {
     // study updates
     pub study_update: Option<StudyUpdate>,
 
+    // latest backtest of each pine strategy study, by (chart session id, study id)
+    pub strategy_reports: BTreeMap<(String, String), StrategyReport>,
+
+    // labels, lines, boxes, ... currently drawn, by (chart session id, study id) as study ids repeat across charts
+    pub study_graphics: BTreeMap<(String, String), StudyGraphics>,
+
     // series update
     pub series_update: Option<SeriesUpdate>,
 
+    // final bars from BarClosed events as (chart session id, bar), oldest first
+    pub closed_bars: VecDeque<(String, SeriesUpdate)>,
+
+    // backfill + live bars, by chart session id
+    pub bar_history: BTreeMap<String, BarHistory>,
+    pub bar_history_capacity: usize,
+
+    // realtime vs delayed, from series_completed, by chart session id
+    pub series_freshness: BTreeMap<String, DataFreshness>,
+
+    // exchange timezone + session, from symbol_resolved, by chart session id
+    pub symbol_info: BTreeMap<String, SymbolResolvedMessage>,
+
     // time
+    pub clock: Clock,
     pub last_quote_update: Option<u64>,
     pub last_timescale_update: Option<u64>,
     pub last_series_update: Option<u64>,
@@ -37,10 +63,30 @@ impl Default for StatefulMessageProcessor {
         // study updates
         study_update: None,
 
+        // strategy reports
+        strategy_reports: BTreeMap::new(),
+
+        // study graphics
+        study_graphics: BTreeMap::new(),
+
         // series updates
         series_update: None,
 
+        // closed bars
+        closed_bars: VecDeque::new(),
+
+        // bar history
+        bar_history: BTreeMap::new(),
+        bar_history_capacity: DEFAULT_BAR_HISTORY_CAPACITY,
+
+        // freshness
+        series_freshness: BTreeMap::new(),
+
+        // symbol info
+        symbol_info: BTreeMap::new(),
+
         // time
+        clock: Clock::default(),
         last_quote_update: None,
         last_timescale_update: None,          
         last_series_update: None,
@@ -49,6 +95,31 @@ impl Default for StatefulMessageProcessor {
     }
 }
 
+impl StatefulMessageProcessor {
+    /// Bars kept per series in `bar_history`.
+    pub fn with_bar_history_capacity(self, bar_history_capacity: usize) -> Self {
+        Self { bar_history_capacity, ..self }
+    }
+
+    /// Stamps `last_*_update` with the client's clock (`TradingViewClient::clock`), so replays keep capture time.
+    pub fn with_clock(self, clock: Clock) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// Closed bars since the last call, one per bar, with the chart session they belong to.
+    pub fn take_closed_bars(&mut self) -> Vec<(String, SeriesUpdate)> {
+        self.closed_bars.drain(..).collect()
+    }
+
+    fn symbol(&self, chart_session_id: &str) -> Option<String> {
+        self.symbol_info.get(chart_session_id).and_then(|symbol_info| symbol_info.pro_name.clone())
+    }
+
+    fn freshness(&self, chart_session_id: &str) -> Option<DataFreshness> {
+        self.series_freshness.get(chart_session_id).cloned()
+    }
+}
+
 #[async_trait]
 impl TradingViewMessageProcessor for StatefulMessageProcessor {
     async fn process_message(
@@ -105,23 +176,56 @@ impl TradingViewMessageProcessor for StatefulMessageProcessor {
                   if let Some(bid_size) = quote_series_data_message.quote_update.bid_size {
                     quote_update.bid_size = Some(bid_size);
                   }
+                  if let Some(update_mode) = &quote_series_data_message.quote_update.update_mode {
+                    quote_update.update_mode = Some(update_mode.clone());
+                  }
+                  if let Some(current_session) = &quote_series_data_message.quote_update.current_session {
+                    quote_update.current_session = Some(current_session.clone());
+                  }
+                  if let Some(pricescale) = quote_series_data_message.quote_update.pricescale {
+                    quote_update.pricescale = Some(pricescale);
+                  }
+                  if let Some(minmov) = quote_series_data_message.quote_update.minmov {
+                    quote_update.minmov = Some(minmov);
+                  }
+                  if let Some(minmove2) = quote_series_data_message.quote_update.minmove2 {
+                    quote_update.minmove2 = Some(minmove2);
+                  }
+                  if let Some(fractional) = quote_series_data_message.quote_update.fractional {
+                    quote_update.fractional = Some(fractional);
+                  }
                 },
                 
               }
 
-              self.last_quote_update = Some(tradingview_common::utilities::now()?);
+              self.last_quote_update = Some(self.clock.now()?);
           },
           ParsedTradingViewMessage::TimescaleUpdate(timescale_update_message) => {
             // timescale updates
             match &timescale_update_message.updates {
               Some(updates) => {
+                // backfill
+                let symbol = self.symbol(&timescale_update_message.chart_session_id);
+                let freshness = self.freshness(&timescale_update_message.chart_session_id);
+                let bar_history = self.bar_history
+                  .entry(timescale_update_message.chart_session_id.clone())
+                  .or_insert_with(|| BarHistory::new(self.bar_history_capacity));
+                for update in updates {
+                  let mut series_update = update.clone();
+                  series_update.freshness = freshness.clone();
+                  series_update.symbol = symbol.clone();
+                  bar_history.merge(series_update);
+                }
+
                 if updates.len() == 0 {
                   log::warn!("empty timescale updates?");
                 } else if updates.len() == 1 {
-                  self.timescale_update = Some(updates[0].clone());
-                  self.last_timescale_update = Some(tradingview_common::utilities::now()?);
+                  let mut timescale_update = updates[0].clone();
+                  timescale_update.freshness = freshness;
+                  self.timescale_update = Some(timescale_update);
+                  self.last_timescale_update = Some(self.clock.now()?);
                 } else {
-                  log::warn!("multiple timescale updates?");
+                  log::debug!("{} timescale updates, kept in bar history", updates.len());
                 }
               },
               None => {
@@ -137,7 +241,7 @@ impl TradingViewMessageProcessor for StatefulMessageProcessor {
                   log::warn!("empty study updates?");
                 } else if study_updates.len() == 1 {
                   self.study_update = Some(study_updates[0].clone());
-                  self.last_study_update = Some(tradingview_common::utilities::now()?);
+                  self.last_study_update = Some(self.clock.now()?);
                 } else {
                   log::warn!("multiple study updates?");
                 }
@@ -145,14 +249,41 @@ impl TradingViewMessageProcessor for StatefulMessageProcessor {
               None => ()
             }
 
+            // strategy report (pine strategies)
+            if let Some(strategy_report) = &data_update_message.strategy_report {
+              let key = (data_update_message.chart_session_id.clone(), data_update_message.update_key.clone());
+              self.strategy_reports.insert(key, strategy_report.as_ref().clone());
+            }
+
+            // drawings (label.new, box.new, ...)
+            if let Some(graphics) = &data_update_message.graphics {
+              let key = (data_update_message.chart_session_id.clone(), data_update_message.update_key.clone());
+              self.study_graphics.entry(key).or_default().apply(graphics);
+            }
+
             // series updates (candles)
             match &data_update_message.series_updates {
               Some(series_updates) => {
+                let symbol = self.symbol(&data_update_message.chart_session_id);
+                let freshness = self.freshness(&data_update_message.chart_session_id);
+                let bar_history = self.bar_history
+                  .entry(data_update_message.chart_session_id.clone())
+                  .or_insert_with(|| BarHistory::new(self.bar_history_capacity));
+                for series_update in series_updates {
+                  let mut series_update = series_update.clone();
+                  series_update.freshness = freshness.clone();
+                  series_update.symbol = symbol.clone();
+                  bar_history.merge(series_update);
+                }
+
                 if series_updates.len() == 0 {
                   log::warn!("empty study updates?");
                 } else if series_updates.len() == 1 {
-                  self.series_update = Some(series_updates[0].clone());
-                  self.last_series_update = Some(tradingview_common::utilities::now()?);
+                  let mut series_update = series_updates[0].clone();
+                  series_update.freshness = freshness;
+                  series_update.symbol = symbol;
+                  self.series_update = Some(series_update);
+                  self.last_series_update = Some(self.clock.now()?);
                 } else {
                   log::warn!("multiple series updates?");
                 }
@@ -160,6 +291,16 @@ impl TradingViewMessageProcessor for StatefulMessageProcessor {
               None => ()
             }
           }
+          ParsedTradingViewMessage::SymbolResolved(symbol_resolved_message) => {
+            self.symbol_info.insert(symbol_resolved_message.chart_session_id.clone(), symbol_resolved_message.clone());
+          }
+          ParsedTradingViewMessage::SeriesCompleted(series_completed_message) => {
+            let chart_session_id = series_completed_message.chart_session_id.clone();
+            match series_completed_message.freshness() {
+              Some(freshness) => { self.series_freshness.insert(chart_session_id, freshness); },
+              None => { self.series_freshness.remove(&chart_session_id); },
+            }
+          }
           _ => {
               log::info!("unknown_message = {:?}", parsed_message);
           }
@@ -167,4 +308,72 @@ impl TradingViewMessageProcessor for StatefulMessageProcessor {
 
         Ok(())
     }
+
+    async fn process_event(&mut self, _name: String, event: TradingViewClientEvent) -> SimpleResult<()> {
+        if let TradingViewClientEvent::BarClosed { chart_session_id, mut bar } = event {
+            bar.freshness = self.freshness(&chart_session_id);
+            if self.closed_bars.len() == MAX_CLOSED_BARS {
+                log::warn!("closed bars are not being taken, dropping the oldest");
+                self.closed_bars.pop_front();
+            }
+            self.closed_bars.push_back((chart_session_id, bar));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tradingview_common::{Bar, UpdateMode};
+
+    use super::*;
+
+    async fn process(message_processor: &mut StatefulMessageProcessor, message: &str) {
+        let parsed_message = ParsedTradingViewMessage::from_string(message).unwrap();
+        message_processor.process_message("test".to_string(), parsed_message).await.unwrap();
+    }
+
+    #[test]
+    fn labels_bars_with_their_chart_symbol() {
+        async_io::block_on(async {
+            let mut message_processor = StatefulMessageProcessor::default();
+            process(&mut message_processor, r#"{"m":"symbol_resolved","p":["cs_000000000001","sds_sym_1",{"pro_name":"AMEX:SPY","timezone":"America/New_York"}]}"#).await;
+            process(&mut message_processor, r#"{"m":"symbol_resolved","p":["cs_000000000002","sds_sym_1",{"pro_name":"NASDAQ:QQQ","timezone":"America/New_York"}]}"#).await;
+            process(&mut message_processor, r#"{"m":"du","p":["cs_000000000001",{"sds_1":{"s":[{"i":0,"v":[1700000000,1.0,2.0,0.5,1.5,100]}]}}]}"#).await;
+            process(&mut message_processor, r#"{"m":"du","p":["cs_000000000002",{"sds_1":{"s":[{"i":0,"v":[1700000000,3.0,4.0,2.5,3.5,200]}]}}]}"#).await;
+            let symbol = |chart_session_id: &str| message_processor.bar_history[chart_session_id].back(0).and_then(|bar| bar.symbol.clone());
+            assert_eq!(symbol("cs_000000000001").as_deref(), Some("AMEX:SPY"));
+            assert_eq!(symbol("cs_000000000002").as_deref(), Some("NASDAQ:QQQ"));
+            assert_eq!(message_processor.series_update.as_ref().and_then(|bar| bar.symbol.as_deref()), Some("NASDAQ:QQQ"));
+        });
+    }
+
+    #[test]
+    fn keeps_freshness_per_chart() {
+        async_io::block_on(async {
+            let mut message_processor = StatefulMessageProcessor::default();
+            process(&mut message_processor, r#"{"m":"series_completed","p":["cs_000000000001","sds_1","streaming","s1"]}"#).await;
+            process(&mut message_processor, r#"{"m":"series_completed","p":["cs_000000000002","sds_1","delayed_streaming_900","s1"]}"#).await;
+            process(&mut message_processor, r#"{"m":"du","p":["cs_000000000001",{"sds_1":{"s":[{"i":0,"v":[1700000000,1.0,2.0,0.5,1.5,100]}]}}]}"#).await;
+            let update_mode = |chart_session_id: &str| message_processor.series_freshness.get(chart_session_id).map(|freshness| freshness.update_mode);
+            assert_eq!(update_mode("cs_000000000001"), Some(UpdateMode::Streaming));
+            assert_eq!(update_mode("cs_000000000002"), Some(UpdateMode::DelayedStreaming));
+            let bar = message_processor.bar_history["cs_000000000001"].back(0).unwrap();
+            assert_eq!(bar.freshness.as_ref().map(|freshness| freshness.update_mode), Some(UpdateMode::Streaming));
+        });
+    }
+
+    #[test]
+    fn keeps_the_chart_of_closed_bars() {
+        async_io::block_on(async {
+            let mut message_processor = StatefulMessageProcessor::default();
+            let bar = Bar::from_value(&miniserde::json::from_str(r#"{"i":0,"v":[1700000000,1.0,2.0,0.5,1.5]}"#).unwrap()).unwrap();
+            let event = TradingViewClientEvent::BarClosed { chart_session_id: "cs_000000000002".to_string(), bar };
+            message_processor.process_event("test".to_string(), event).await.unwrap();
+            let closed_bars = message_processor.take_closed_bars();
+            assert_eq!(closed_bars.len(), 1);
+            assert_eq!(closed_bars[0].0, "cs_000000000002");
+            assert!(message_processor.take_closed_bars().is_empty());
+        });
+    }
 }
@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use futures_lite::io::{AsyncRead, AsyncWrite};
+use simple_error::SimpleResult;
+use tradingview_codec::TradingViewMessageWrapper;
+use websocket_client::WebSocketWriter;
+
+/// Source of inbound TradingView messages (live websocket or replayed capture).
+#[async_trait]
+pub trait TradingViewMessageSource {
+    async fn read_message(&mut self) -> SimpleResult<Option<TradingViewMessageWrapper>>;
+}
+
+/// Sink for outbound framed TradingView messages.
+#[async_trait]
+pub trait TradingViewMessageSink {
+    async fn write_text_message(&mut self, message: &str) -> SimpleResult<()>;
+    async fn write_close_message(&mut self) -> SimpleResult<()>;
+}
+
+#[async_trait]
+impl<W> TradingViewMessageSink for WebSocketWriter<W>
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    async fn write_text_message(&mut self, message: &str) -> SimpleResult<()> {
+        WebSocketWriter::write_text_message(self, message).await
+    }
+
+    async fn write_close_message(&mut self) -> SimpleResult<()> {
+        WebSocketWriter::write_close_message(self).await
+    }
+}
+
+#[async_trait]
+impl<R> TradingViewMessageSource for crate::reader::TradingViewReader<R>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    async fn read_message(&mut self) -> SimpleResult<Option<TradingViewMessageWrapper>> {
+        crate::reader::TradingViewReader::read_message(self).await
+    }
+}
@@ -0,0 +1,41 @@
+use base64::Engine;
+use futures_lite::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use http::Request;
+use sha1::{Digest, Sha1};
+use simple_error::{box_err, SimpleResult};
+
+use crate::http_utilities;
+
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Performs the websocket upgrade over an already open (and possibly tunneled / encrypted) stream.
+pub async fn upgrade<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, request: &Request<Vec<u8>>) -> SimpleResult<()> {
+    // random 16 byte key
+    let key_bytes: Vec<u8> = (0..16).map(|_| fastrand::u8(..)).collect();
+    let key = base64::engine::general_purpose::STANDARD.encode(key_bytes);
+
+    // build upgrade request
+    let target = request.uri().path_and_query().map(|path_and_query| path_and_query.as_str()).unwrap_or("/");
+    let mut head = format!("GET {target} HTTP/1.1\r\n");
+    for (name, value) in request.headers() {
+        head.push_str(&format!("{}: {}\r\n", name.as_str(), value.to_str()?));
+    }
+    head.push_str(&format!("Upgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n"));
+    stream.write_all(head.as_bytes()).await?;
+    stream.flush().await?;
+
+    // expect 101 with matching accept key
+    let response = http_utilities::read_response_head(stream).await?;
+    let status_code = http_utilities::parse_status_code(&response)?;
+    if status_code != 101 {
+        return Err(box_err!(format!("websocket upgrade failed with status {status_code}")));
+    }
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    let expected_accept = base64::engine::general_purpose::STANDARD.encode(hasher.finalize());
+    match http_utilities::find_header(&response, "sec-websocket-accept") {
+        Some(accept) if accept == expected_accept => Ok(()),
+        _ => Err(box_err!("websocket upgrade returned an invalid Sec-WebSocket-Accept")),
+    }
+}
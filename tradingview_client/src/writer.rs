@@ -1,34 +1,34 @@
-use futures_lite::io::AsyncWrite;
 use simple_error::SimpleResult;
 use tradingview_codec::TradingViewMessageWrapper;
-use websocket_client::WebSocketWriter;
+
+use crate::transport::TradingViewMessageSink;
 
 /// TradingViewWriter handles writing TradingView messages.
-pub struct TradingViewWriter<W>
+pub struct TradingViewWriter<S>
 where
-    W: AsyncWrite + Unpin,
+    S: TradingViewMessageSink + Send,
 {
-    ws_writer: WebSocketWriter<W>,
+    sink: S,
 }
 
-impl<W> TradingViewWriter<W>
+impl<S> TradingViewWriter<S>
 where
-    W: AsyncWrite + Unpin,
+    S: TradingViewMessageSink + Send,
 {
-    /// Creates a new `TradingViewWriter` with the given `WebSocketWriter`.
-    pub fn new(ws_writer: WebSocketWriter<W>) -> Self {
-        Self { ws_writer }
+    /// Creates a new `TradingViewWriter` with the given sink (usually a `WebSocketWriter`).
+    pub fn new(sink: S) -> Self {
+        Self { sink }
     }
 
     /// Writes a message to the TradingView server.
     pub async fn write_message(&mut self, message: &str) -> SimpleResult<()> {
         let tv_message = TradingViewMessageWrapper::serialize(message);
         log::debug!("write_message: tv_message = {tv_message}");
-        self.ws_writer.write_text_message(&tv_message).await
+        self.sink.write_text_message(&tv_message).await
     }
 
     pub async fn close(&mut self) -> SimpleResult<()> {
-        self.ws_writer.write_close_message().await
+        self.sink.write_close_message().await
     }
 
     pub async fn set_auth_token(&mut self, auth_token: &str) -> SimpleResult<()> {
@@ -178,12 +178,13 @@ where
         self.write_message(&message).await
     }
 
+    /// `parent_id` is the series (`sds_1`) or, for a study applied to another study's output, that study's id.
     pub async fn create_study(
         &mut self,
         chart_session_id: &str,
         study_id: &str,
         session_id: &str,
-        series_id: &str,
+        parent_id: &str,
         name: &str,
         value: &str,
     ) -> SimpleResult<()> {
@@ -194,7 +195,7 @@ where
                     "{chart_session_id}",
                     "{study_id}",
                     "{session_id}",
-                    "{series_id}",
+                    "{parent_id}",
                     "{name}",
                     {value}
                 ]
@@ -203,6 +204,42 @@ where
         self.write_message(&message).await
     }
 
+    /// Replaces a study's inputs in place; the server answers with `study_loading`, the recomputed values and `study_completed`.
+    pub async fn modify_study(
+        &mut self,
+        chart_session_id: &str,
+        study_id: &str,
+        session_id: &str,
+        value: &str,
+    ) -> SimpleResult<()> {
+        let message = format!(
+            r#"{{
+                "m":"modify_study",
+                "p":[
+                    "{chart_session_id}",
+                    "{study_id}",
+                    "{session_id}",
+                    {value}
+                ]
+            }}"#
+        );
+        self.write_message(&message).await
+    }
+
+    /// Studies applied to this one have to be removed first.
+    pub async fn remove_study(&mut self, chart_session_id: &str, study_id: &str) -> SimpleResult<()> {
+        let message = format!(
+            r#"{{
+                "m":"remove_study",
+                "p":[
+                    "{chart_session_id}",
+                    "{study_id}"
+                ]
+            }}"#
+        );
+        self.write_message(&message).await
+    }
+
     pub async fn pong(&mut self, nonce: usize) -> SimpleResult<()> {
         let message = format!("~h~{nonce}");
         self.write_message(&message).await
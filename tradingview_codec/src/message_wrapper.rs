@@ -19,8 +19,8 @@ impl TradingViewMessageWrapper {
         format!("~m~{input_len}~m~{input}")
     }
 
-    /// Parses a TradingView message from the input bytes.
-    pub fn parse(input: &[u8]) -> IResult<&[u8], TradingViewMessageWrapper> {
+    /// Parses the raw payload of one `~m~<len>~m~<payload>` frame without interpreting it.
+    pub fn parse_payload(input: &[u8]) -> IResult<&[u8], String> {
         // Parse the prefix "~m~"
         let (input, _) = tag_streaming("~m~")(input)?;
 
@@ -49,14 +49,21 @@ impl TradingViewMessageWrapper {
                 nom::Err::Failure(nom::error::Error::new(payload, ErrorKind::Fail))
             })?;
 
+        Ok((input, string_payload))
+    }
+
+    /// Parses a TradingView message from the input bytes.
+    pub fn parse(input: &[u8]) -> IResult<&[u8], TradingViewMessageWrapper> {
+        let (remaining, string_payload) = Self::parse_payload(input)?;
+
         // Try to parse into message
         let parsed_message = ParsedTradingViewMessage::from_string(&string_payload)
             .map_err(|_| {
-                nom::Err::Failure(nom::error::Error::new(payload, ErrorKind::Fail))
+                nom::Err::Failure(nom::error::Error::new(input, ErrorKind::Fail))
             })?;
 
         Ok((
-            input,
+            remaining,
             TradingViewMessageWrapper {
                 payload: string_payload,
                 parsed_message
@@ -0,0 +1,162 @@
+use std::collections::VecDeque;
+
+use crate::Bar;
+
+/// Bars kept per series unless configured otherwise, a bit more than the `range` scrapers ask for.
+pub const DEFAULT_BAR_HISTORY_CAPACITY: usize = 5000;
+
+/// One series' bars by index, merged from the `timescale_update` backfill and live `du` updates.
+///
+/// Updates for a bar that is already stored replace it (the forming bar is revised until it closes). Once
+/// full, the oldest bars are dropped.
+#[derive(Debug, Clone)]
+pub struct BarHistory {
+    capacity: usize,
+    bars: VecDeque<Bar>, // ascending index
+}
+
+impl Default for BarHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_BAR_HISTORY_CAPACITY)
+    }
+}
+
+impl BarHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            bars: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.bars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bars.is_empty()
+    }
+
+    pub fn merge(&mut self, bar: Bar) {
+        match self.bars.binary_search_by_key(&bar.index, |stored| stored.index) {
+            // revision
+            Ok(position) => self.bars[position] = bar,
+            // older than anything kept and no room for it
+            Err(0) if self.bars.len() == self.capacity => (),
+            Err(position) => {
+                self.bars.insert(position, bar);
+                if self.bars.len() > self.capacity {
+                    self.bars.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Bar with this series index.
+    pub fn get(&self, index: u64) -> Option<&Bar> {
+        let position = self.bars.binary_search_by_key(&index, |bar| bar.index).ok()?;
+        self.bars.get(position)
+    }
+
+    /// Bar that contains `timestamp` (the last one opening at or before it).
+    pub fn at_timestamp(&self, timestamp: u64) -> Option<&Bar> {
+        let position = self.bars.partition_point(|bar| bar.timestamp <= timestamp);
+        self.bars.get(position.checked_sub(1)?)
+    }
+
+    /// `bars_ago` bars before the latest one, `back(0)` is the latest (possibly still forming) bar.
+    pub fn back(&self, bars_ago: usize) -> Option<&Bar> {
+        let position = self.bars.len().checked_sub(bars_ago + 1)?;
+        self.bars.get(position)
+    }
+
+    pub fn first(&self) -> Option<&Bar> {
+        self.bars.front()
+    }
+
+    pub fn last(&self) -> Option<&Bar> {
+        self.bars.back()
+    }
+
+    /// Oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Bar> {
+        self.bars.iter()
+    }
+
+    /// Up to `count` of the latest bars, oldest first.
+    pub fn last_n(&self, count: usize) -> impl DoubleEndedIterator<Item = &Bar> {
+        self.bars.range(self.bars.len().saturating_sub(count)..)
+    }
+
+    pub fn clear(&mut self) {
+        self.bars.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(index: u64, close: f64) -> Bar {
+        Bar {
+            index,
+            timestamp: 1_700_000_000 + index * 60,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: None,
+            extra: vec![],
+            freshness: None,
+            symbol: None,
+        }
+    }
+
+    #[test]
+    fn merge_keeps_bars_ordered_and_revises_in_place() {
+        let mut history = BarHistory::new(10);
+        for index in [2, 0, 1] {
+            history.merge(bar(index, index as f64));
+        }
+        history.merge(bar(2, 20.0));
+        assert_eq!(history.iter().map(|bar| bar.index).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(history.last().unwrap().close, 20.0);
+        assert_eq!(history.back(1).unwrap().index, 1);
+        assert!(history.back(3).is_none());
+        assert_eq!(history.get(1).unwrap().close, 1.0);
+        assert!(history.get(5).is_none());
+    }
+
+    #[test]
+    fn full_history_drops_the_oldest_bars() {
+        let mut history = BarHistory::new(3);
+        for index in 0..5 {
+            history.merge(bar(index, index as f64));
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.first().unwrap().index, 2);
+        // older than anything kept
+        history.merge(bar(1, 1.0));
+        assert_eq!(history.first().unwrap().index, 2);
+        assert_eq!(history.last_n(2).map(|bar| bar.index).collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(BarHistory::new(0).capacity(), 1);
+    }
+
+    #[test]
+    fn at_timestamp_finds_the_containing_bar() {
+        let mut history = BarHistory::default();
+        for index in 0..3 {
+            history.merge(bar(index, index as f64));
+        }
+        assert!(history.at_timestamp(1_699_999_999).is_none());
+        assert_eq!(history.at_timestamp(1_700_000_000).unwrap().index, 0);
+        assert_eq!(history.at_timestamp(1_700_000_090).unwrap().index, 1);
+        assert_eq!(history.at_timestamp(1_800_000_000).unwrap().index, 2);
+        history.clear();
+        assert!(history.is_empty());
+    }
+}
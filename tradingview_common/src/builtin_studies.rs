@@ -0,0 +1,177 @@
+use miniserde::json::{Number, Object, Value};
+
+use crate::{IndicatorConfig, PinePlotDefinition};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RsiInputs {
+    pub length: u32,
+}
+
+impl Default for RsiInputs {
+    fn default() -> Self {
+        Self { length: 14 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacdInputs {
+    pub fast_length: u32,
+    pub slow_length: u32,
+    pub signal_length: u32,
+    pub source: String, // close, hl2, ...
+}
+
+impl Default for MacdInputs {
+    fn default() -> Self {
+        Self {
+            fast_length: 12,
+            slow_length: 26,
+            signal_length: 9,
+            source: "close".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BollingerBandsInputs {
+    pub length: u32,
+    pub mult: f64, // standard deviations
+}
+
+impl Default for BollingerBandsInputs {
+    fn default() -> Self {
+        Self { length: 20, mult: 2.0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovingAverageInputs {
+    pub length: u32,
+    pub source: String,
+}
+
+impl Default for MovingAverageInputs {
+    fn default() -> Self {
+        Self {
+            length: 9,
+            source: "close".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeInputs {
+    pub ma_length: u32,
+    pub color_by_previous_close: bool,
+}
+
+impl Default for VolumeInputs {
+    fn default() -> Self {
+        Self {
+            ma_length: 20,
+            color_by_previous_close: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtrInputs {
+    pub length: u32,
+}
+
+impl Default for AtrInputs {
+    fn default() -> Self {
+        Self { length: 14 }
+    }
+}
+
+/// Standard TradingView studies, added with their own study id instead of `Script@tv-scripting-101!`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuiltinStudy {
+    Rsi(RsiInputs),
+    Macd(MacdInputs),
+    BollingerBands(BollingerBandsInputs),
+    Ema(MovingAverageInputs),
+    Sma(MovingAverageInputs),
+    Vwap,
+    Volume(VolumeInputs),
+    Atr(AtrInputs),
+}
+
+impl BuiltinStudy {
+    /// Name sent in `create_study`.
+    pub fn study_id(&self) -> &'static str {
+        match self {
+            BuiltinStudy::Rsi(_) => "RSI@tv-basicstudies-241",
+            BuiltinStudy::Macd(_) => "MACD@tv-basicstudies-241",
+            BuiltinStudy::BollingerBands(_) => "BB@tv-basicstudies-241",
+            BuiltinStudy::Ema(_) => "MAExp@tv-basicstudies-241",
+            BuiltinStudy::Sma(_) => "MASimple@tv-basicstudies-241",
+            BuiltinStudy::Vwap => "VWAP@tv-basicstudies-241",
+            BuiltinStudy::Volume(_) => "Volume@tv-basicstudies-241",
+            BuiltinStudy::Atr(_) => "ATR@tv-basicstudies-241",
+        }
+    }
+
+    /// `create_study` value; built-in studies take named inputs rather than `in_N` objects.
+    pub fn inputs(&self) -> Object {
+        let mut inputs = Object::new();
+        let integer = |value: u32| Value::Number(Number::U64(value as u64));
+        match self {
+            BuiltinStudy::Rsi(rsi) => {
+                inputs.insert("length".to_string(), integer(rsi.length));
+            }
+            BuiltinStudy::Macd(macd) => {
+                inputs.insert("fast_length".to_string(), integer(macd.fast_length));
+                inputs.insert("slow_length".to_string(), integer(macd.slow_length));
+                inputs.insert("signal_length".to_string(), integer(macd.signal_length));
+                inputs.insert("source".to_string(), Value::String(macd.source.clone()));
+            }
+            BuiltinStudy::BollingerBands(bollinger_bands) => {
+                inputs.insert("length".to_string(), integer(bollinger_bands.length));
+                inputs.insert("mult".to_string(), Value::Number(Number::F64(bollinger_bands.mult)));
+            }
+            BuiltinStudy::Ema(moving_average) | BuiltinStudy::Sma(moving_average) => {
+                inputs.insert("length".to_string(), integer(moving_average.length));
+                inputs.insert("source".to_string(), Value::String(moving_average.source.clone()));
+            }
+            BuiltinStudy::Vwap => (),
+            BuiltinStudy::Volume(volume) => {
+                inputs.insert("length".to_string(), integer(volume.ma_length));
+                inputs.insert("col_prev_close".to_string(), Value::Bool(volume.color_by_previous_close));
+            }
+            BuiltinStudy::Atr(atr) => {
+                inputs.insert("length".to_string(), integer(atr.length));
+            }
+        }
+        inputs
+    }
+
+    /// Output layout, in the order values arrive in study updates.
+    pub fn plots(&self) -> Vec<PinePlotDefinition> {
+        let plots: &[(&str, &str)] = match self {
+            BuiltinStudy::Rsi(_) => &[("line", "RSI")],
+            BuiltinStudy::Macd(_) => &[("columns", "Histogram"), ("line", "MACD"), ("line", "Signal")],
+            BuiltinStudy::BollingerBands(_) => &[("line", "Basis"), ("line", "Upper"), ("line", "Lower")],
+            BuiltinStudy::Ema(_) => &[("line", "EMA")],
+            BuiltinStudy::Sma(_) => &[("line", "MA")],
+            BuiltinStudy::Vwap => &[("line", "VWAP")],
+            BuiltinStudy::Volume(_) => &[("columns", "Volume"), ("line", "Volume MA")],
+            BuiltinStudy::Atr(_) => &[("line", "ATR")],
+        };
+        plots
+            .iter()
+            .enumerate()
+            .map(|(index, (plot_type, title))| PinePlotDefinition::new(&format!("plot_{index}"), plot_type, title))
+            .collect()
+    }
+
+    /// Entry for `TradingViewClientConfig::indicators`.
+    pub fn to_indicator(&self) -> IndicatorConfig {
+        IndicatorConfig {
+            name: self.study_id().to_string(),
+            value: miniserde::json::to_string(&Value::Object(self.inputs())),
+            plots: self.plots(),
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use miniserde::json::{Number, Object, Value};
+use simple_error::{box_err, SimpleResult};
+
+use crate::{json_utilities, SeriesUpdate};
+
+/// How brick / box sizes are derived.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoxSize {
+    Traditional(f64), // fixed price amount
+    Atr(u32), // ATR length
+    Percentage(f64), // percent of price
+}
+
+/// Price used to build bricks / boxes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarSource {
+    Close,
+    HighLow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenkoInputs {
+    pub source: BarSource,
+    pub box_size: BoxSize,
+    pub wicks: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KagiInputs {
+    pub reversal: BoxSize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineBreakInputs {
+    pub line_count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointAndFigureInputs {
+    pub source: BarSource,
+    pub box_size: BoxSize,
+    pub reversal: u32, // boxes
+    pub one_step_back_building: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeInputs {
+    pub range: u32, // ticks
+    pub phantom_bars: bool,
+}
+
+/// Bar types TradingView builds server side from a chart `type` + `inputs` in the symbol spec.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChartType {
+    Standard,
+    HeikinAshi,
+    Renko(RenkoInputs),
+    Kagi(KagiInputs),
+    LineBreak(LineBreakInputs),
+    PointAndFigure(PointAndFigureInputs),
+    Range(RangeInputs),
+}
+
+/// Direction of a brick / box / line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarDirection {
+    Up,
+    Down,
+}
+
+/// A series update with the extra columns of its chart type pulled out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChartBar {
+    Standard,
+    HeikinAshi,
+    Renko { direction: BarDirection, box_size: Option<f64> },
+    Kagi { direction: BarDirection, reversal: Option<f64> },
+    LineBreak { direction: BarDirection },
+    PointAndFigure { direction: BarDirection, box_size: Option<f64>, box_count: Option<u64> },
+    Range { direction: BarDirection },
+}
+
+impl ChartType {
+    /// Study id sent as the spec `type`; `None` for standard candles.
+    pub fn type_id(&self) -> Option<&'static str> {
+        match self {
+            ChartType::Standard => None,
+            ChartType::HeikinAshi => Some("BarSetHeikenAshi@tv-basicstudies-60!"),
+            ChartType::Renko(_) => Some("BarSetRenko@tv-prostudies-40!"),
+            ChartType::Kagi(_) => Some("BarSetKagi@tv-prostudies-40!"),
+            ChartType::LineBreak(_) => Some("BarSetPriceBreak@tv-prostudies-34!"),
+            ChartType::PointAndFigure(_) => Some("BarSetPnF@tv-prostudies-40!"),
+            ChartType::Range(_) => Some("BarSetRange@tv-basicstudies-72!"),
+        }
+    }
+
+    pub fn inputs(&self) -> Object {
+        let mut inputs = Object::new();
+        match self {
+            ChartType::Standard | ChartType::HeikinAshi => (),
+            ChartType::Renko(renko) => {
+                insert_source(&mut inputs, renko.source);
+                insert_box_size(&mut inputs, "boxSize", renko.box_size);
+                inputs.insert("wicks".to_string(), Value::Bool(renko.wicks));
+            }
+            ChartType::Kagi(kagi) => {
+                inputs.insert("source".to_string(), Value::String("close".to_string()));
+                insert_box_size(&mut inputs, "reversalAmount", kagi.reversal);
+            }
+            ChartType::LineBreak(line_break) => {
+                inputs.insert("source".to_string(), Value::String("close".to_string()));
+                inputs.insert("lb".to_string(), Value::Number(Number::U64(line_break.line_count as u64)));
+            }
+            ChartType::PointAndFigure(point_and_figure) => {
+                insert_source(&mut inputs, point_and_figure.source);
+                insert_box_size(&mut inputs, "boxSize", point_and_figure.box_size);
+                inputs.insert("reversalAmount".to_string(), Value::Number(Number::U64(point_and_figure.reversal as u64)));
+                inputs.insert("oneStepBackBuilding".to_string(), Value::Bool(point_and_figure.one_step_back_building));
+            }
+            ChartType::Range(range) => {
+                inputs.insert("range".to_string(), Value::Number(Number::U64(range.range as u64)));
+                inputs.insert("phantomBars".to_string(), Value::Bool(range.phantom_bars));
+            }
+        }
+        inputs
+    }
+
+    /// Inverse of `type_id` + `inputs`, for specs decoded from configs / logs.
+    pub fn from_type_id(type_id: Option<&str>, inputs: &Object) -> SimpleResult<Self> {
+        let type_id = match type_id {
+            Some(type_id) => type_id,
+            None => return Ok(ChartType::Standard),
+        };
+        let name = type_id.split('@').next().unwrap_or(type_id);
+        match name {
+            "BarSetHeikenAshi" => Ok(ChartType::HeikinAshi),
+            "BarSetRenko" => Ok(ChartType::Renko(RenkoInputs {
+                source: read_source(inputs),
+                box_size: read_box_size(inputs, "boxSize")?,
+                wicks: read_bool(inputs, "wicks", true),
+            })),
+            "BarSetKagi" => Ok(ChartType::Kagi(KagiInputs {
+                reversal: read_box_size(inputs, "reversalAmount")?,
+            })),
+            "BarSetPriceBreak" => Ok(ChartType::LineBreak(LineBreakInputs {
+                line_count: read_u64(inputs, "lb").unwrap_or(3) as u32,
+            })),
+            "BarSetPnF" => Ok(ChartType::PointAndFigure(PointAndFigureInputs {
+                source: read_source(inputs),
+                box_size: read_box_size(inputs, "boxSize")?,
+                reversal: read_u64(inputs, "reversalAmount").unwrap_or(3) as u32,
+                one_step_back_building: read_bool(inputs, "oneStepBackBuilding", false),
+            })),
+            "BarSetRange" => Ok(ChartType::Range(RangeInputs {
+                range: read_u64(inputs, "range").unwrap_or(10) as u32,
+                phantom_bars: read_bool(inputs, "phantomBars", false),
+            })),
+            _ => Err(box_err!(format!("unknown chart type {type_id}"))),
+        }
+    }
+
+    /// Names of the columns after OHLCV in this chart type's series values.
+    pub fn extra_columns(&self) -> &'static [&'static str] {
+        match self {
+            ChartType::Standard | ChartType::HeikinAshi | ChartType::LineBreak(_) | ChartType::Range(_) => &[],
+            ChartType::Renko(_) => &["box_size"],
+            ChartType::Kagi(_) => &["reversal"],
+            ChartType::PointAndFigure(_) => &["box_size", "box_count"],
+        }
+    }
+
+    /// Typed view of a series update built with this chart type.
+    pub fn parse_bar(&self, series_update: &SeriesUpdate) -> ChartBar {
+        let direction = if series_update.close >= series_update.open { BarDirection::Up } else { BarDirection::Down };
+        let extra = |index: usize| series_update.extra.get(index).copied().filter(|value| !value.is_nan());
+        match self {
+            ChartType::Standard => ChartBar::Standard,
+            ChartType::HeikinAshi => ChartBar::HeikinAshi,
+            ChartType::Renko(_) => ChartBar::Renko { direction, box_size: extra(0) },
+            ChartType::Kagi(_) => ChartBar::Kagi { direction, reversal: extra(0) },
+            ChartType::LineBreak(_) => ChartBar::LineBreak { direction },
+            ChartType::PointAndFigure(_) => {
+                let box_size = extra(0);
+                // fall back to the column span when the server omits the count
+                let box_count = extra(1).map(|box_count| box_count as u64).or_else(|| {
+                    box_size.filter(|box_size| *box_size > 0.0).map(|box_size| ((series_update.high - series_update.low) / box_size).round() as u64)
+                });
+                ChartBar::PointAndFigure { direction, box_size, box_count }
+            }
+            ChartType::Range(_) => ChartBar::Range { direction },
+        }
+    }
+}
+
+fn insert_source(inputs: &mut Object, source: BarSource) {
+    let (source, sources) = match source {
+        BarSource::Close => ("close", "Close"),
+        BarSource::HighLow => ("hl", "HL"),
+    };
+    inputs.insert("source".to_string(), Value::String(source.to_string()));
+    inputs.insert("sources".to_string(), Value::String(sources.to_string()));
+}
+
+fn insert_box_size(inputs: &mut Object, amount_key: &str, box_size: BoxSize) {
+    let (style, amount) = match box_size {
+        BoxSize::Traditional(amount) => ("Traditional", Some(amount)),
+        BoxSize::Percentage(amount) => ("Percentage", Some(amount)),
+        BoxSize::Atr(atr_length) => {
+            inputs.insert("atrLength".to_string(), Value::Number(Number::U64(atr_length as u64)));
+            ("ATR", None)
+        }
+    };
+    inputs.insert("style".to_string(), Value::String(style.to_string()));
+    if let Some(amount) = amount {
+        inputs.insert(amount_key.to_string(), Value::Number(Number::F64(amount)));
+    }
+}
+
+fn read_source(inputs: &Object) -> BarSource {
+    match inputs.get("sources").and_then(|sources| json_utilities::value_to_string(sources).ok()).as_deref() {
+        Some("HL") => BarSource::HighLow,
+        _ => BarSource::Close,
+    }
+}
+
+fn read_box_size(inputs: &Object, amount_key: &str) -> SimpleResult<BoxSize> {
+    let style = match inputs.get("style") {
+        Some(style) => json_utilities::value_to_string(style)?,
+        None => "ATR".to_string(),
+    };
+    let amount = inputs.get(amount_key).and_then(|amount| json_utilities::value_to_f64_cast(amount).ok());
+    match style.as_str() {
+        "ATR" => Ok(BoxSize::Atr(read_u64(inputs, "atrLength").unwrap_or(14) as u32)),
+        "Traditional" => Ok(BoxSize::Traditional(amount.ok_or(box_err!(format!("missing {amount_key}")))?)),
+        "Percentage" => Ok(BoxSize::Percentage(amount.ok_or(box_err!(format!("missing {amount_key}")))?)),
+        _ => Err(box_err!(format!("unknown box size style {style}"))),
+    }
+}
+
+fn read_u64(inputs: &Object, key: &str) -> Option<u64> {
+    inputs.get(key).and_then(|value| json_utilities::value_to_u64_cast(value).ok())
+}
+
+fn read_bool(inputs: &Object, key: &str, default: bool) -> bool {
+    inputs.get(key).and_then(|value| json_utilities::value_to_bool(value).ok()).unwrap_or(default)
+}
@@ -1,12 +1,25 @@
 use miniserde::{Deserialize, Serialize};
 
+use crate::{ConnectionOptions, PinePlotDefinition, Timeframe};
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct TradingViewClientConfig {
     pub name: String,
     pub auth_token: String,
+    pub anonymous: bool, // send unauthorized_user_token instead of auth_token (delayed data)
     pub chart_symbols: Vec<String>,
     pub quote_symbols: Vec<String>,
-    pub indicators: Vec<String>,
-    pub timeframe: Option<String>, // not needed for quotes
+    pub indicators: Vec<IndicatorConfig>,
+    pub timeframe: Option<Timeframe>, // not needed for quotes
     pub range: Option<usize>, // not needed for quotes
+    pub capture_path: Option<String>, // record inbound messages for replay
+    pub connection: ConnectionOptions,
+}
+
+/// A study added to each chart, see `PineStudy::to_indicator` / `BuiltinStudy::to_indicator`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct IndicatorConfig {
+    pub name: String, // Script@tv-scripting-101!, RSI@tv-basicstudies-241, ...
+    pub value: String, // create_study value
+    pub plots: Vec<PinePlotDefinition>, // attached to this study's updates
 }
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use miniserde::{Deserialize, Serialize};
+use simple_error::{box_err, SimpleResult};
+
+use crate::utilities::url_encode;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TradingViewHost {
+    Data,
+    ProData,
+    WidgetData,
+    Custom, // uses ConnectionOptions::custom_endpoint
+}
+
+impl TradingViewHost {
+    pub fn hostname(&self) -> Option<&'static str> {
+        match self {
+            TradingViewHost::Data => Some("data.tradingview.com"),
+            TradingViewHost::ProData => Some("prodata.tradingview.com"),
+            TradingViewHost::WidgetData => Some("widgetdata.tradingview.com"),
+            TradingViewHost::Custom => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ProxyKind {
+    HttpConnect,
+    Socks5,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProxyOptions {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>, // basic auth for HttpConnect, username/password auth for Socks5
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConnectionOptions {
+    pub host: TradingViewHost,
+    pub custom_endpoint: Option<String>, // e.g. ws://127.0.0.1:8080/socket.io/websocket, query params are appended
+    pub query_params: BTreeMap<String, String>,
+    pub headers: BTreeMap<String, String>, // Host is always derived from the endpoint
+    pub proxy: Option<ProxyOptions>, // tunnel is set up before tls and the websocket upgrade
+    pub connect_timeout_ms: u64, // tcp + proxy + tls + websocket upgrade
+    pub handshake_timeout_ms: u64, // server hello after upgrade
+    pub response_timeout_ms: u64, // symbol / series / quote replies
+    pub study_timeout_ms: u64, // study loading / completed / first data update
+    pub study_session_timeout_ms: u64, // initial Sessions study, slow on cold symbols
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        let mut query_params = BTreeMap::new();
+        query_params.insert("type".to_string(), "chart".to_string());
+        let mut headers = BTreeMap::new();
+        headers.insert("User-Agent".to_string(), "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36".to_string());
+        headers.insert("Origin".to_string(), "https://www.tradingview.com".to_string());
+        Self {
+            host: TradingViewHost::Data,
+            custom_endpoint: None,
+            query_params,
+            headers,
+            proxy: None,
+            connect_timeout_ms: 10_000,
+            handshake_timeout_ms: 5_000,
+            response_timeout_ms: 2_000,
+            study_timeout_ms: 3_000,
+            study_session_timeout_ms: 10_000,
+        }
+    }
+}
+
+impl ConnectionOptions {
+    /// Full websocket URL including the percent-encoded query string; `Custom` without a `custom_endpoint` is an error
+    /// rather than a silent fallback to TradingView.
+    pub fn endpoint(&self) -> SimpleResult<String> {
+        let base = match (self.host.hostname(), &self.custom_endpoint) {
+            (Some(hostname), _) => format!("wss://{hostname}/socket.io/websocket"),
+            (None, Some(custom_endpoint)) => custom_endpoint.clone(),
+            (None, None) => return Err(box_err!("TradingViewHost::Custom requires custom_endpoint")),
+        };
+        if self.query_params.is_empty() {
+            return Ok(base);
+        }
+        let query = self
+            .query_params
+            .iter()
+            .map(|(key, value)| format!("{}={}", url_encode(key), url_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let separator = if base.contains('?') { '&' } else { '?' };
+        Ok(format!("{base}{separator}{query}"))
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout_ms)
+    }
+
+    pub fn handshake_timeout(&self) -> Duration {
+        Duration::from_millis(self.handshake_timeout_ms)
+    }
+
+    pub fn response_timeout(&self) -> Duration {
+        Duration::from_millis(self.response_timeout_ms)
+    }
+
+    pub fn study_timeout(&self) -> Duration {
+        Duration::from_millis(self.study_timeout_ms)
+    }
+
+    pub fn study_session_timeout(&self) -> Duration {
+        Duration::from_millis(self.study_session_timeout_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoint_encodes_the_query() {
+        let mut options = ConnectionOptions::default();
+        options.query_params.insert("date".to_string(), "2024-07-15T12:00:00 UTC".to_string());
+        assert_eq!(
+            options.endpoint().unwrap(),
+            "wss://data.tradingview.com/socket.io/websocket?date=2024-07-15T12%3A00%3A00%20UTC&type=chart"
+        );
+    }
+
+    #[test]
+    fn custom_host_requires_an_endpoint() {
+        let options = ConnectionOptions {
+            host: TradingViewHost::Custom,
+            query_params: BTreeMap::new(),
+            ..ConnectionOptions::default()
+        };
+        assert!(options.endpoint().is_err());
+        let options = ConnectionOptions {
+            custom_endpoint: Some("ws://127.0.0.1:8080/socket.io/websocket?from=mock".to_string()),
+            ..options
+        };
+        assert_eq!(options.endpoint().unwrap(), "ws://127.0.0.1:8080/socket.io/websocket?from=mock");
+    }
+}
@@ -0,0 +1,50 @@
+use miniserde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum UpdateMode {
+    Streaming,
+    DelayedStreaming,
+    EndOfDay,
+    Unknown,
+}
+
+/// How current the data behind a quote / bar is, from TradingView's `update_mode`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DataFreshness {
+    pub update_mode: UpdateMode,
+    pub delay_secs: u64, // 0 unless delayed
+}
+
+impl DataFreshness {
+    /// Parses `streaming`, `delayed_streaming_900`, `endofday`, ...
+    pub fn from_update_mode(update_mode: &str) -> Self {
+        if update_mode == "streaming" {
+            return Self { update_mode: UpdateMode::Streaming, delay_secs: 0 };
+        }
+        if let Some(delay_secs) = update_mode.strip_prefix("delayed_streaming_") {
+            return Self {
+                update_mode: UpdateMode::DelayedStreaming,
+                delay_secs: delay_secs.parse().unwrap_or(0),
+            };
+        }
+        if update_mode == "endofday" {
+            return Self { update_mode: UpdateMode::EndOfDay, delay_secs: 0 };
+        }
+        log::warn!("unknown update_mode = {update_mode}");
+        Self { update_mode: UpdateMode::Unknown, delay_secs: 0 }
+    }
+
+    pub fn is_realtime(&self) -> bool {
+        self.update_mode == UpdateMode::Streaming
+    }
+
+    /// Short label for csv output.
+    pub fn label(&self) -> &'static str {
+        match self.update_mode {
+            UpdateMode::Streaming => "realtime",
+            UpdateMode::DelayedStreaming => "delayed",
+            UpdateMode::EndOfDay => "endofday",
+            UpdateMode::Unknown => "unknown",
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use simple_error::{box_err, SimpleResult};
+use time::{Date, Duration};
+
+/// UTC offset in seconds of an exchange timezone (IANA name, as in `symbol_resolved`) at a unix timestamp.
+pub fn utc_offset(timezone: &str, timestamp: i64) -> SimpleResult<i64> {
+    let time_zone = tzdb::tz_by_name(timezone).ok_or(box_err!(format!("unknown timezone {timezone}")))?;
+    let local_time_type = time_zone
+        .find_local_time_type(timestamp)
+        .map_err(|err| box_err!(format!("no utc offset for {timezone} at {timestamp}: {err}")))?;
+    Ok(local_time_type.ut_offset() as i64)
+}
+
+pub(crate) fn day_start(date: Date) -> i64 {
+    date.midnight().assume_utc().unix_timestamp()
+}
+
+pub(crate) fn add_days(date: Date, days: i64) -> SimpleResult<Date> {
+    date.checked_add(Duration::days(days)).ok_or(box_err!("date out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utc_offset_follows_daylight_saving() {
+        // 2024-01-15 / 2024-07-15 12:00 utc
+        let (winter, summer) = (1_705_320_000, 1_721_044_800);
+        assert_eq!(utc_offset("America/New_York", winter).unwrap(), -5 * 3600);
+        assert_eq!(utc_offset("America/New_York", summer).unwrap(), -4 * 3600);
+        assert_eq!(utc_offset("Europe/London", summer).unwrap(), 3600);
+        // southern hemisphere summer is in january
+        assert_eq!(utc_offset("Pacific/Auckland", winter).unwrap(), 13 * 3600);
+        assert_eq!(utc_offset("Pacific/Auckland", summer).unwrap(), 12 * 3600);
+    }
+
+    #[test]
+    fn utc_offset_knows_every_iana_zone() {
+        let timestamp = 1_721_044_800;
+        assert_eq!(utc_offset("Asia/Jakarta", timestamp).unwrap(), 7 * 3600);
+        assert_eq!(utc_offset("Asia/Kolkata", timestamp).unwrap(), 5 * 3600 + 1800);
+        assert_eq!(utc_offset("America/Mexico_City", timestamp).unwrap(), -6 * 3600);
+        assert_eq!(utc_offset("Africa/Johannesburg", timestamp).unwrap(), 2 * 3600);
+        assert_eq!(utc_offset("Etc/UTC", timestamp).unwrap(), 0);
+        assert!(utc_offset("Mars/Olympus_Mons", timestamp).is_err());
+    }
+}
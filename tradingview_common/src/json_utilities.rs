@@ -86,3 +86,33 @@ pub fn is_null(input: &Object, key: &str) -> SimpleResult<bool> {
         _ => Ok(false),
     }
 }
+
+pub fn array_or_empty(input: &Object, key: &str) -> SimpleResult<Array> {
+    match input.get(key) {
+        Some(Value::Null) | None => Ok(Array::new()),
+        Some(value) => value_to_array(value),
+    }
+}
+
+pub fn object_or_empty(input: &Object, key: &str) -> SimpleResult<Object> {
+    match input.get(key) {
+        Some(Value::Null) | None => Ok(Object::new()),
+        Some(value) => value_to_object(value),
+    }
+}
+
+pub fn string_or_empty(input: &Object, key: &str) -> String {
+    input.get(key).and_then(|value| value_to_string(value).ok()).unwrap_or_default()
+}
+
+pub fn u64_or_zero(input: &Object, key: &str) -> u64 {
+    input.get(key).and_then(|value| value_to_u64_cast(value).ok()).unwrap_or(0)
+}
+
+pub fn f64_or_zero(input: &Object, key: &str) -> f64 {
+    input.get(key).and_then(|value| value_to_f64_cast(value).ok()).unwrap_or(0.0)
+}
+
+pub fn f64_or_nan(input: &Object, key: &str) -> f64 {
+    input.get(key).and_then(|value| value_to_f64_cast(value).ok()).unwrap_or(f64::NAN)
+}
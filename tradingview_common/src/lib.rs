@@ -1,16 +1,46 @@
+mod bar_history;
+mod builtin_studies;
+mod chart_types;
 mod client_config;
+mod connection_options;
+mod data_freshness;
+mod exchange_time;
 mod scrape_result;
+mod session_calendar;
+mod strategy_report;
+mod study_graph;
+mod study_graphics;
 mod message_parsing;
 mod parsed_message;
 mod json_utilities;
 mod indicators;
+mod pine_metadata;
+mod pine_study;
+mod price;
+mod symbol_expressions;
 mod symbols;
+mod timeframe;
 pub mod utilities;
 
+pub use bar_history::*;
+pub use builtin_studies::*;
+pub use chart_types::*;
 pub use client_config::*;
+pub use connection_options::*;
+pub use data_freshness::*;
+pub use exchange_time::*;
 pub use scrape_result::*;
+pub use session_calendar::*;
+pub use strategy_report::*;
+pub use study_graph::*;
+pub use study_graphics::*;
 pub use indicators::*;
+pub use pine_metadata::*;
+pub use pine_study::*;
+pub use price::*;
+pub use symbol_expressions::*;
 pub use symbols::*;
+pub use timeframe::*;
 pub use parsed_message::*;
 pub use message_parsing::*;
 pub use utilities::*;
@@ -0,0 +1,107 @@
+use miniserde::{json::Value, Deserialize, Serialize};
+use simple_error::{box_err, SimpleResult};
+
+use crate::{json_utilities, DataFreshness};
+
+/// One OHLCV bar of a series, from the `timescale_update` backfill or a live `du`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bar {
+    pub index: u64,
+    pub timestamp: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: Option<f64>, // None for series without volume (indices, FX, some chart types)
+    pub extra: Vec<f64>, // columns after volume on non-standard chart types, see ChartType::extra_columns
+    pub freshness: Option<DataFreshness>, // filled in by message processors from series_completed
+    pub symbol: Option<String>, // filled in by message processors from symbol_resolved, may be an expression
+}
+
+/// Bar from a live `du`.
+pub type SeriesUpdate = Bar;
+
+/// Bar from the `timescale_update` backfill.
+pub type TimescaleUpdate = Bar;
+
+impl Bar {
+    /// Parses `{"i":12,"v":[time,open,high,low,close,volume,...]}`; integer and float numbers are both accepted.
+    pub fn from_value(value: &Value) -> SimpleResult<Self> {
+        let element = json_utilities::value_to_object(value)?;
+
+        // pluck i (index)
+        let index = json_utilities::value_to_u64_cast(element.get("i").ok_or(box_err!("failed to get i"))?)?;
+
+        // pluck v (values)
+        let v = json_utilities::value_to_array(element.get("v").ok_or(box_err!("failed to get v"))?)?;
+        if v.len() < 5 {
+            return Err(box_err!(format!("bar {index} has {} values, expected at least 5", v.len())));
+        }
+
+        // pluck out of values
+        let timestamp = json_utilities::value_to_u64_cast(&v[0])?;
+        let open = json_utilities::value_to_f64_cast(&v[1])?;
+        let high = json_utilities::value_to_f64_cast(&v[2])?;
+        let low = json_utilities::value_to_f64_cast(&v[3])?;
+        let close = json_utilities::value_to_f64_cast(&v[4])?;
+        // missing or null when the symbol has no volume
+        let volume = v.get(5).and_then(|volume| json_utilities::value_to_f64_cast(volume).ok());
+        let extra = v.iter().skip(6).map(|value| json_utilities::value_to_f64_cast(value).unwrap_or(f64::NAN)).collect::<Vec<_>>();
+
+        // return
+        Ok(Self {
+            index,
+            timestamp,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            extra,
+            freshness: None,
+            symbol: None,
+        })
+    }
+
+    /// Parses the `s` array of a series update.
+    pub fn from_array(values: &[Value]) -> SimpleResult<Vec<Self>> {
+        values.iter().map(Self::from_value).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(json: &str) -> SimpleResult<Bar> {
+        let value: Value = miniserde::json::from_str(json)?;
+        Bar::from_value(&value)
+    }
+
+    #[test]
+    fn parses_ohlcv_and_extra_columns() {
+        let parsed = bar(r#"{"i":12,"v":[1700000000,1.5,2,1,1.75,1200,3.5,4]}"#).unwrap();
+        assert_eq!((parsed.index, parsed.timestamp), (12, 1700000000));
+        assert_eq!((parsed.open, parsed.high, parsed.low, parsed.close), (1.5, 2.0, 1.0, 1.75));
+        assert_eq!(parsed.volume, Some(1200.0));
+        assert_eq!(parsed.extra, vec![3.5, 4.0]);
+        assert!(parsed.freshness.is_none() && parsed.symbol.is_none());
+    }
+
+    #[test]
+    fn tolerates_missing_volume() {
+        assert_eq!(bar(r#"{"i":0,"v":[1700000000.0,1,2,1,2]}"#).unwrap().volume, None);
+        let null_volume = bar(r#"{"i":0,"v":[1700000000,1,2,1,2,null,null]}"#).unwrap();
+        assert_eq!(null_volume.volume, None);
+        assert!(null_volume.extra[0].is_nan());
+    }
+
+    #[test]
+    fn rejects_short_or_malformed_bars() {
+        assert!(bar(r#"{"i":0,"v":[1700000000,1,2,1]}"#).is_err());
+        assert!(bar(r#"{"v":[1700000000,1,2,1,2]}"#).is_err());
+        assert!(bar(r#"{"i":0,"v":[1700000000,"1",2,1,2]}"#).is_err());
+        let values: Value = miniserde::json::from_str(r#"[{"i":0,"v":[1,1,1,1,1]},{"i":1,"v":[]}]"#).unwrap();
+        assert!(Bar::from_array(&json_utilities::value_to_array(&values).unwrap()).is_err());
+    }
+}
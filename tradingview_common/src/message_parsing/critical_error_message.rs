@@ -3,18 +3,26 @@ use std::error::Error;
 use miniserde::{json::Object, Deserialize, Serialize};
 use simple_error::{box_err, SimpleResult};
 
+use crate::json_utilities;
 use crate::ParsedTradingViewMessage;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CriticalErrorMessage {
-
+    pub params: Vec<String>,
 }
 
 impl CriticalErrorMessage {
     pub fn from_object(parsed_message: &Object) -> SimpleResult<Self> {
         log::debug!("critical_error = {parsed_message:?}");
+        let params = match parsed_message.get("p") {
+            Some(p) => json_utilities::value_to_array(p)?
+                .iter()
+                .map(|param| json_utilities::value_to_string(param).unwrap_or_else(|_| miniserde::json::to_string(param)))
+                .collect(),
+            None => vec![],
+        };
         Ok(CriticalErrorMessage {
-            
+            params
         })
     }
 }
@@ -1,26 +1,37 @@
 use std::error::Error;
 
-use miniserde::json::Object;
+use miniserde::json::{Array, Object};
 use miniserde::{Deserialize, Serialize};
 use simple_error::{box_err, SimpleResult};
 
-use crate::{json_utilities, ParsedTradingViewMessage};
+use crate::{json_utilities, Bar, ParsedTradingViewMessage, PinePlotDefinition, SeriesUpdate, StrategyReport, StudyGraphicsUpdate};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SeriesUpdate {
+pub struct StudyUpdate {
+    pub study_id: String, // st2, ...
     pub index: u64,
-    pub timestamp: u64,
-    pub open: f64,
-    pub high: f64,
-    pub low: f64,
-    pub close: f64,
-    pub volume: f64,
+    pub values: Vec<f64>, // timestamp, then one value per plot
+    pub plots: Vec<PinePlotDefinition>, // filled in by the client from the study's IndicatorConfig
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StudyUpdate {
-    pub index: u64,
-    pub values: Vec<f64>,
+impl StudyUpdate {
+    pub fn timestamp(&self) -> Option<f64> {
+        self.values.first().copied()
+    }
+
+    /// Value of a plot by id (`plot_0`) or title.
+    ///
+    /// Errors instead of guessing when there is no schema or it no longer matches the values.
+    pub fn plot(&self, name: &str) -> SimpleResult<f64> {
+        if self.plots.is_empty() {
+            return Err(box_err!(format!("study {} has no plot schema", self.study_id)));
+        }
+        if self.values.len() != self.plots.len() + 1 {
+            return Err(box_err!(format!("study {} sent {} plot values, schema has {} plots", self.study_id, self.values.len().saturating_sub(1), self.plots.len())));
+        }
+        let index = self.plots.iter().position(|plot| plot.matches(name)).ok_or(box_err!(format!("study {} has no plot {name}", self.study_id)))?;
+        Ok(self.values[index + 1])
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,9 +40,17 @@ pub struct DataUpdateMessage {
     pub update_key: String,
     pub series_updates: Option<Vec<SeriesUpdate>>,
     pub study_updates: Option<Vec<StudyUpdate>>,
+    pub strategy_report: Option<Box<StrategyReport>>, // Pine strategies only
+    pub graphics: Option<Box<StudyGraphicsUpdate>>, // labels, lines, boxes, ... drawn by the study
+    pub bar_close_time: Option<u64>, // lbs.bar_close_time of series updates, unix seconds the forming bar closes at
 }
 
 impl DataUpdateMessage {
+    /// Series bars of this update, empty for study updates.
+    pub fn bars(&self) -> &[Bar] {
+        self.series_updates.as_deref().unwrap_or_default()
+    }
+
     pub fn from_object(parsed_message: &Object) -> SimpleResult<Self> {
         log::debug!("du = {parsed_message:?}");
         let p = parsed_message.get("p").ok_or(box_err!("failed to get p"))?;
@@ -45,45 +64,23 @@ impl DataUpdateMessage {
         let update_key = update_keys[0];
         if update_key == "sds_1" { // series
             let update_value = json_utilities::value_to_object(update.get(update_key).ok_or(box_err!("failed to get update_key"))?)?;
+            // lbs is missing on some chart types
+            let bar_close_time = update_value
+                .get("lbs")
+                .and_then(|lbs| json_utilities::value_to_object(lbs).ok())
+                .and_then(|lbs| lbs.get("bar_close_time").and_then(|bar_close_time| json_utilities::value_to_u64_cast(bar_close_time).ok()));
             let message = if update_value.contains_key("s") {
                 let s = update_value.get("s").ok_or(box_err!("failed to get s"))?;
                 let s = json_utilities::value_to_array(s)?;
-                let series_updates = s.iter().map(|element| {
-                    // value -> object
-                    let element = json_utilities::value_to_object(&element).expect("failed to cast");
-                    
-                    // pluck i (index)
-                    let i = element.get("i").expect("failed to get i");
-                    let i = json_utilities::value_to_u64_cast(i).expect("failed to cast");
-
-                    // pluck v (values)
-                    let v = element.get("v").expect("failed to get v");
-                    let v = json_utilities::value_to_array(v).expect("failed to cast");
-
-                    // pluck out of values
-                    let timestamp = json_utilities::value_to_u64_cast(&v[0]).expect("failed to cast");
-                    let open = json_utilities::value_to_f64_cast(&v[1]).expect("failed to cast");
-                    let high = json_utilities::value_to_f64_cast(&v[2]).expect("failed to cast");
-                    let low = json_utilities::value_to_f64_cast(&v[3]).expect("failed to cast");
-                    let close = json_utilities::value_to_f64_cast(&v[4]).expect("failed to cast");
-                    let volume = json_utilities::value_to_f64_cast(&v[5]).expect("failed to cast");
-
-                    // return
-                    SeriesUpdate {
-                        index: i,
-                        timestamp,
-                        open,
-                        high,
-                        low,
-                        close,
-                        volume,
-                    }
-                }).collect::<Vec<_>>();
+                let series_updates = Bar::from_array(&s)?;
                 DataUpdateMessage {
                     chart_session_id,
                     update_key: update_key.to_string(),
                     series_updates: Some(series_updates),
-                    study_updates: None
+                    study_updates: None,
+                    strategy_report: None,
+                    graphics: None,
+                    bar_close_time
                 }
             } else {
                 // watch out for weird du message with no updates on it? ns property
@@ -91,36 +88,67 @@ impl DataUpdateMessage {
                     chart_session_id,
                     update_key: update_key.to_string(),
                     series_updates: None,
-                    study_updates: None
+                    study_updates: None,
+                    strategy_report: None,
+                    graphics: None,
+                    bar_close_time
                 }
             };
             Ok(message)
-        } else if update_key == "st1" || update_key == "st2" { // study
+        } else if update_key.starts_with("st") { // study
             let update_value = json_utilities::value_to_object(update.get(update_key).ok_or(box_err!("failed to get update_key"))?)?;
-            let st = update_value.get("st").ok_or(box_err!("failed to get st"))?;
-            let st = json_utilities::value_to_array(st)?;
+            // strategies can send a report alone, without plot values
+            let st = match update_value.get("st") {
+                Some(st) => json_utilities::value_to_array(st)?,
+                None => Array::new(),
+            };
             let study_updates = st.iter().map(|element| {
                 // value -> object
-                let element = json_utilities::value_to_object(&element).expect("failed to cast");
+                let element = json_utilities::value_to_object(element)?;
 
                 // pluck i (index)
-                let i = element.get("i").expect("failed to get i");
-                let i = json_utilities::value_to_u64_cast(i).expect("failed to cast");
+                let i = element.get("i").ok_or(box_err!("failed to get i"))?;
+                let i = json_utilities::value_to_u64_cast(i)?;
 
                 // pluck v (values)
-                let v = element.get("v").expect("failed to get v");
-                let v = json_utilities::value_to_array(v).expect("failed to cast");
-                let v = v.iter().map(|value| json_utilities::value_to_f64_cast(value).expect("failed to cast")).collect::<Vec<_>>();
-                StudyUpdate {
+                let v = element.get("v").ok_or(box_err!("failed to get v"))?;
+                let v = json_utilities::value_to_array(v)?;
+                let v = v.iter().map(json_utilities::value_to_f64_cast).collect::<SimpleResult<Vec<_>>>()?;
+                Ok(StudyUpdate {
+                    study_id: update_key.to_string(),
                     index: i,
-                    values: v
+                    values: v,
+                    plots: vec![],
+                })
+            }).collect::<SimpleResult<Vec<_>>>()?;
+
+            // strategy report and drawings ride along in ns.d as a json string
+            let (strategy_report, graphics) = match update_value.get("ns") {
+                Some(ns) => {
+                    let ns = json_utilities::value_to_object(ns)?;
+                    let strategy_report = match ns.get("d") {
+                        Some(d) => StrategyReport::from_ns_data(&json_utilities::value_to_string(d)?).unwrap_or_else(|err| {
+                            log::warn!("failed to parse strategy report: {err:?}");
+                            None
+                        }).map(Box::new),
+                        None => None,
+                    };
+                    let graphics = StudyGraphicsUpdate::from_ns(&ns).unwrap_or_else(|err| {
+                        log::warn!("failed to parse study graphics: {err:?}");
+                        None
+                    }).map(Box::new);
+                    (strategy_report, graphics)
                 }
-            }).collect::<Vec<_>>();
+                None => (None, None),
+            };
             let message = DataUpdateMessage {
                 chart_session_id,
                 update_key: update_key.to_string(),
                 series_updates: None,
-                study_updates: Some(study_updates)
+                study_updates: if study_updates.is_empty() { None } else { Some(study_updates) },
+                strategy_report,
+                graphics,
+                bar_close_time: None
             };
             Ok(message)
         } else {
@@ -139,3 +167,44 @@ impl TryFrom<ParsedTradingViewMessage> for DataUpdateMessage {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn study_update(values: Vec<f64>, plots: Vec<PinePlotDefinition>) -> StudyUpdate {
+        StudyUpdate {
+            study_id: "st2".to_string(),
+            index: 0,
+            values,
+            plots,
+        }
+    }
+
+    #[test]
+    fn reads_plots_by_title_or_id() {
+        let update = study_update(vec![1700000000.0, 10.0, 20.0], vec![
+            PinePlotDefinition::new("plot_0", "line", "VWAP"),
+            PinePlotDefinition::new("plot_1", "line", "EMA 1"),
+        ]);
+        assert_eq!(update.timestamp(), Some(1700000000.0));
+        assert_eq!(update.plot("VWAP").unwrap(), 10.0);
+        assert_eq!(update.plot("plot_1").unwrap(), 20.0);
+        assert!(update.plot("EMA 2").is_err());
+    }
+
+    #[test]
+    fn plot_errors_without_matching_schema() {
+        assert!(study_update(vec![1700000000.0, 10.0], vec![]).plot("VWAP").is_err());
+        let update = study_update(vec![1700000000.0, 10.0, 20.0], vec![PinePlotDefinition::new("plot_0", "line", "VWAP")]);
+        assert!(update.plot("VWAP").is_err());
+    }
+
+    #[test]
+    fn malformed_study_rows_are_errors() {
+        let parsed_message = ParsedTradingViewMessage::from_string(r#"{"m":"du","p":["cs_000000000001",{"st2":{"st":[{"i":0,"v":[1700000000,"x"]}]}}]}"#);
+        assert!(parsed_message.is_err());
+        let parsed_message = ParsedTradingViewMessage::from_string(r#"{"m":"du","p":["cs_000000000001",{"st2":{"st":[{"v":[1700000000,1.0]}]}}]}"#);
+        assert!(parsed_message.is_err());
+    }
+}
@@ -0,0 +1,23 @@
+/// Error code TradingView puts first in `critical_error` / `protocol_error` params when it rejects (or stops accepting)
+/// the auth token.
+pub const UNAUTHORIZED_ACCESS: &str = "unauthorized_access";
+
+/// True when the error's code is `unauthorized_access`. Only the code is compared, the free text after it may echo
+/// anything (including the token).
+pub fn is_unauthorized_error(params: &[String]) -> bool {
+    params.first().is_some_and(|code| code == UNAUTHORIZED_ACCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_error_code_counts() {
+        let params = |values: &[&str]| values.iter().map(|value| value.to_string()).collect::<Vec<_>>();
+        assert!(is_unauthorized_error(&params(&["unauthorized_access", "auth token expired"])));
+        assert!(!is_unauthorized_error(&params(&["invalid_parameters", "set_auth_token", "unauthorized"])));
+        assert!(!is_unauthorized_error(&params(&["Unauthorized_Access"])));
+        assert!(!is_unauthorized_error(&[]));
+    }
+}
@@ -1,5 +1,7 @@
+pub mod bar;
 pub mod critical_error_message;
 pub mod data_update_message;
+pub mod error_codes;
 pub mod notify_user_message;
 pub mod protocol_error_message;
 pub mod quote_completed_message;
@@ -14,8 +16,10 @@ pub mod symbol_resolved_message;
 pub mod tickmark_update_message;
 pub mod timescale_updated_message;
 
+pub use bar::*;
 pub use critical_error_message::*;
 pub use data_update_message::*;
+pub use error_codes::*;
 pub use notify_user_message::*;
 pub use protocol_error_message::*;
 pub use quote_completed_message::*;
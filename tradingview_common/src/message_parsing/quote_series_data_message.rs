@@ -3,7 +3,7 @@ use std::error::Error;
 use miniserde::{json::Object, Deserialize, Serialize};
 use simple_error::{box_err, SimpleResult};
 
-use crate::{json_utilities, ParsedTradingViewMessage};
+use crate::{json_utilities, price_format, DataFreshness, MarketSession, ParsedTradingViewMessage, PriceFormat};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuoteSeriesDataUpdate {
@@ -22,6 +22,28 @@ pub struct QuoteSeriesDataUpdate {
     pub bid: Option<f64>,
     pub bid_size: Option<f64>,
     pub trade_loaded: Option<bool>,
+    pub update_mode: Option<String>,
+    pub current_session: Option<String>,
+    pub pricescale: Option<u64>,
+    pub minmov: Option<u64>,
+    pub minmove2: Option<u64>,
+    pub fractional: Option<bool>,
+}
+
+impl QuoteSeriesDataUpdate {
+    pub fn freshness(&self) -> Option<DataFreshness> {
+        self.update_mode.as_deref().map(DataFreshness::from_update_mode)
+    }
+
+    /// Exchange state from `current_session`.
+    pub fn market_session(&self) -> Option<MarketSession> {
+        self.current_session.as_deref().map(MarketSession::from_current_session)
+    }
+
+    /// Tick size and notation for `lp` / `bid` / `ask`, `None` until `pricescale` arrived.
+    pub fn price_format(&self) -> Option<PriceFormat> {
+        price_format(self.pricescale, self.minmov, self.minmove2, self.fractional)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,6 +91,15 @@ impl QuoteSeriesDataMessage {
 
             trade_loaded: if v.contains_key("trade_loaded") { Some(json_utilities::value_to_bool(v.get("trade_loaded").ok_or(box_err!("failed to get trade_loaded"))?)?) } else { None },
 
+            update_mode: if v.contains_key("update_mode") { Some(json_utilities::value_to_string(v.get("update_mode").ok_or(box_err!("failed to get update_mode"))?)?) } else { None },
+
+            current_session: if v.contains_key("current_session") && !json_utilities::is_null(&v, "current_session")? { Some(json_utilities::value_to_string(v.get("current_session").ok_or(box_err!("failed to get current_session"))?)?) } else { None },
+
+            pricescale: if v.contains_key("pricescale") { Some(json_utilities::value_to_u64_cast(v.get("pricescale").ok_or(box_err!("failed to get pricescale"))?)?) } else { None },
+            minmov: if v.contains_key("minmov") { Some(json_utilities::value_to_u64_cast(v.get("minmov").ok_or(box_err!("failed to get minmov"))?)?) } else { None },
+            minmove2: if v.contains_key("minmove2") { Some(json_utilities::value_to_u64_cast(v.get("minmove2").ok_or(box_err!("failed to get minmove2"))?)?) } else { None },
+            fractional: if v.contains_key("fractional") { Some(json_utilities::value_to_bool(v.get("fractional").ok_or(box_err!("failed to get fractional"))?)?) } else { None },
+
             // TODO: more fields?
         };
         Ok(QuoteSeriesDataMessage {
@@ -3,20 +3,37 @@ use std::error::Error;
 use miniserde::{json::Object, Deserialize, Serialize};
 use simple_error::{box_err, SimpleResult};
 
-use crate::ParsedTradingViewMessage;
+use crate::{json_utilities, DataFreshness, ParsedTradingViewMessage};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SeriesCompletedMessage {
-
+    pub chart_session_id: String,
+    pub series_id: String,
+    pub update_mode: Option<String>,
 }
 
 impl SeriesCompletedMessage {
     pub fn from_object(parsed_message: &Object) -> SimpleResult<Self> {
         log::debug!("series_completed = {parsed_message:?}");
+        let p = parsed_message.get("p").ok_or(box_err!("failed to get p"))?;
+        let p = json_utilities::value_to_array(p)?;
+        let chart_session_id = json_utilities::value_to_string(p.first().ok_or(box_err!("failed to get chart_session_id"))?)?;
+        let series_id = json_utilities::value_to_string(p.get(1).ok_or(box_err!("failed to get series_id"))?)?;
+        // ["cs_...", "sds_1", "streaming" | "delayed_streaming_900", "s1", {...}]
+        let update_mode = match p.get(2) {
+            Some(update_mode) => json_utilities::value_to_string(update_mode).ok(),
+            None => None,
+        };
         Ok(SeriesCompletedMessage {
-            
+            chart_session_id,
+            series_id,
+            update_mode
         })
     }
+
+    pub fn freshness(&self) -> Option<DataFreshness> {
+        self.update_mode.as_deref().map(DataFreshness::from_update_mode)
+    }
 }
 
 impl TryFrom<ParsedTradingViewMessage> for SeriesCompletedMessage {
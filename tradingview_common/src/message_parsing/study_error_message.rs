@@ -3,18 +3,28 @@ use std::error::Error;
 use miniserde::{json::Object, Deserialize, Serialize};
 use simple_error::{box_err, SimpleResult};
 
-use crate::ParsedTradingViewMessage;
+use crate::{json_utilities, ParsedTradingViewMessage};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StudyErrorMessage {
-
+    pub chart_session_id: String,
+    pub study_id: String,
+    pub reason: Option<String>,
 }
 
 impl StudyErrorMessage {
     pub fn from_object(parsed_message: &Object) -> SimpleResult<Self> {
         log::debug!("study_error = {parsed_message:?}");
+        let p = parsed_message.get("p").ok_or(box_err!("failed to get p"))?;
+        let p = json_utilities::value_to_array(p)?;
+        // ["cs_...", "st2", "st2_...", "reason"]
+        let chart_session_id = json_utilities::value_to_string(p.first().ok_or(box_err!("failed to get chart_session_id"))?)?;
+        let study_id = json_utilities::value_to_string(p.get(1).ok_or(box_err!("failed to get study_id"))?)?;
+        let reason = p.get(3).and_then(|reason| json_utilities::value_to_string(reason).ok());
         Ok(StudyErrorMessage {
-            
+            chart_session_id,
+            study_id,
+            reason
         })
     }
 }
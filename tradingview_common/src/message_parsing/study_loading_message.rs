@@ -3,18 +3,25 @@ use std::error::Error;
 use miniserde::{json::Object, Deserialize, Serialize};
 use simple_error::{box_err, SimpleResult};
 
-use crate::ParsedTradingViewMessage;
+use crate::{json_utilities, ParsedTradingViewMessage};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StudyLoadingMessage {
-
+    pub chart_session_id: String,
+    pub study_id: String,
 }
 
 impl StudyLoadingMessage {
     pub fn from_object(parsed_message: &Object) -> SimpleResult<Self> {
         log::debug!("study_loading = {parsed_message:?}");
+        let p = parsed_message.get("p").ok_or(box_err!("failed to get p"))?;
+        let p = json_utilities::value_to_array(p)?;
+        // ["cs_...", "st2", "st2_..."]
+        let chart_session_id = json_utilities::value_to_string(p.first().ok_or(box_err!("failed to get chart_session_id"))?)?;
+        let study_id = json_utilities::value_to_string(p.get(1).ok_or(box_err!("failed to get study_id"))?)?;
         Ok(StudyLoadingMessage {
-            
+            chart_session_id,
+            study_id
         })
     }
 }
@@ -3,20 +3,117 @@ use std::error::Error;
 use miniserde::{json::Object, Deserialize, Serialize};
 use simple_error::{box_err, SimpleResult};
 
-use crate::ParsedTradingViewMessage;
+use crate::{json_utilities, price_format, ContinuousContract, ParsedTradingViewMessage, PriceFormat, SessionCalendar, SymbolExpression};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SymbolResolvedMessage {
-
+    pub chart_session_id: String,
+    pub symbol_id: String,
+    pub timezone: Option<String>,
+    pub session: Option<String>, // the requested session, extended hours if asked for
+    pub session_regular: Option<String>,
+    pub session_premarket: Option<String>,
+    pub session_postmarket: Option<String>,
+    pub session_holidays: Option<String>, // 20241225,20250101
+    pub corrections: Option<String>, // special hours, 0930-1300:20241129;...
+    pub pro_name: Option<String>,
+    pub front_contract: Option<String>, // contract a continuous future (ES1!) currently maps to
+    pub pricescale: Option<u64>,
+    pub minmov: Option<u64>,
+    pub minmove2: Option<u64>,
+    pub fractional: Option<bool>,
 }
 
 impl SymbolResolvedMessage {
     pub fn from_object(parsed_message: &Object) -> SimpleResult<Self> {
         log::debug!("symbol_resolved = {parsed_message:?}");
+        let p = parsed_message.get("p").ok_or(box_err!("failed to get p"))?;
+        let p = json_utilities::value_to_array(p)?;
+        let chart_session_id = json_utilities::value_to_string(p.first().ok_or(box_err!("failed to get chart_session_id"))?)?;
+        let symbol_id = json_utilities::value_to_string(p.get(1).ok_or(box_err!("failed to get symbol_id"))?)?;
+        // ["cs_...", "sds_sym_1", {"timezone":"America/New_York","session":"0930-1600",...}]
+        let symbol_info = json_utilities::value_to_object(p.get(2).ok_or(box_err!("failed to get symbol_info"))?)?;
+        let timezone = symbol_info.get("timezone").and_then(|timezone| json_utilities::value_to_string(timezone).ok());
+        let session = symbol_info.get("session").and_then(|session| json_utilities::value_to_string(session).ok());
+        let session_regular = symbol_info.get("session_regular").and_then(|session| json_utilities::value_to_string(session).ok());
+        let session_premarket = symbol_info.get("session_premarket").and_then(|session| json_utilities::value_to_string(session).ok());
+        let session_postmarket = symbol_info.get("session_postmarket").and_then(|session| json_utilities::value_to_string(session).ok());
+        let session_holidays = symbol_info.get("session_holidays").and_then(|session_holidays| json_utilities::value_to_string(session_holidays).ok());
+        let corrections = symbol_info.get("corrections").and_then(|corrections| json_utilities::value_to_string(corrections).ok());
+        let pro_name = symbol_info.get("pro_name").and_then(|pro_name| json_utilities::value_to_string(pro_name).ok());
+        let front_contract = symbol_info.get("front_contract").and_then(|front_contract| json_utilities::value_to_string(front_contract).ok());
+        let pricescale = symbol_info.get("pricescale").and_then(|pricescale| json_utilities::value_to_u64_cast(pricescale).ok());
+        let minmov = symbol_info.get("minmov").and_then(|minmov| json_utilities::value_to_u64_cast(minmov).ok());
+        let minmove2 = symbol_info.get("minmove2").and_then(|minmove2| json_utilities::value_to_u64_cast(minmove2).ok());
+        let fractional = symbol_info.get("fractional").and_then(|fractional| json_utilities::value_to_bool(fractional).ok());
         Ok(SymbolResolvedMessage {
-            
+            chart_session_id,
+            symbol_id,
+            timezone,
+            session,
+            session_regular,
+            session_premarket,
+            session_postmarket,
+            session_holidays,
+            corrections,
+            pro_name,
+            front_contract,
+            pricescale,
+            minmov,
+            minmove2,
+            fractional,
         })
     }
+
+    pub fn continuous_contract(&self) -> Option<ContinuousContract> {
+        ContinuousContract::from_symbol(self.pro_name.as_deref()?)
+    }
+
+    /// Spread / ratio symbols resolve with the expression as their name.
+    pub fn expression(&self) -> Option<SymbolExpression> {
+        SymbolExpression::from_string(self.pro_name.as_deref()?).ok().filter(|expression| !expression.is_single_symbol())
+    }
+
+    /// Tick size and notation for this symbol's prices, `None` when `pricescale` is missing.
+    pub fn price_format(&self) -> Option<PriceFormat> {
+        price_format(self.pricescale, self.minmov, self.minmove2, self.fractional)
+    }
+
+    /// Hours the chart's bars are built from (`Timeframe::bar_end`): the requested `session`, extended hours if asked
+    /// for, as regular hours with the holidays, and the special hours when it is the regular session. Symbols without
+    /// a session trade around the clock.
+    pub fn chart_calendar(&self) -> SimpleResult<SessionCalendar> {
+        let timezone = self.timezone.as_deref().unwrap_or("Etc/UTC");
+        let session = self.session.as_deref().unwrap_or("24x7");
+        let mut calendar = SessionCalendar::from_string(session, timezone)?;
+        if let Some(session_holidays) = &self.session_holidays {
+            calendar = calendar.with_holidays(session_holidays)?;
+        }
+        if let Some(corrections) = self.corrections.as_ref().filter(|_| self.session_regular.as_deref().is_none_or(|regular| regular == session)) {
+            calendar = calendar.with_corrections(corrections)?;
+        }
+        Ok(calendar)
+    }
+
+    /// Regular / pre / post-market hours, holidays and special hours of this symbol.
+    pub fn session_calendar(&self) -> SimpleResult<SessionCalendar> {
+        let timezone = self.timezone.as_deref().unwrap_or("Etc/UTC");
+        let regular = self.session_regular.as_deref().or(self.session.as_deref()).unwrap_or("24x7");
+        let mut calendar = SessionCalendar::from_string(regular, timezone)?;
+        if let Some(session_premarket) = self.session_premarket.as_deref().filter(|session| !session.is_empty()) {
+            calendar = calendar.with_premarket(session_premarket)?;
+        }
+        if let Some(session_postmarket) = self.session_postmarket.as_deref().filter(|session| !session.is_empty()) {
+            calendar = calendar.with_postmarket(session_postmarket)?;
+        }
+        if let Some(session_holidays) = &self.session_holidays {
+            calendar = calendar.with_holidays(session_holidays)?;
+        }
+        if let Some(corrections) = &self.corrections {
+            calendar = calendar.with_corrections(corrections)?;
+        }
+        Ok(calendar)
+    }
 }
 
 impl TryFrom<ParsedTradingViewMessage> for SymbolResolvedMessage {
@@ -3,18 +3,7 @@ use std::error::Error;
 use miniserde::{json::Object, Deserialize, Serialize};
 use simple_error::{box_err, SimpleResult};
 
-use crate::{json_utilities, ParsedTradingViewMessage};
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TimescaleUpdate {
-    pub index: u64,
-    pub timestamp: u64,
-    pub open: f64,
-    pub high: f64,
-    pub low: f64,
-    pub close: f64,
-    pub volume: f64,
-}
+use crate::{json_utilities, Bar, ParsedTradingViewMessage, TimescaleUpdate};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimescaleUpdatedMessage {
@@ -24,6 +13,11 @@ pub struct TimescaleUpdatedMessage {
 }
 
 impl TimescaleUpdatedMessage {
+    /// Backfill bars, oldest first.
+    pub fn bars(&self) -> &[Bar] {
+        self.updates.as_deref().unwrap_or_default()
+    }
+
     pub fn from_object(parsed_message: &Object) -> SimpleResult<Self> {
         log::debug!("timescale_update parsed_message = {parsed_message:?}");
         let p = parsed_message.get("p").ok_or(box_err!("failed to get p"))?;
@@ -45,37 +39,7 @@ impl TimescaleUpdatedMessage {
             let update_value = json_utilities::value_to_object(update.get(update_key).ok_or(box_err!("failed to get update_key"))?)?;
             let s = update_value.get("s").ok_or(box_err!("failed to get s"))?;
             let s = json_utilities::value_to_array(s)?;
-            let timescale_updates = s.iter().map(|element| {
-                // value -> object
-                let element = json_utilities::value_to_object(&element).expect("failed to cast");
-                
-                // pluck i (index)
-                let i = element.get("i").expect("failed to get i");
-                let i = json_utilities::value_to_u64(i).expect("failed to cast");
-
-                // pluck v (values)
-                let v = element.get("v").expect("failed to get v");
-                let v = json_utilities::value_to_array(v).expect("failed to cast");
-
-                // pluck out of values
-                let timestamp = json_utilities::value_to_u64_cast(&v[0]).expect("failed to cast");
-                let open = json_utilities::value_to_f64(&v[1]).expect("failed to cast");
-                let high = json_utilities::value_to_f64(&v[2]).expect("failed to cast");
-                let low = json_utilities::value_to_f64(&v[3]).expect("failed to cast");
-                let close = json_utilities::value_to_f64(&v[4]).expect("failed to cast");
-                let volume = json_utilities::value_to_f64_cast(&v[5]).expect("failed to cast");
-
-                // return
-                TimescaleUpdate {
-                    index: i,
-                    timestamp,
-                    open,
-                    high,
-                    low,
-                    close,
-                    volume,
-                }
-            }).collect::<Vec<_>>();
+            let timescale_updates = Bar::from_array(&s)?;
             Ok(TimescaleUpdatedMessage {
                 chart_session_id,
                 update_key: Some(update_key.to_string()),
@@ -84,7 +84,7 @@ impl ParsedTradingViewMessage {
             Ok(ParsedTradingViewMessage::TickmarkUpdate(TickmarkUpdateMessage::from_object(&parsed_message)?))
         } else if message_type == "critical_error" {
             Ok(ParsedTradingViewMessage::CriticalError(CriticalErrorMessage::from_object(&parsed_message)?))
-        } else if message_type == "protcol_error" {
+        } else if message_type == "protocol_error" {
             Ok(ParsedTradingViewMessage::ProtocolError(ProtocolErrorMessage::from_object(&parsed_message)?))
         } else if message_type == "notify_user" {
             Ok(ParsedTradingViewMessage::NotifyUser(NotifyUserMessage::from_object(&parsed_message)?))
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use miniserde::json::{Array, Object, Value};
+use miniserde::{Deserialize, Serialize};
+use simple_error::{box_err, SimpleResult};
+
+use crate::{json_utilities, PineInputValue, PineStudy};
+
+/// An `in_N` input as declared by the script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PineInputDefinition {
+    pub id: String, // in_0
+    pub name: String, // title shown in the settings dialog
+    pub type_name: String, // integer, float, source, ...
+    pub default: Option<PineInputValue>, // None for types PineInputValue does not model
+    pub options: Vec<String>, // allowed values of dropdown inputs
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PinePlotDefinition {
+    pub id: String, // plot_0
+    pub plot_type: String, // line, shapes, ...
+    pub title: Option<String>,
+}
+
+impl PinePlotDefinition {
+    pub fn new(id: &str, plot_type: &str, title: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            plot_type: plot_type.to_string(),
+            title: Some(title.to_string()),
+        }
+    }
+
+    /// Matches either the plot id (`plot_0`) or its title.
+    pub fn matches(&self, name: &str) -> bool {
+        self.id == name || self.title.as_deref() == Some(name)
+    }
+}
+
+/// Script metadata from pine-facade `translate/{pineId}/{version}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PineScriptMetadata {
+    pub pine_id: String,
+    pub pine_version: String,
+    pub description: String,
+    pub text: String, // compiled study (ilTemplate)
+    pub inputs: Vec<PineInputDefinition>,
+    pub plots: Vec<PinePlotDefinition>,
+}
+
+impl PineScriptMetadata {
+    /// Parses `{"success":true,"result":{"metaInfo":{...},"ilTemplate":"..."}}`.
+    pub fn from_translate_response(body: &str) -> SimpleResult<Self> {
+        let body: Object = miniserde::json::from_str(body)?;
+        if let Some(Value::Bool(false)) = body.get("success") {
+            let reason = body.get("reason").and_then(|reason| json_utilities::value_to_string(reason).ok()).unwrap_or_default();
+            return Err(box_err!(format!("pine-facade translate failed: {reason}")));
+        }
+        let result = json_utilities::value_to_object(body.get("result").ok_or(box_err!("translate response has no result"))?)?;
+        let text = json_utilities::value_to_string(result.get("ilTemplate").ok_or(box_err!("translate response has no ilTemplate"))?)?;
+        let meta_info = json_utilities::value_to_object(result.get("metaInfo").ok_or(box_err!("translate response has no metaInfo"))?)?;
+        let string = |object: &Object, key: &str| object.get(key).and_then(|value| json_utilities::value_to_string(value).ok());
+
+        // text / pineId / pineVersion are declared as hidden inputs next to the in_N ones
+        let mut pine_id = None;
+        let mut pine_version = None;
+        let mut inputs = vec![];
+        let meta_inputs = match meta_info.get("inputs") {
+            Some(meta_inputs) => json_utilities::value_to_array(meta_inputs)?,
+            None => Array::new(),
+        };
+        for meta_input in &meta_inputs {
+            let meta_input = json_utilities::value_to_object(meta_input)?;
+            let id = string(&meta_input, "id").ok_or(box_err!("input has no id"))?;
+            match id.as_str() {
+                "pineId" => pine_id = string(&meta_input, "defval"),
+                "pineVersion" => pine_version = string(&meta_input, "defval"),
+                _ if id.starts_with("in_") => {
+                    let type_name = string(&meta_input, "type").ok_or(box_err!(format!("{id} has no type")))?;
+                    let default = meta_input.get("defval").and_then(|defval| PineInputValue::from_value(&type_name, defval).ok());
+                    let options = match meta_input.get("options") {
+                        Some(options) => json_utilities::value_to_array(options)?.iter().filter_map(|option| json_utilities::value_to_string(option).ok()).collect(),
+                        None => vec![],
+                    };
+                    inputs.push(PineInputDefinition {
+                        name: string(&meta_input, "name").unwrap_or_else(|| id.clone()),
+                        id,
+                        type_name,
+                        default,
+                        options,
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        // plot titles live in styles, keyed by plot id
+        let styles = match meta_info.get("styles") {
+            Some(styles) => json_utilities::value_to_object(styles)?,
+            None => Object::new(),
+        };
+        let mut plots = vec![];
+        let meta_plots = match meta_info.get("plots") {
+            Some(meta_plots) => json_utilities::value_to_array(meta_plots)?,
+            None => Array::new(),
+        };
+        for meta_plot in &meta_plots {
+            let meta_plot = json_utilities::value_to_object(meta_plot)?;
+            let id = string(&meta_plot, "id").ok_or(box_err!("plot has no id"))?;
+            let title = styles.get(&id).and_then(|style| json_utilities::value_to_object(style).ok()).and_then(|style| string(&style, "title"));
+            plots.push(PinePlotDefinition {
+                plot_type: string(&meta_plot, "type").unwrap_or_default(),
+                id,
+                title,
+            });
+        }
+
+        Ok(Self {
+            pine_id: pine_id.or_else(|| string(&meta_info, "scriptIdPart")).ok_or(box_err!("translate response has no pineId"))?,
+            pine_version: pine_version.or_else(|| string(&meta_info, "pine_version")).unwrap_or_else(|| "last".to_string()),
+            description: string(&meta_info, "description").unwrap_or_default(),
+            text,
+            inputs,
+            plots,
+        })
+    }
+
+    /// Input declared with this name (or id).
+    pub fn input(&self, name: &str) -> Option<&PineInputDefinition> {
+        self.inputs.iter().find(|input| input.name == name || input.id == name)
+    }
+
+    /// Plot declared with this title (or id).
+    pub fn plot(&self, name: &str) -> Option<&PinePlotDefinition> {
+        self.plots.iter().find(|plot| plot.matches(name))
+    }
+
+    /// Builds the study from script defaults overridden by input name (or `in_N` id).
+    /// Inputs of types `PineInputValue` does not model are left to the script default.
+    pub fn study(&self, values: &HashMap<String, PineInputValue>) -> SimpleResult<PineStudy> {
+        for name in values.keys() {
+            if self.input(name).is_none() {
+                return Err(box_err!(format!("{} has no input named {name}", self.pine_id)));
+            }
+        }
+        let mut study = PineStudy::new(&self.pine_id, &self.pine_version, &self.text);
+        for input in &self.inputs {
+            let value = values.get(&input.name).or_else(|| values.get(&input.id));
+            let value = match (value, &input.default) {
+                (Some(value), _) => coerce_input(input, value.clone())?,
+                (None, Some(default)) => default.clone(),
+                (None, None) => continue,
+            };
+            if !input.options.is_empty() {
+                if let PineInputValue::String(option) = &value {
+                    if !input.options.contains(option) {
+                        return Err(box_err!(format!("{option} is not an option of {}", input.name)));
+                    }
+                }
+            }
+            study = study.with_input_id(&input.id, value);
+        }
+        Ok(study.with_plots(self.plots.clone()))
+    }
+}
+
+fn coerce_input(input: &PineInputDefinition, value: PineInputValue) -> SimpleResult<PineInputValue> {
+    match (input.type_name.as_str(), value) {
+        ("float", PineInputValue::Integer(value)) => Ok(PineInputValue::Float(value as f64)),
+        (type_name, value) if value.type_name() == type_name || (type_name == "string" && value.type_name() == "text") => Ok(value),
+        (type_name, value) => Err(box_err!(format!("input {} is {type_name}, got {}", input.name, value.type_name()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSLATE_RESPONSE: &str = r#"{"success":true,"result":{"metaInfo":{"description":"Crossover","inputs":[{"id":"pineId","name":"pineId","type":"text","defval":"PUB;abc","isHidden":true},{"id":"pineVersion","name":"pineVersion","type":"text","defval":"2.0","isHidden":true},{"id":"in_0","name":"Length","type":"integer","defval":7}],"plots":[{"id":"plot_0","type":"line"},{"id":"plot_1","type":"shapes"}],"styles":{"plot_0":{"title":"EMA"}}},"ilTemplate":"bmI9Ks46_test"}}"#;
+
+    #[test]
+    fn parses_translate_response() {
+        let metadata = PineScriptMetadata::from_translate_response(TRANSLATE_RESPONSE).unwrap();
+        assert_eq!(metadata.pine_id, "PUB;abc");
+        assert_eq!(metadata.pine_version, "2.0");
+        assert_eq!(metadata.text, "bmI9Ks46_test");
+        assert_eq!(metadata.input("Length").map(|input| input.id.as_str()), Some("in_0"));
+        assert_eq!(metadata.plots.len(), 2);
+        assert_eq!(metadata.plots[1].title, None);
+    }
+
+    #[test]
+    fn finds_plots_by_title_or_id() {
+        let metadata = PineScriptMetadata::from_translate_response(TRANSLATE_RESPONSE).unwrap();
+        assert_eq!(metadata.plot("EMA").map(|plot| plot.id.as_str()), Some("plot_0"));
+        assert_eq!(metadata.plot("plot_1").map(|plot| plot.plot_type.as_str()), Some("shapes"));
+        assert!(metadata.plot("VWAP").is_none());
+    }
+
+    #[test]
+    fn rejects_failed_translate() {
+        assert!(PineScriptMetadata::from_translate_response(r#"{"success":false,"reason":"no such script"}"#).is_err());
+    }
+}
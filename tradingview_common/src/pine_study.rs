@@ -0,0 +1,164 @@
+use std::fmt;
+
+use miniserde::json::{Number, Object, Value};
+use simple_error::{box_err, SimpleResult};
+
+use crate::{json_utilities, IndicatorConfig, PinePlotDefinition};
+
+/// Typed value of a Pine script input (`t` in the `in_N` objects).
+#[derive(Debug, Clone, PartialEq)]
+pub enum PineInputValue {
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Source(String), // close, hl2, ...
+    Resolution(String), // 5, 60, 1D, ...
+    Session(String), // 0930-1600
+    Color(String), // #RRGGBB
+}
+
+impl PineInputValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            PineInputValue::Integer(_) => "integer",
+            PineInputValue::Float(_) => "float",
+            PineInputValue::Bool(_) => "bool",
+            PineInputValue::String(_) => "text",
+            PineInputValue::Source(_) => "source",
+            PineInputValue::Resolution(_) => "resolution",
+            PineInputValue::Session(_) => "session",
+            PineInputValue::Color(_) => "color",
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            PineInputValue::Integer(value) if *value < 0 => Value::Number(Number::I64(*value)),
+            PineInputValue::Integer(value) => Value::Number(Number::U64(*value as u64)),
+            PineInputValue::Float(value) => Value::Number(Number::F64(*value)),
+            PineInputValue::Bool(value) => Value::Bool(*value),
+            PineInputValue::String(value)
+            | PineInputValue::Source(value)
+            | PineInputValue::Resolution(value)
+            | PineInputValue::Session(value)
+            | PineInputValue::Color(value) => Value::String(value.clone()),
+        }
+    }
+
+    pub(crate) fn from_value(type_name: &str, value: &Value) -> SimpleResult<Self> {
+        match type_name {
+            "integer" => Ok(PineInputValue::Integer(json_utilities::value_to_f64_cast(value)? as i64)),
+            "float" => Ok(PineInputValue::Float(json_utilities::value_to_f64_cast(value)?)),
+            "bool" => Ok(PineInputValue::Bool(json_utilities::value_to_bool(value)?)),
+            "text" | "string" => Ok(PineInputValue::String(json_utilities::value_to_string(value)?)),
+            "source" => Ok(PineInputValue::Source(json_utilities::value_to_string(value)?)),
+            "resolution" => Ok(PineInputValue::Resolution(json_utilities::value_to_string(value)?)),
+            "session" => Ok(PineInputValue::Session(json_utilities::value_to_string(value)?)),
+            "color" => Ok(PineInputValue::Color(json_utilities::value_to_string(value)?)),
+            _ => Err(box_err!(format!("unknown pine input type {type_name}"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PineInput {
+    pub id: String, // in_0, in_1, ...
+    pub value: PineInputValue,
+}
+
+/// Value passed to `create_study` for `Script@tv-scripting-101!`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PineStudy {
+    pub pine_id: String, // PUB;... or USER;...
+    pub pine_version: String,
+    pub text: String, // encrypted source blob from pine-facade
+    pub inputs: Vec<PineInput>,
+    pub plots: Vec<PinePlotDefinition>, // output layout, not sent to the server
+}
+
+impl PineStudy {
+    pub fn new(pine_id: &str, pine_version: &str, text: &str) -> Self {
+        Self {
+            pine_id: pine_id.to_string(),
+            pine_version: pine_version.to_string(),
+            text: text.to_string(),
+            inputs: vec![],
+            plots: vec![],
+        }
+    }
+
+    /// Adds the next positional input (`in_0`, `in_1`, ...).
+    pub fn with_input(mut self, value: PineInputValue) -> Self {
+        let id = format!("in_{}", self.inputs.len());
+        self.inputs.push(PineInput { id, value });
+        self
+    }
+
+    /// Sets an input by id, replacing any existing value.
+    pub fn with_input_id(mut self, id: &str, value: PineInputValue) -> Self {
+        match self.inputs.iter_mut().find(|input| input.id == id) {
+            Some(input) => input.value = value,
+            None => self.inputs.push(PineInput { id: id.to_string(), value }),
+        }
+        self
+    }
+
+    /// Output layout used to address study update values by plot id or title.
+    pub fn with_plots(mut self, plots: Vec<PinePlotDefinition>) -> Self {
+        self.plots = plots;
+        self
+    }
+
+    /// Entry for `TradingViewClientConfig::indicators`.
+    pub fn to_indicator(&self) -> IndicatorConfig {
+        IndicatorConfig {
+            name: "Script@tv-scripting-101!".to_string(),
+            value: self.to_string(),
+            plots: self.plots.clone(),
+        }
+    }
+
+    pub fn from_string(value: &str) -> SimpleResult<Self> {
+        let object: Object = miniserde::json::from_str(value)?;
+        Self::from_object(&object)
+    }
+
+    pub fn from_object(object: &Object) -> SimpleResult<Self> {
+        let field = |key: &str| -> SimpleResult<String> {
+            json_utilities::value_to_string(object.get(key).ok_or(box_err!(format!("pine study has no {key}")))?)
+        };
+        let mut study = Self::new(&field("pineId")?, &field("pineVersion")?, &field("text")?);
+        let mut ids = object.keys().filter(|key| key.starts_with("in_")).cloned().collect::<Vec<_>>();
+        ids.sort_by_key(|id| id[3..].parse::<usize>().unwrap_or(usize::MAX));
+        for id in ids {
+            let input = json_utilities::value_to_object(object.get(&id).ok_or(box_err!("failed to get input"))?)?;
+            let type_name = json_utilities::value_to_string(input.get("t").ok_or(box_err!(format!("{id} has no t")))?)?;
+            let value = input.get("v").ok_or(box_err!(format!("{id} has no v")))?;
+            study.inputs.push(PineInput { id, value: PineInputValue::from_value(&type_name, value)? });
+        }
+        Ok(study)
+    }
+
+    pub fn to_object(&self) -> Object {
+        let mut object = Object::new();
+        object.insert("text".to_string(), Value::String(self.text.clone()));
+        object.insert("pineId".to_string(), Value::String(self.pine_id.clone()));
+        object.insert("pineVersion".to_string(), Value::String(self.pine_version.clone()));
+        for input in &self.inputs {
+            let mut value = Object::new();
+            value.insert("v".to_string(), input.value.to_value());
+            value.insert("f".to_string(), Value::Bool(true));
+            value.insert("t".to_string(), Value::String(input.value.type_name().to_string()));
+            object.insert(input.id.clone(), Value::Object(value));
+        }
+        object
+    }
+}
+
+/// JSON as embedded in `create_study` (what `IndicatorConfig::value` holds).
+impl fmt::Display for PineStudy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", miniserde::json::to_string(&Value::Object(self.to_object())))
+    }
+}
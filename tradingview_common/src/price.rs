@@ -0,0 +1,275 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Neg;
+
+use simple_error::{box_err, SimpleResult};
+
+use crate::Bar;
+
+/// How a symbol quotes prices, from `pricescale` / `minmov` / `minmove2` / `fractional` of `symbol_resolved` or quotes.
+///
+/// Prices move in units of `1 / pricescale`, the tick is `minmov` units. Fractional symbols (bonds) are written in
+/// 1/32s, `minmove2` splits those further (`4` for quarters of 1/32 on `pricescale` 128).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceFormat {
+    pub pricescale: u64,
+    pub minmov: u64,
+    pub minmove2: u64,
+    pub fractional: bool,
+}
+
+impl PriceFormat {
+    pub fn new(pricescale: u64, minmov: u64) -> Self {
+        Self {
+            pricescale: pricescale.max(1),
+            minmov: minmov.max(1),
+            minmove2: 0,
+            fractional: false,
+        }
+    }
+
+    pub fn with_fractional(self, minmove2: u64) -> Self {
+        Self {
+            minmove2,
+            fractional: true,
+            ..self
+        }
+    }
+
+    pub fn tick_size(&self) -> f64 {
+        self.minmov as f64 / self.pricescale as f64
+    }
+
+    /// Decimals that write every unit exactly (5 for 1/32s), capped at 18.
+    pub fn decimals(&self) -> u32 {
+        (0..=18u32)
+            .find(|decimals| 10u64.pow(*decimals) % self.pricescale == 0)
+            .unwrap_or_else(|| self.pricescale.to_string().len() as u32)
+    }
+
+    /// Nearest unit to `value`; `None` for NaN / infinity.
+    pub fn price(&self, value: f64) -> Option<Price> {
+        if !value.is_finite() {
+            return None;
+        }
+        Some(Price {
+            units: (value * self.pricescale as f64).round() as i64,
+            format: *self,
+        })
+    }
+
+    /// Like `price`, but errors unless `value` is a whole number of ticks.
+    pub fn validate(&self, value: f64) -> SimpleResult<Price> {
+        let scaled = value * self.pricescale as f64;
+        let price = self.price(value).ok_or(box_err!(format!("{value} is not a price")))?;
+        if (scaled - price.units as f64).abs() > 1e-6 || !price.is_on_tick() {
+            return Err(box_err!(format!("{value} is not a multiple of the tick size {}", self.tick_size())));
+        }
+        Ok(price)
+    }
+
+    /// `value` written with `decimals()` digits, empty for NaN / infinity. Fractional symbols are written as decimals
+    /// too, use `Price`'s `Display` for the 1/32 notation.
+    pub fn format(&self, value: f64) -> String {
+        self.price(value).map(|price| price.to_decimal_string()).unwrap_or_default()
+    }
+}
+
+/// A price as a whole number of `1 / pricescale` units, so tick arithmetic is exact.
+///
+/// Prices of different formats do not mix: they do not compare and `checked_add` / `checked_sub` error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Price {
+    pub units: i64,
+    pub format: PriceFormat,
+}
+
+impl Price {
+    pub fn from_ticks(ticks: i64, format: PriceFormat) -> Self {
+        Self {
+            units: ticks * format.minmov as i64,
+            format,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.units as f64 / self.format.pricescale as f64
+    }
+
+    pub fn is_on_tick(&self) -> bool {
+        self.units % self.format.minmov as i64 == 0
+    }
+
+    /// Nearest tick, halves away from zero.
+    pub fn round_to_tick(&self) -> Self {
+        let minmov = self.format.minmov as i64;
+        let remainder = self.units % minmov;
+        let units = if remainder.abs() * 2 >= minmov {
+            self.units - remainder + remainder.signum() * minmov
+        } else {
+            self.units - remainder
+        };
+        Self { units, ..*self }
+    }
+
+    /// Whole ticks from zero, rounded to the nearest tick first.
+    pub fn ticks(&self) -> i64 {
+        self.round_to_tick().units / self.format.minmov as i64
+    }
+
+    pub fn add_ticks(&self, ticks: i64) -> Self {
+        Self {
+            units: self.units + ticks * self.format.minmov as i64,
+            ..*self
+        }
+    }
+
+    /// Errors when `other` has a different format, rather than mixing units of different size.
+    pub fn checked_add(&self, other: &Price) -> SimpleResult<Price> {
+        if self.format != other.format {
+            return Err(box_err!(format!("adding prices with different formats ({:?} and {:?})", self.format, other.format)));
+        }
+        Ok(Price { units: self.units + other.units, ..*self })
+    }
+
+    /// Errors when `other` has a different format, rather than mixing units of different size.
+    pub fn checked_sub(&self, other: &Price) -> SimpleResult<Price> {
+        if self.format != other.format {
+            return Err(box_err!(format!("subtracting prices with different formats ({:?} and {:?})", self.format, other.format)));
+        }
+        Ok(Price { units: self.units - other.units, ..*self })
+    }
+
+    /// Plain decimal with `PriceFormat::decimals()` digits, also for fractional symbols (CSV output).
+    pub fn to_decimal_string(&self) -> String {
+        let decimals = self.format.decimals();
+        let sign = if self.units < 0 { "-" } else { "" };
+        let units = self.units.unsigned_abs();
+        let whole = units / self.format.pricescale;
+        if decimals == 0 {
+            return format!("{sign}{whole}");
+        }
+        let fraction = (units % self.format.pricescale) as u128 * 10u128.pow(decimals) / self.format.pricescale as u128;
+        format!("{sign}{whole}.{fraction:0width$}", width = decimals as usize)
+    }
+}
+
+/// TradingView notation: decimals, or `119'16` (16/32) and `110'165` (16.5/32) for fractional symbols.
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.format.fractional {
+            return write!(f, "{}", self.to_decimal_string());
+        }
+        let sign = if self.units < 0 { "-" } else { "" };
+        let units = self.units.unsigned_abs();
+        let pricescale = self.format.pricescale;
+        let whole = units / pricescale;
+        let remainder = units % pricescale;
+        match self.format.minmove2 {
+            0 | 1 => {
+                let width = (pricescale - 1).to_string().len();
+                write!(f, "{sign}{whole}'{remainder:0width$}")
+            }
+            minmove2 => {
+                let width = (pricescale / minmove2 - 1).to_string().len();
+                let part = remainder / minmove2;
+                let sub_part = (remainder % minmove2) * 10 / minmove2;
+                write!(f, "{sign}{whole}'{part:0width$}{sub_part}")
+            }
+        }
+    }
+}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.format == other.format).then(|| self.units.cmp(&other.units))
+    }
+}
+
+impl Neg for Price {
+    type Output = Price;
+
+    fn neg(self) -> Price {
+        Price { units: -self.units, ..self }
+    }
+}
+
+/// A bar's OHLC in a symbol's `PriceFormat`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BarPrices {
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+}
+
+impl Bar {
+    /// `None` if a price is NaN / infinite.
+    pub fn prices(&self, price_format: &PriceFormat) -> Option<BarPrices> {
+        Some(BarPrices {
+            open: price_format.price(self.open)?,
+            high: price_format.price(self.high)?,
+            low: price_format.price(self.low)?,
+            close: price_format.price(self.close)?,
+        })
+    }
+}
+
+/// `PriceFormat` from the optional fields of `symbol_resolved` / quote messages.
+pub(crate) fn price_format(pricescale: Option<u64>, minmov: Option<u64>, minmove2: Option<u64>, fractional: Option<bool>) -> Option<PriceFormat> {
+    let price_format = PriceFormat::new(pricescale?, minmov.unwrap_or(1));
+    match fractional {
+        Some(true) => Some(price_format.with_fractional(minmove2.unwrap_or(0))),
+        _ => Some(price_format),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimals_follow_the_pricescale() {
+        assert_eq!(PriceFormat::new(100, 1).decimals(), 2);
+        assert_eq!(PriceFormat::new(100000, 1).decimals(), 5);
+        assert_eq!(PriceFormat::new(32, 1).decimals(), 5);
+        assert_eq!(PriceFormat::new(1, 1).decimals(), 0);
+        assert_eq!(PriceFormat::new(100000000, 1).format(0.00001234), "0.00001234");
+        assert_eq!(PriceFormat::new(100, 1).format(-1.5), "-1.50");
+        assert_eq!(PriceFormat::new(100, 1).format(f64::INFINITY), "");
+    }
+
+    #[test]
+    fn fractional_prices() {
+        let thirty_seconds = PriceFormat::new(32, 1).with_fractional(0);
+        assert_eq!(thirty_seconds.price(119.5).unwrap().to_string(), "119'16");
+        assert_eq!(thirty_seconds.format(119.5), "119.50000");
+        let quarters = PriceFormat::new(128, 1).with_fractional(4);
+        assert_eq!(quarters.price(110.515625).unwrap().to_string(), "110'165");
+    }
+
+    #[test]
+    fn ticks() {
+        let quarter_ticks = PriceFormat::new(100, 25);
+        assert_eq!(quarter_ticks.tick_size(), 0.25);
+        assert!(quarter_ticks.validate(4500.25).is_ok());
+        assert!(quarter_ticks.validate(4500.1).is_err());
+        let price = quarter_ticks.price(4500.13).unwrap();
+        assert_eq!(price.round_to_tick().to_f64(), 4500.25);
+        assert_eq!(price.add_ticks(-1).round_to_tick().to_f64(), 4500.0);
+        assert_eq!(quarter_ticks.price(4500.0).unwrap().ticks(), 18000);
+        let spread = quarter_ticks.price(4500.5).unwrap().checked_sub(&quarter_ticks.price(4500.0).unwrap()).unwrap();
+        assert_eq!(spread.ticks(), 2);
+        assert_eq!(spread.checked_add(&spread).unwrap().ticks(), 4);
+        assert_eq!(Price::from_ticks(3, quarter_ticks).to_f64(), 0.75);
+    }
+
+    #[test]
+    fn different_formats_do_not_mix() {
+        let cents = PriceFormat::new(100, 1).price(1.0).unwrap();
+        let ticks = PriceFormat::new(4, 1).price(1.0).unwrap();
+        assert_eq!(cents.partial_cmp(&ticks), None);
+        assert!(cents.checked_add(&ticks).is_err());
+        assert!(cents.checked_sub(&ticks).is_err());
+    }
+}
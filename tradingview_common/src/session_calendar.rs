@@ -0,0 +1,404 @@
+use simple_error::{box_err, SimpleResult};
+use time::{Date, Month, OffsetDateTime, Weekday};
+
+use crate::exchange_time::day_start;
+use crate::{add_days, utc_offset};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+// how far next_open / next_close look ahead
+const MAX_LOOKAHEAD_DAYS: i64 = 400;
+
+/// Part of the trading day a timestamp falls in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketSession {
+    PreMarket,
+    Regular,
+    PostMarket,
+    Closed,
+}
+
+impl MarketSession {
+    pub fn from_string(value: &str) -> SimpleResult<Self> {
+        match value {
+            "pre" => Ok(MarketSession::PreMarket),
+            "regular" => Ok(MarketSession::Regular),
+            "post" => Ok(MarketSession::PostMarket),
+            "closed" => Ok(MarketSession::Closed),
+            _ => Err(box_err!(format!("unknown market session {value}"))),
+        }
+    }
+
+    /// The `current_session` quote field (`pre_market`, `market`, `post_market`, `out_of_session`, ...).
+    pub fn from_current_session(value: &str) -> Self {
+        match value {
+            "pre_market" => MarketSession::PreMarket,
+            "market" => MarketSession::Regular,
+            "post_market" => MarketSession::PostMarket,
+            _ => MarketSession::Closed,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarketSession::PreMarket => "pre",
+            MarketSession::Regular => "regular",
+            MarketSession::PostMarket => "post",
+            MarketSession::Closed => "closed",
+        }
+    }
+}
+
+/// One range of a TradingView session string (`0930-1600`, `1700-1600:23456`, `24x7`) and the days it trades.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionRange {
+    pub timezone: String,
+    pub open: i64, // seconds after local midnight
+    pub close: i64, // seconds after local midnight, <= open when the range opens the evening before
+    pub days: Vec<Weekday>, // days the range closes on
+}
+
+impl SessionRange {
+    pub fn from_string(range: &str, timezone: &str) -> SimpleResult<Self> {
+        // validate timezone up front
+        utc_offset(timezone, 0)?;
+        if range == "24x7" {
+            return Ok(Self {
+                timezone: timezone.to_string(),
+                open: 0,
+                close: SECONDS_PER_DAY,
+                days: vec![Weekday::Monday, Weekday::Tuesday, Weekday::Wednesday, Weekday::Thursday, Weekday::Friday, Weekday::Saturday, Weekday::Sunday],
+            });
+        }
+        let (hours, days) = match range.split_once(':') {
+            Some((hours, days)) => (hours, days),
+            None => (range, "23456"),
+        };
+        let (open, close) = hours.split_once('-').ok_or(box_err!(format!("invalid session range {range}")))?;
+        let days = days
+            .chars()
+            .map(|day| match day {
+                '1' => Ok(Weekday::Sunday),
+                '2' => Ok(Weekday::Monday),
+                '3' => Ok(Weekday::Tuesday),
+                '4' => Ok(Weekday::Wednesday),
+                '5' => Ok(Weekday::Thursday),
+                '6' => Ok(Weekday::Friday),
+                '7' => Ok(Weekday::Saturday),
+                _ => Err(box_err!(format!("invalid session day in {range}"))),
+            })
+            .collect::<SimpleResult<Vec<_>>>()?;
+        let close = match parse_hhmm(close)? {
+            0 => SECONDS_PER_DAY,
+            close => close,
+        };
+        Ok(Self {
+            timezone: timezone.to_string(),
+            open: parse_hhmm(open)?,
+            close,
+            days,
+        })
+    }
+
+    pub fn is_overnight(&self) -> bool {
+        self.close <= self.open
+    }
+
+    pub fn is_trading_day(&self, date: Date) -> bool {
+        self.days.contains(&date.weekday())
+    }
+
+    /// Unix timestamp the range opens at on a trading day (the evening before for overnight ranges).
+    pub fn session_open(&self, date: Date) -> SimpleResult<i64> {
+        let overnight_shift = if self.is_overnight() { SECONDS_PER_DAY } else { 0 };
+        self.local_to_utc(day_start(date) + self.open - overnight_shift)
+    }
+
+    /// Unix timestamp the range closes at on a trading day.
+    pub fn session_close(&self, date: Date) -> SimpleResult<i64> {
+        self.local_to_utc(day_start(date) + self.close)
+    }
+
+    fn local_to_utc(&self, local: i64) -> SimpleResult<i64> {
+        let offset = utc_offset(&self.timezone, local - utc_offset(&self.timezone, local)?)?;
+        Ok(local - offset)
+    }
+}
+
+/// A symbol's trading calendar: regular, pre- and post-market hours, holidays and days with special hours.
+///
+/// Built from `symbol_resolved` (`SymbolResolvedMessage::session_calendar()`), or by hand from session strings. Each
+/// comma separated range is kept on its own, so lunch breaks (`0900-1130,1230-1500`) count as closed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionCalendar {
+    pub timezone: String,
+    pub regular: Vec<SessionRange>,
+    pub premarket: Vec<SessionRange>,
+    pub postmarket: Vec<SessionRange>,
+    pub holidays: Vec<Date>, // no session at all
+    pub corrections: Vec<(Date, Vec<SessionRange>)>, // regular hours replaced on these dates (early closes, ...)
+}
+
+impl SessionCalendar {
+    /// Regular hours only, from a session string such as `0930-1600`, `0900-1130,1230-1500:23456` or `24x7`.
+    pub fn from_string(session: &str, timezone: &str) -> SimpleResult<Self> {
+        Ok(Self {
+            timezone: timezone.to_string(),
+            regular: parse_ranges(session, timezone)?,
+            premarket: vec![],
+            postmarket: vec![],
+            holidays: vec![],
+            corrections: vec![],
+        })
+    }
+
+    pub fn with_premarket(self, session: &str) -> SimpleResult<Self> {
+        let premarket = parse_ranges(session, &self.timezone)?;
+        Ok(Self { premarket, ..self })
+    }
+
+    pub fn with_postmarket(self, session: &str) -> SimpleResult<Self> {
+        let postmarket = parse_ranges(session, &self.timezone)?;
+        Ok(Self { postmarket, ..self })
+    }
+
+    /// `session_holidays` format, `20241225,20250101`.
+    pub fn with_holidays(self, holidays: &str) -> SimpleResult<Self> {
+        let holidays = holidays.split(',').filter(|date| !date.is_empty()).map(parse_date).collect::<SimpleResult<Vec<_>>>()?;
+        Ok(Self { holidays, ..self })
+    }
+
+    /// `corrections` format, `0930-1300:20241129,20241224;1000-1400:20250102`.
+    pub fn with_corrections(self, corrections: &str) -> SimpleResult<Self> {
+        let mut parsed = vec![];
+        for correction in corrections.split(';').filter(|correction| !correction.is_empty()) {
+            let (hours, dates) = correction.split_once(':').ok_or(box_err!(format!("invalid session correction {correction}")))?;
+            let sessions = parse_ranges(hours, &self.timezone)?;
+            for date in dates.split(',') {
+                parsed.push((parse_date(date)?, sessions.clone()));
+            }
+        }
+        Ok(Self { corrections: parsed, ..self })
+    }
+
+    pub fn is_holiday(&self, date: Date) -> bool {
+        self.holidays.contains(&date)
+    }
+
+    /// Session windows (`open..close` unix timestamps) of a trading day, by open time.
+    pub fn sessions_on(&self, date: Date) -> SimpleResult<Vec<(MarketSession, i64, i64)>> {
+        let mut windows = vec![];
+        if self.is_holiday(date) {
+            return Ok(windows);
+        }
+        let regular = match self.corrections.iter().find(|(correction_date, _)| *correction_date == date) {
+            Some((_, sessions)) => sessions.iter().collect::<Vec<_>>(),
+            None => self.regular.iter().filter(|session| session.is_trading_day(date)).collect::<Vec<_>>(),
+        };
+        let extended = self.premarket.iter().map(|session| (MarketSession::PreMarket, session))
+            .chain(self.postmarket.iter().map(|session| (MarketSession::PostMarket, session)))
+            .filter(|(_, session)| session.is_trading_day(date));
+        for (market_session, session) in regular.into_iter().map(|session| (MarketSession::Regular, session)).chain(extended) {
+            windows.push((market_session, session.session_open(date)?, session.session_close(date)?));
+        }
+        windows.sort_by_key(|(_, open, _)| *open);
+        Ok(windows)
+    }
+
+    /// Regular session windows (`open..close` unix timestamps) of a trading day, by open time.
+    pub fn regular_sessions_on(&self, date: Date) -> SimpleResult<Vec<(i64, i64)>> {
+        Ok(self.sessions_on(date)?
+            .into_iter()
+            .filter(|(market_session, _, _)| *market_session == MarketSession::Regular)
+            .map(|(_, open, close)| (open, close))
+            .collect())
+    }
+
+    /// Trading day (the date its session closes on) of a timestamp in regular hours, its exchange date otherwise.
+    pub fn trading_day(&self, timestamp: i64) -> SimpleResult<Date> {
+        let date = self.local_date(timestamp)?;
+        // overnight sessions belong to the next day
+        for offset in [0, 1, -1] {
+            let trading_day = add_days(date, offset)?;
+            if self.regular_sessions_on(trading_day)?.iter().any(|(open, close)| timestamp >= *open && timestamp < *close) {
+                return Ok(trading_day);
+            }
+        }
+        Ok(date)
+    }
+
+    /// Session a timestamp falls in; regular hours win where sessions overlap.
+    pub fn session_at(&self, timestamp: i64) -> SimpleResult<MarketSession> {
+        let date = self.local_date(timestamp)?;
+        let mut found = MarketSession::Closed;
+        // overnight sessions belong to the next day
+        for offset in -1..=1 {
+            for (market_session, open, close) in self.sessions_on(add_days(date, offset)?)? {
+                if timestamp >= open && timestamp < close {
+                    if market_session == MarketSession::Regular {
+                        return Ok(market_session);
+                    }
+                    found = market_session;
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Regular session open at `timestamp`.
+    pub fn is_open(&self, timestamp: i64) -> SimpleResult<bool> {
+        Ok(self.session_at(timestamp)? == MarketSession::Regular)
+    }
+
+    /// Next regular session open after `timestamp`.
+    pub fn next_open(&self, timestamp: i64) -> SimpleResult<i64> {
+        self.next_boundary(timestamp, |(_, open, _)| open)
+    }
+
+    /// Next regular session close after `timestamp`, the current session's close while open.
+    pub fn next_close(&self, timestamp: i64) -> SimpleResult<i64> {
+        self.next_boundary(timestamp, |(_, _, close)| close)
+    }
+
+    fn next_boundary(&self, timestamp: i64, boundary: fn((MarketSession, i64, i64)) -> i64) -> SimpleResult<i64> {
+        let date = self.local_date(timestamp)?;
+        for offset in -1..MAX_LOOKAHEAD_DAYS {
+            let next = self.sessions_on(add_days(date, offset)?)?
+                .into_iter()
+                .filter(|(market_session, _, _)| *market_session == MarketSession::Regular)
+                .map(boundary)
+                .filter(|time| *time > timestamp)
+                .min();
+            if let Some(next) = next {
+                return Ok(next);
+            }
+        }
+        Err(box_err!(format!("no regular session within {MAX_LOOKAHEAD_DAYS} days of {timestamp}")))
+    }
+
+    fn local_date(&self, timestamp: i64) -> SimpleResult<Date> {
+        let local = timestamp + utc_offset(&self.timezone, timestamp)?;
+        Ok(OffsetDateTime::from_unix_timestamp(local)?.date())
+    }
+}
+
+// "0400-0930,0930-1600:23456|1000-1400:7" -> one SessionRange per range with its group's days
+fn parse_ranges(session: &str, timezone: &str) -> SimpleResult<Vec<SessionRange>> {
+    if session == "24x7" {
+        return Ok(vec![SessionRange::from_string(session, timezone)?]);
+    }
+    let mut sessions = vec![];
+    for group in session.split('|') {
+        let (hours, days) = match group.split_once(':') {
+            Some((hours, days)) => (hours, Some(days)),
+            None => (group, None),
+        };
+        for range in hours.split(',') {
+            let range = match days {
+                Some(days) => format!("{range}:{days}"),
+                None => range.to_string(),
+            };
+            sessions.push(SessionRange::from_string(&range, timezone)?);
+        }
+    }
+    Ok(sessions)
+}
+
+fn parse_hhmm(value: &str) -> SimpleResult<i64> {
+    if value.len() != 4 {
+        return Err(box_err!(format!("invalid session time {value}")));
+    }
+    let hours = value[0..2].parse::<i64>().map_err(|_| box_err!(format!("invalid session time {value}")))?;
+    let minutes = value[2..4].parse::<i64>().map_err(|_| box_err!(format!("invalid session time {value}")))?;
+    Ok(hours * 3600 + minutes * 60)
+}
+
+fn parse_date(value: &str) -> SimpleResult<Date> {
+    let invalid = || box_err!(format!("invalid date {value}"));
+    if value.len() != 8 {
+        return Err(invalid());
+    }
+    let year = value[0..4].parse::<i32>().map_err(|_| invalid())?;
+    let month = value[4..6].parse::<u8>().map_err(|_| invalid())?;
+    let day = value[6..8].parse::<u8>().map_err(|_| invalid())?;
+    Ok(Date::from_calendar_date(year, Month::try_from(month)?, day)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-07-15 (monday) 09:00 Asia/Tokyo
+    const TOKYO_OPEN: i64 = 1_721_001_600;
+
+    fn monday() -> Date {
+        Date::from_calendar_date(2024, Month::July, 15).unwrap()
+    }
+
+    #[test]
+    fn session_range_parses_hours_and_days() {
+        let range = SessionRange::from_string("0930-1600:23456", "America/New_York").unwrap();
+        assert_eq!((range.open, range.close), (9 * 3600 + 1800, 16 * 3600));
+        assert_eq!(range.days, vec![Weekday::Monday, Weekday::Tuesday, Weekday::Wednesday, Weekday::Thursday, Weekday::Friday]);
+        assert!(!range.is_overnight());
+        assert_eq!(SessionRange::from_string("1800-0000:1", "Etc/UTC").unwrap().close, 24 * 3600);
+        assert!(SessionRange::from_string("1700-1600", "America/Chicago").unwrap().is_overnight());
+        assert!(SessionRange::from_string("0930", "America/New_York").is_err());
+        assert!(SessionRange::from_string("0930-1600:9", "America/New_York").is_err());
+        assert!(SessionRange::from_string("0930-1600", "Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn lunch_break_is_closed() {
+        let calendar = SessionCalendar::from_string("0900-1130,1230-1500", "Asia/Tokyo").unwrap();
+        assert_eq!(calendar.regular_sessions_on(monday()).unwrap(), vec![
+            (TOKYO_OPEN, TOKYO_OPEN + 9000),
+            (TOKYO_OPEN + 12600, TOKYO_OPEN + 21600),
+        ]);
+        assert_eq!(calendar.session_at(TOKYO_OPEN + 3600).unwrap(), MarketSession::Regular);
+        assert_eq!(calendar.session_at(TOKYO_OPEN + 10800).unwrap(), MarketSession::Closed);
+        assert_eq!(calendar.next_open(TOKYO_OPEN + 10800).unwrap(), TOKYO_OPEN + 12600);
+        assert_eq!(calendar.next_close(TOKYO_OPEN + 3600).unwrap(), TOKYO_OPEN + 9000);
+    }
+
+    #[test]
+    fn overnight_sessions_trade_for_the_next_day() {
+        let calendar = SessionCalendar::from_string("1700-1600", "America/Chicago").unwrap();
+        // monday 18:00 cdt trades for tuesday, sunday evening for monday
+        let monday_evening = 1_721_084_400;
+        let sunday_evening = monday_evening - 24 * 3600;
+        assert_eq!(calendar.trading_day(monday_evening).unwrap(), add_days(monday(), 1).unwrap());
+        assert_eq!(calendar.trading_day(sunday_evening).unwrap(), monday());
+        assert!(calendar.is_open(sunday_evening).unwrap());
+        // the daily maintenance break
+        assert!(!calendar.is_open(monday_evening - 5400).unwrap());
+    }
+
+    #[test]
+    fn holidays_and_corrections_replace_the_regular_hours() {
+        let calendar = SessionCalendar::from_string("0900-1130,1230-1500", "Asia/Tokyo").unwrap();
+        let holiday = calendar.clone().with_holidays("20240715").unwrap();
+        assert!(holiday.regular_sessions_on(monday()).unwrap().is_empty());
+        assert!(!holiday.is_open(TOKYO_OPEN + 3600).unwrap());
+        let half_day = calendar.with_corrections("0900-1130:20240715").unwrap();
+        assert_eq!(half_day.regular_sessions_on(monday()).unwrap(), vec![(TOKYO_OPEN, TOKYO_OPEN + 9000)]);
+        assert_eq!(half_day.regular_sessions_on(add_days(monday(), 1).unwrap()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn extended_hours_are_tagged() {
+        let calendar = SessionCalendar::from_string("0930-1600", "America/New_York")
+            .unwrap()
+            .with_premarket("0400-0930")
+            .unwrap()
+            .with_postmarket("1600-2000")
+            .unwrap();
+        // 2024-07-15 13:30 utc is 09:30 edt
+        let regular_open = 1_721_050_200;
+        assert_eq!(calendar.session_at(regular_open - 60).unwrap(), MarketSession::PreMarket);
+        assert_eq!(calendar.session_at(regular_open).unwrap(), MarketSession::Regular);
+        assert_eq!(calendar.session_at(regular_open + 6 * 3600 + 1800).unwrap(), MarketSession::PostMarket);
+        assert_eq!(calendar.session_at(regular_open + 11 * 3600).unwrap(), MarketSession::Closed);
+        assert_eq!(calendar.regular_sessions_on(monday()).unwrap(), vec![(regular_open, regular_open + 6 * 3600 + 1800)]);
+    }
+}
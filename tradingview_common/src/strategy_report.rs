@@ -0,0 +1,240 @@
+use miniserde::json::{Object, Value};
+use miniserde::{Deserialize, Serialize};
+use simple_error::{box_err, SimpleResult};
+
+use crate::json_utilities::{self, f64_or_zero, object_or_empty};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TradeDirection {
+    Long,
+    Short,
+}
+
+/// Entry or exit order of a trade.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyTradeFill {
+    pub signal: String, // order id from strategy.entry / strategy.close
+    pub price: f64,
+    pub timestamp: u64, // ms
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyTrade {
+    pub direction: TradeDirection,
+    pub entry: StrategyTradeFill,
+    pub exit: Option<StrategyTradeFill>, // None while the position is open
+    pub quantity: f64,
+    pub profit: f64,
+    pub profit_percent: f64,
+    pub cumulative_profit: f64,
+    pub run_up: f64,
+    pub drawdown: f64,
+}
+
+impl StrategyTrade {
+    pub fn is_open(&self) -> bool {
+        self.exit.is_none()
+    }
+}
+
+/// One column of the performance summary (all / long / short trades).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StrategyPerformance {
+    pub net_profit: f64,
+    pub net_profit_percent: f64,
+    pub gross_profit: f64,
+    pub gross_loss: f64,
+    pub total_trades: u64,
+    pub winning_trades: u64,
+    pub losing_trades: u64,
+    pub percent_profitable: f64,
+    pub profit_factor: Option<f64>, // undefined without losing trades
+    pub average_trade: f64,
+}
+
+/// Backtest results the server streams with the plots of a Pine strategy (`ns.d` of its study updates).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyReport {
+    pub currency: Option<String>,
+    pub trades: Vec<StrategyTrade>,
+    pub equity: Vec<f64>,
+    pub drawdown: Vec<f64>,
+    pub all: StrategyPerformance,
+    pub long: StrategyPerformance,
+    pub short: StrategyPerformance,
+    pub max_drawdown: f64,
+    pub max_drawdown_percent: f64,
+    pub open_profit: f64,
+}
+
+impl StrategyReport {
+    /// Parses the `ns.d` string of a study update; `None` when it carries no report (plain studies, compressed reports).
+    pub fn from_ns_data(data: &str) -> SimpleResult<Option<Self>> {
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let data: Object = miniserde::json::from_str(data)?;
+        match data.get("report") {
+            Some(report) => Ok(Some(Self::from_object(&json_utilities::value_to_object(report)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn from_object(report: &Object) -> SimpleResult<Self> {
+        let trades = match report.get("trades") {
+            Some(trades) => json_utilities::value_to_array(trades)?
+                .iter()
+                .map(|trade| parse_trade(&json_utilities::value_to_object(trade)?))
+                .collect::<SimpleResult<Vec<_>>>()?,
+            None => vec![],
+        };
+        let history = object_or_empty(report, "history")?;
+        let performance = object_or_empty(report, "performance")?;
+        Ok(Self {
+            currency: report.get("currency").and_then(|currency| json_utilities::value_to_string(currency).ok()),
+            trades,
+            equity: f64_array(&history, "equity")?,
+            drawdown: f64_array(&history, "drawDown")?,
+            all: parse_performance(&object_or_empty(&performance, "all")?),
+            long: parse_performance(&object_or_empty(&performance, "long")?),
+            short: parse_performance(&object_or_empty(&performance, "short")?),
+            max_drawdown: f64_or_zero(&performance, "maxStrategyDrawDown"),
+            max_drawdown_percent: f64_or_zero(&performance, "maxStrategyDrawDownPercent"),
+            open_profit: f64_or_zero(&performance, "openPL"),
+        })
+    }
+
+    pub fn closed_trades(&self) -> impl Iterator<Item = &StrategyTrade> {
+        self.trades.iter().filter(|trade| !trade.is_open())
+    }
+
+    pub fn open_trades(&self) -> impl Iterator<Item = &StrategyTrade> {
+        self.trades.iter().filter(|trade| trade.is_open())
+    }
+
+    pub fn net_profit(&self) -> f64 {
+        self.all.net_profit
+    }
+
+    /// Share of closed trades that were profitable, 0..=1.
+    pub fn win_rate(&self) -> f64 {
+        if self.all.total_trades > 0 {
+            return self.all.winning_trades as f64 / self.all.total_trades as f64;
+        }
+        // older reports only list trades
+        let closed = self.closed_trades().count();
+        if closed == 0 {
+            return 0.0;
+        }
+        self.closed_trades().filter(|trade| trade.profit > 0.0).count() as f64 / closed as f64
+    }
+}
+
+// {"e":{"c":"Long","tp":"l","p":100.0,"tm":...},"x":{"c":"Exit","p":101.0,"tm":...},"q":1,"tp":{"v":1.0,"p":1.0},"cp":{...},"rn":{...},"dd":{...}}
+fn parse_trade(trade: &Object) -> SimpleResult<StrategyTrade> {
+    let entry = object_or_empty(trade, "e")?;
+    let direction = match entry.get("tp").and_then(|tp| json_utilities::value_to_string(tp).ok()).as_deref() {
+        Some("s") => TradeDirection::Short,
+        _ => TradeDirection::Long,
+    };
+    let exit = object_or_empty(trade, "x")?;
+    let value = |key: &str| -> SimpleResult<f64> {
+        match trade.get(key) {
+            Some(Value::Object(amount)) => Ok(amount.get("v").and_then(|v| json_utilities::value_to_f64_cast(v).ok()).unwrap_or(0.0)),
+            Some(amount) => json_utilities::value_to_f64_cast(amount),
+            None => Ok(0.0),
+        }
+    };
+    let profit_percent = match trade.get("tp") {
+        Some(Value::Object(amount)) => f64_or_zero(amount, "p"),
+        _ => 0.0,
+    };
+    Ok(StrategyTrade {
+        direction,
+        entry: parse_fill(&entry)?.ok_or(box_err!("strategy trade has no entry"))?,
+        exit: parse_fill(&exit)?,
+        quantity: f64_or_zero(trade, "q"),
+        profit: value("tp")?,
+        profit_percent,
+        cumulative_profit: value("cp")?,
+        run_up: value("rn")?,
+        drawdown: value("dd")?,
+    })
+}
+
+fn parse_fill(fill: &Object) -> SimpleResult<Option<StrategyTradeFill>> {
+    let timestamp = match fill.get("tm") {
+        Some(timestamp) => json_utilities::value_to_u64_cast(timestamp)?,
+        None => return Ok(None),
+    };
+    Ok(Some(StrategyTradeFill {
+        signal: fill.get("c").and_then(|signal| json_utilities::value_to_string(signal).ok()).unwrap_or_default(),
+        price: f64_or_zero(fill, "p"),
+        timestamp,
+    }))
+}
+
+fn parse_performance(performance: &Object) -> StrategyPerformance {
+    let count = |key: &str| performance.get(key).and_then(|value| json_utilities::value_to_u64_cast(value).ok()).unwrap_or(0);
+    StrategyPerformance {
+        net_profit: f64_or_zero(performance, "netProfit"),
+        net_profit_percent: f64_or_zero(performance, "netProfitPercent"),
+        gross_profit: f64_or_zero(performance, "grossProfit"),
+        gross_loss: f64_or_zero(performance, "grossLoss"),
+        total_trades: count("totalTrades"),
+        winning_trades: count("numberOfWiningTrades"), // sic
+        losing_trades: count("numberOfLosingTrades"),
+        percent_profitable: f64_or_zero(performance, "percentProfitable"),
+        profit_factor: performance.get("profitFactor").and_then(|value| json_utilities::value_to_f64_cast(value).ok()),
+        average_trade: f64_or_zero(performance, "avgTrade"),
+    }
+}
+
+fn f64_array(object: &Object, key: &str) -> SimpleResult<Vec<f64>> {
+    match object.get(key) {
+        Some(values) => Ok(json_utilities::value_to_array(values)?
+            .iter()
+            .map(|value| json_utilities::value_to_f64_cast(value).unwrap_or(f64::NAN))
+            .collect()),
+        None => Ok(vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NS_DATA: &str = r#"{"report":{"currency":"USD","trades":[{"e":{"c":"Long","tp":"l","p":100.0,"tm":1700000000000},"x":{"c":"Exit","p":101.5,"tm":1700000300000},"q":2,"tp":{"v":3.0,"p":1.5},"cp":{"v":3.0},"rn":{"v":4.0},"dd":{"v":-1.0}},{"e":{"c":"Short","tp":"s","p":102.0,"tm":1700000600000},"x":{},"q":1,"tp":{"v":-0.5,"p":-0.49}}],"history":{"equity":[0,3.0,2.5],"drawDown":[0,0,0.5]},"performance":{"all":{"netProfit":3.0,"totalTrades":1,"numberOfWiningTrades":1,"numberOfLosingTrades":0,"profitFactor":null},"maxStrategyDrawDown":1.0,"openPL":-0.5}}}"#;
+
+    #[test]
+    fn parses_report() {
+        let report = StrategyReport::from_ns_data(NS_DATA).unwrap().unwrap();
+        assert_eq!(report.currency.as_deref(), Some("USD"));
+        assert_eq!(report.trades.len(), 2);
+        let trade = &report.trades[0];
+        assert_eq!(trade.direction, TradeDirection::Long);
+        assert_eq!(trade.exit.as_ref().map(|exit| exit.price), Some(101.5));
+        assert_eq!((trade.quantity, trade.profit, trade.profit_percent, trade.drawdown), (2.0, 3.0, 1.5, -1.0));
+        assert_eq!(report.trades[1].direction, TradeDirection::Short);
+        assert!(report.trades[1].is_open());
+        assert_eq!(report.equity, vec![0.0, 3.0, 2.5]);
+        assert_eq!(report.all.profit_factor, None);
+        assert_eq!(report.long, StrategyPerformance::default());
+        assert_eq!((report.max_drawdown, report.max_drawdown_percent, report.open_profit), (1.0, 0.0, -0.5));
+    }
+
+    #[test]
+    fn win_rate_falls_back_to_trades() {
+        let mut report = StrategyReport::from_ns_data(NS_DATA).unwrap().unwrap();
+        assert_eq!(report.win_rate(), 1.0);
+        report.all.total_trades = 0;
+        assert_eq!(report.win_rate(), 1.0); // one closed, profitable trade
+        assert_eq!(report.open_trades().count(), 1);
+    }
+
+    #[test]
+    fn no_report() {
+        assert_eq!(StrategyReport::from_ns_data("").unwrap(), None);
+        assert_eq!(StrategyReport::from_ns_data(r#"{"graphicsCmds":{}}"#).unwrap(), None);
+    }
+}
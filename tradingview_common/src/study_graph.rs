@@ -0,0 +1,246 @@
+use std::fmt;
+
+use miniserde::json::Value;
+use simple_error::{box_err, SimpleResult};
+
+use crate::IndicatorConfig;
+
+/// Another study's output used as a source input, written `st<N>:plot` (plot id or title).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StudySource {
+    pub study_id: String,
+    pub plot: String,
+}
+
+impl StudySource {
+    /// `None` for plain sources like `close` or `hl2`.
+    pub fn from_string(value: &str) -> Option<Self> {
+        let (study_id, plot) = value.split_once(':')?;
+        let number = study_id.strip_prefix("st")?;
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) || plot.is_empty() {
+            return None;
+        }
+        Some(Self {
+            study_id: study_id.to_string(),
+            plot: plot.to_string(),
+        })
+    }
+
+    /// Source value sent in `create_study`, `st2$0` for the parent's first plot.
+    pub fn to_input_value(&self, parent: &IndicatorConfig) -> SimpleResult<String> {
+        let index = match parent.plots.iter().position(|plot| plot.matches(&self.plot)) {
+            Some(index) => index,
+            // without a schema only plot ids can be resolved
+            None => self
+                .plot
+                .strip_prefix("plot_")
+                .and_then(|index| index.parse::<usize>().ok())
+                .filter(|index| parent.plots.is_empty() || *index < parent.plots.len())
+                .ok_or(box_err!(format!("study {} has no plot {}", self.study_id, self.plot)))?,
+        };
+        Ok(format!("{}${index}", self.study_id))
+    }
+}
+
+impl fmt::Display for StudySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.study_id, self.plot)
+    }
+}
+
+/// A study ready for `create_study`.
+#[derive(Clone)]
+pub struct StudyNode {
+    pub study_id: String,
+    pub parent_id: Option<String>, // None for studies on the chart series
+    pub indicator: IndicatorConfig, // source inputs resolved to the wire format
+}
+
+/// Allocates study ids for `TradingViewClientConfig::indicators` and orders them so parents exist before the studies applied to them.
+///
+/// The n-th indicator (from 0) is `st<n + 2>`; `st1` is the sessions study every chart gets.
+#[derive(Clone)]
+pub struct StudyGraph {
+    nodes: Vec<StudyNode>, // creation order
+}
+
+impl StudyGraph {
+    pub fn study_id(index: usize) -> String {
+        format!("st{}", index + 2)
+    }
+
+    pub fn new(indicators: &[IndicatorConfig]) -> SimpleResult<Self> {
+        let study_ids = (0..indicators.len()).map(Self::study_id).collect::<Vec<_>>();
+
+        // find each study's parent from its source inputs
+        let mut pending = vec![];
+        for (index, indicator) in indicators.iter().enumerate() {
+            let mut value: Value = miniserde::json::from_str(&indicator.value)?;
+            let mut sources = vec![];
+            collect_sources(&value, &mut sources);
+            let mut parent_index = None;
+            for source in &sources {
+                let source_index = study_ids
+                    .iter()
+                    .position(|study_id| *study_id == source.study_id)
+                    .ok_or(box_err!(format!("{} references unknown study {source}", study_ids[index])))?;
+                if source_index == index {
+                    return Err(box_err!(format!("{} references itself", study_ids[index])));
+                }
+                if parent_index.is_some_and(|parent_index| parent_index != source_index) {
+                    return Err(box_err!(format!("{} takes sources from more than one study", study_ids[index])));
+                }
+                parent_index = Some(source_index);
+            }
+
+            // st2:RSI -> st2$0
+            let indicator = match parent_index {
+                Some(parent_index) => resolve_sources(indicator, &mut value, &sources, &indicators[parent_index])?,
+                None => indicator.clone(),
+            };
+            pending.push((parent_index, StudyNode {
+                study_id: study_ids[index].clone(),
+                parent_id: parent_index.map(|parent_index| study_ids[parent_index].clone()),
+                indicator,
+            }));
+        }
+
+        // parents first, otherwise config order
+        let mut created = vec![false; pending.len()];
+        let mut nodes = vec![];
+        while nodes.len() < pending.len() {
+            let ready = (0..pending.len()).find(|index| !created[*index] && !matches!(pending[*index].0, Some(parent_index) if !created[parent_index]));
+            match ready {
+                Some(index) => {
+                    created[index] = true;
+                    nodes.push(pending[index].1.clone());
+                }
+                None => {
+                    let cycle = (0..pending.len()).filter(|index| !created[*index]).map(|index| study_ids[index].clone()).collect::<Vec<_>>();
+                    return Err(box_err!(format!("study sources form a cycle: {}", cycle.join(", "))));
+                }
+            }
+        }
+        Ok(Self { nodes })
+    }
+
+    pub fn creation_order(&self) -> &[StudyNode] {
+        &self.nodes
+    }
+
+    /// Studies applied to others go first, parents last.
+    pub fn teardown_order(&self) -> impl Iterator<Item = &StudyNode> {
+        self.nodes.iter().rev()
+    }
+
+    /// Resolves the source inputs of new inputs for `study_id` (`modify_study`) the same way `new` does.
+    ///
+    /// A study keeps its parent, so sources can only name the one it was created on.
+    pub fn resolve(&self, study_id: &str, indicator: &IndicatorConfig) -> SimpleResult<IndicatorConfig> {
+        let node = self.node(study_id)?;
+        let mut value: Value = miniserde::json::from_str(&indicator.value)?;
+        let mut sources = vec![];
+        collect_sources(&value, &mut sources);
+        if sources.is_empty() {
+            return Ok(indicator.clone());
+        }
+        if let Some(source) = sources.iter().find(|source| node.parent_id.as_ref() != Some(&source.study_id)) {
+            return Err(box_err!(format!("{study_id} can not take source {source}, it was created on {}", node.parent_id.as_deref().unwrap_or("the chart series"))));
+        }
+        let parent = self.node(&sources[0].study_id)?;
+        resolve_sources(indicator, &mut value, &sources, &parent.indicator)
+    }
+
+    fn node(&self, study_id: &str) -> SimpleResult<&StudyNode> {
+        self.nodes.iter().find(|node| node.study_id == study_id).ok_or(box_err!(format!("unknown study {study_id}")))
+    }
+}
+
+fn resolve_sources(indicator: &IndicatorConfig, value: &mut Value, sources: &[StudySource], parent: &IndicatorConfig) -> SimpleResult<IndicatorConfig> {
+    let mut resolved = vec![];
+    for source in sources {
+        resolved.push((source.to_string(), source.to_input_value(parent)?));
+    }
+    replace_sources(value, &resolved);
+    Ok(IndicatorConfig {
+        value: miniserde::json::to_string(value),
+        ..indicator.clone()
+    })
+}
+
+// pine inputs are {"v":"st2:RSI","f":true,"t":"source"}, built-in studies use a "source" key
+fn collect_sources(value: &Value, sources: &mut Vec<StudySource>) {
+    match value {
+        Value::Object(object) => {
+            let is_pine_source = matches!(object.get("t"), Some(Value::String(type_name)) if type_name == "source");
+            for (key, value) in object.iter() {
+                match value {
+                    Value::String(source) if key == "source" || (is_pine_source && key == "v") => {
+                        if let Some(source) = StudySource::from_string(source) {
+                            sources.push(source);
+                        }
+                    }
+                    _ => collect_sources(value, sources),
+                }
+            }
+        }
+        Value::Array(array) => array.iter().for_each(|value| collect_sources(value, sources)),
+        _ => (),
+    }
+}
+
+fn replace_sources(value: &mut Value, resolved: &[(String, String)]) {
+    match value {
+        Value::String(source) => {
+            if let Some((_, input_value)) = resolved.iter().find(|(reference, _)| reference == source) {
+                *source = input_value.clone();
+            }
+        }
+        Value::Object(object) => object.values_mut().for_each(|value| replace_sources(value, resolved)),
+        Value::Array(array) => array.iter_mut().for_each(|value| replace_sources(value, resolved)),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PinePlotDefinition;
+
+    fn indicator(value: &str, plots: &[&str]) -> IndicatorConfig {
+        IndicatorConfig {
+            name: "Study@tv-basicstudies-241".to_string(),
+            value: value.to_string(),
+            plots: plots.iter().enumerate().map(|(index, title)| PinePlotDefinition::new(&format!("plot_{index}"), "line", title)).collect(),
+        }
+    }
+
+    #[test]
+    fn creates_parents_first_and_removes_them_last() {
+        let graph = StudyGraph::new(&[indicator(r#"{"source":"st3:RSI"}"#, &["EMA"]), indicator(r#"{"length":14}"#, &["RSI"])]).unwrap();
+        let creation_order = graph.creation_order().iter().map(|node| node.study_id.as_str()).collect::<Vec<_>>();
+        assert_eq!(creation_order, vec!["st3", "st2"]);
+        assert_eq!(graph.creation_order()[1].parent_id.as_deref(), Some("st3"));
+        assert!(graph.creation_order()[1].indicator.value.contains("st3$0"));
+        let teardown_order = graph.teardown_order().map(|node| node.study_id.as_str()).collect::<Vec<_>>();
+        assert_eq!(teardown_order, vec!["st2", "st3"]);
+    }
+
+    #[test]
+    fn rejects_cycles_and_unknown_studies() {
+        assert!(StudyGraph::new(&[indicator(r#"{"source":"st3:plot_0"}"#, &[]), indicator(r#"{"source":"st2:plot_0"}"#, &[])]).is_err());
+        assert!(StudyGraph::new(&[indicator(r#"{"source":"st9:plot_0"}"#, &[])]).is_err());
+    }
+
+    #[test]
+    fn resolves_modified_inputs_against_the_parent() {
+        let graph = StudyGraph::new(&[indicator(r#"{"length":14}"#, &["RSI", "Signal"]), indicator(r#"{"source":"st2:RSI"}"#, &["EMA"])]).unwrap();
+        let modified = graph.resolve("st3", &indicator(r#"{"source":"st2:Signal","length":9}"#, &["EMA"])).unwrap();
+        assert!(modified.value.contains("st2$1"));
+        assert!(!modified.value.contains("st2:Signal"));
+        assert_eq!(graph.resolve("st2", &indicator(r#"{"length":21}"#, &["RSI"])).unwrap().value, r#"{"length":21}"#);
+        assert!(graph.resolve("st2", &indicator(r#"{"source":"st3:EMA"}"#, &[])).is_err()); // st2 is on the chart series
+        assert!(graph.resolve("st3", &indicator(r#"{"source":"st2:MACD"}"#, &[])).is_err());
+        assert!(graph.resolve("st9", &indicator("{}", &[])).is_err());
+    }
+}
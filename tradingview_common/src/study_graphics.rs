@@ -0,0 +1,420 @@
+use std::collections::BTreeMap;
+
+use miniserde::json::{Object, Value};
+use miniserde::{Deserialize, Serialize};
+use simple_error::SimpleResult;
+
+use crate::json_utilities::{self, array_or_empty, f64_or_nan, string_or_empty, u64_or_zero};
+
+/// Drawing types a Pine script can create, keyed as in `graphicsCmds`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GraphicsKind {
+    Label,
+    Line,
+    Box,
+    Table,
+    TableCell,
+    Polyline,
+}
+
+impl GraphicsKind {
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "dwglabels" => Some(GraphicsKind::Label),
+            "dwglines" => Some(GraphicsKind::Line),
+            "dwgboxes" => Some(GraphicsKind::Box),
+            "dwgtables" => Some(GraphicsKind::Table),
+            "dwgtablecells" => Some(GraphicsKind::TableCell),
+            "polylines" => Some(GraphicsKind::Polyline),
+            _ => None,
+        }
+    }
+}
+
+/// `label.new`; `x` is a position in the study's `indexes`, see `StudyGraphics::bar_index`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphicsLabel {
+    pub id: u64,
+    pub x: u64,
+    pub y: f64,
+    pub y_location: String, // pr (price), ab (above bar), bl (below bar)
+    pub text: String,
+    pub style: String,
+    pub color: Option<u64>,
+    pub text_color: Option<u64>,
+    pub size: String,
+    pub tooltip: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphicsLine {
+    pub id: u64,
+    pub x1: u64,
+    pub y1: f64,
+    pub x2: u64,
+    pub y2: f64,
+    pub extend: String, // n, l, r, b
+    pub style: String,
+    pub color: Option<u64>,
+    pub width: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphicsBox {
+    pub id: u64,
+    pub x1: u64, // left
+    pub y1: f64, // top
+    pub x2: u64, // right
+    pub y2: f64, // bottom
+    pub border_color: Option<u64>,
+    pub background_color: Option<u64>,
+    pub border_width: f64,
+    pub border_style: String,
+    pub extend: String,
+    pub text: String,
+    pub text_color: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphicsTable {
+    pub id: u64,
+    pub position: String, // top_right, ...
+    pub background_color: Option<u64>,
+    pub frame_color: Option<u64>,
+    pub frame_width: f64,
+    pub border_color: Option<u64>,
+    pub border_width: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphicsTableCell {
+    pub id: u64,
+    pub table_id: u64,
+    pub row: u64,
+    pub column: u64,
+    pub text: String,
+    pub text_color: Option<u64>,
+    pub background_color: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphicsPoint {
+    pub x: u64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphicsPolyline {
+    pub id: u64,
+    pub points: Vec<GraphicsPoint>,
+    pub closed: bool,
+    pub line_color: Option<u64>,
+    pub fill_color: Option<u64>,
+    pub width: f64,
+}
+
+/// One `erase` instruction: everything (no kind), every object of a kind, or a single object (kind and id).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphicsErase {
+    pub kind: Option<GraphicsKind>,
+    pub id: Option<u64>,
+}
+
+/// Drawing commands from the `ns.d` of a study update. Creating an id that already exists updates it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StudyGraphicsUpdate {
+    pub erase: Vec<GraphicsErase>, // applied before the creates
+    pub labels: Vec<GraphicsLabel>,
+    pub lines: Vec<GraphicsLine>,
+    pub boxes: Vec<GraphicsBox>,
+    pub tables: Vec<GraphicsTable>,
+    pub table_cells: Vec<GraphicsTableCell>,
+    pub polylines: Vec<GraphicsPolyline>,
+    pub indexes: Option<Vec<u64>>, // None when the server sends "nochange"
+}
+
+impl StudyGraphicsUpdate {
+    /// Parses the `ns` of a study update; `None` when it carries no drawing commands.
+    pub fn from_ns(ns: &Object) -> SimpleResult<Option<Self>> {
+        let indexes = match ns.get("indexes") {
+            Some(Value::Array(indexes)) => Some(indexes.iter().map(json_utilities::value_to_u64_cast).collect::<SimpleResult<Vec<_>>>()?),
+            _ => None,
+        };
+        let data = match ns.get("d") {
+            Some(d) => json_utilities::value_to_string(d)?,
+            None => String::new(),
+        };
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let data: Object = miniserde::json::from_str(&data)?;
+        let commands = match data.get("graphicsCmds") {
+            Some(commands) => json_utilities::value_to_object(commands)?,
+            None => return Ok(None),
+        };
+
+        // erase
+        let mut update = StudyGraphicsUpdate {
+            indexes,
+            ..StudyGraphicsUpdate::default()
+        };
+        for erase in array_or_empty(&commands, "erase")?.iter() {
+            let erase = json_utilities::value_to_object(erase)?;
+            // no type erases every kind, an unknown one must not be mistaken for that
+            let kind = match erase.get("type") {
+                Some(Value::Null) | None => None,
+                Some(kind) => match json_utilities::value_to_string(kind).ok().and_then(|kind| GraphicsKind::from_key(&kind)) {
+                    Some(kind) => Some(kind),
+                    None => {
+                        log::debug!("ignoring erase of unknown graphics type {kind:?}");
+                        continue;
+                    }
+                },
+            };
+            let id = match string_or_empty(&erase, "action").as_str() {
+                "one" => match (kind, erase.get("id").and_then(|id| json_utilities::value_to_u64_cast(id).ok())) {
+                    (Some(_), Some(id)) => Some(id),
+                    _ => {
+                        log::warn!("ignoring erase of one object without a type and id: {erase:?}");
+                        continue;
+                    }
+                },
+                _ => None,
+            };
+            update.erase.push(GraphicsErase { kind, id });
+        }
+
+        // create, grouped by style as {"styleId":0,"data":[...]}
+        let create = match commands.get("create") {
+            Some(Value::Null) | None => Object::new(),
+            Some(create) => json_utilities::value_to_object(create)?,
+        };
+        for (key, groups) in create.iter() {
+            let kind = match GraphicsKind::from_key(key) {
+                Some(kind) => kind,
+                None => {
+                    log::debug!("unknown graphics type {key}");
+                    continue;
+                }
+            };
+            for group in json_utilities::value_to_array(groups)?.iter() {
+                let group = json_utilities::value_to_object(group)?;
+                for item in array_or_empty(&group, "data")?.iter() {
+                    let item = json_utilities::value_to_object(item)?;
+                    match kind {
+                        GraphicsKind::Label => update.labels.push(GraphicsLabel {
+                            id: u64_or_zero(&item, "id"),
+                            x: u64_or_zero(&item, "x"),
+                            y: f64_or_nan(&item, "y"),
+                            y_location: string_or_empty(&item, "yl"),
+                            text: string_or_empty(&item, "t"),
+                            style: string_or_empty(&item, "st"),
+                            color: color(&item, "ci"),
+                            text_color: color(&item, "tci"),
+                            size: string_or_empty(&item, "sz"),
+                            tooltip: string_or_empty(&item, "tt"),
+                        }),
+                        GraphicsKind::Line => update.lines.push(GraphicsLine {
+                            id: u64_or_zero(&item, "id"),
+                            x1: u64_or_zero(&item, "x1"),
+                            y1: f64_or_nan(&item, "y1"),
+                            x2: u64_or_zero(&item, "x2"),
+                            y2: f64_or_nan(&item, "y2"),
+                            extend: string_or_empty(&item, "ex"),
+                            style: string_or_empty(&item, "st"),
+                            color: color(&item, "ci"),
+                            width: f64_or_nan(&item, "w"),
+                        }),
+                        GraphicsKind::Box => update.boxes.push(GraphicsBox {
+                            id: u64_or_zero(&item, "id"),
+                            x1: u64_or_zero(&item, "x1"),
+                            y1: f64_or_nan(&item, "y1"),
+                            x2: u64_or_zero(&item, "x2"),
+                            y2: f64_or_nan(&item, "y2"),
+                            border_color: color(&item, "c"),
+                            background_color: color(&item, "bc"),
+                            border_width: f64_or_nan(&item, "w"),
+                            border_style: string_or_empty(&item, "st"),
+                            extend: string_or_empty(&item, "ex"),
+                            text: string_or_empty(&item, "t"),
+                            text_color: color(&item, "tci"),
+                        }),
+                        GraphicsKind::Table => update.tables.push(GraphicsTable {
+                            id: u64_or_zero(&item, "id"),
+                            position: string_or_empty(&item, "pos"),
+                            background_color: color(&item, "bgc"),
+                            frame_color: color(&item, "frmc"),
+                            frame_width: f64_or_nan(&item, "frmw"),
+                            border_color: color(&item, "brdc"),
+                            border_width: f64_or_nan(&item, "brdw"),
+                        }),
+                        GraphicsKind::TableCell => update.table_cells.push(GraphicsTableCell {
+                            id: u64_or_zero(&item, "id"),
+                            table_id: u64_or_zero(&item, "tid"),
+                            row: u64_or_zero(&item, "row"),
+                            column: u64_or_zero(&item, "col"),
+                            text: string_or_empty(&item, "t"),
+                            text_color: color(&item, "tc"),
+                            background_color: color(&item, "bgc"),
+                        }),
+                        GraphicsKind::Polyline => update.polylines.push(GraphicsPolyline {
+                            id: u64_or_zero(&item, "id"),
+                            points: array_or_empty(&item, "points")?
+                                .iter()
+                                .map(|point| {
+                                    let point = json_utilities::value_to_object(point)?;
+                                    Ok(GraphicsPoint {
+                                        x: u64_or_zero(&point, "x"),
+                                        y: f64_or_nan(&point, "y"),
+                                    })
+                                })
+                                .collect::<SimpleResult<Vec<_>>>()?,
+                            closed: item.get("cl").and_then(|closed| json_utilities::value_to_bool(closed).ok()).unwrap_or(false),
+                            line_color: color(&item, "lc"),
+                            fill_color: color(&item, "fc"),
+                            width: f64_or_nan(&item, "w"),
+                        }),
+                    }
+                }
+            }
+        }
+        Ok(Some(update))
+    }
+}
+
+/// Drawings currently on the chart for one study, built up from `StudyGraphicsUpdate`s.
+#[derive(Debug, Clone, Default)]
+pub struct StudyGraphics {
+    pub labels: BTreeMap<u64, GraphicsLabel>,
+    pub lines: BTreeMap<u64, GraphicsLine>,
+    pub boxes: BTreeMap<u64, GraphicsBox>,
+    pub tables: BTreeMap<u64, GraphicsTable>,
+    pub table_cells: BTreeMap<u64, GraphicsTableCell>,
+    pub polylines: BTreeMap<u64, GraphicsPolyline>,
+    pub indexes: Vec<u64>, // x -> bar index
+}
+
+impl StudyGraphics {
+    pub fn apply(&mut self, update: &StudyGraphicsUpdate) {
+        if let Some(indexes) = &update.indexes {
+            self.indexes = indexes.clone();
+        }
+        for erase in &update.erase {
+            match (erase.kind, erase.id) {
+                (None, Some(_)) => (), // not produced by from_ns, an id without a kind is ambiguous
+                (None, None) => {
+                    self.labels.clear();
+                    self.lines.clear();
+                    self.boxes.clear();
+                    self.tables.clear();
+                    self.table_cells.clear();
+                    self.polylines.clear();
+                }
+                (Some(kind), None) => match kind {
+                    GraphicsKind::Label => self.labels.clear(),
+                    GraphicsKind::Line => self.lines.clear(),
+                    GraphicsKind::Box => self.boxes.clear(),
+                    GraphicsKind::Table => self.tables.clear(),
+                    GraphicsKind::TableCell => self.table_cells.clear(),
+                    GraphicsKind::Polyline => self.polylines.clear(),
+                },
+                (Some(kind), Some(id)) => match kind {
+                    GraphicsKind::Label => {
+                        self.labels.remove(&id);
+                    }
+                    GraphicsKind::Line => {
+                        self.lines.remove(&id);
+                    }
+                    GraphicsKind::Box => {
+                        self.boxes.remove(&id);
+                    }
+                    GraphicsKind::Table => {
+                        self.tables.remove(&id);
+                    }
+                    GraphicsKind::TableCell => {
+                        self.table_cells.remove(&id);
+                    }
+                    GraphicsKind::Polyline => {
+                        self.polylines.remove(&id);
+                    }
+                },
+            }
+        }
+        self.labels.extend(update.labels.iter().map(|label| (label.id, label.clone())));
+        self.lines.extend(update.lines.iter().map(|line| (line.id, line.clone())));
+        self.boxes.extend(update.boxes.iter().map(|graphics_box| (graphics_box.id, graphics_box.clone())));
+        self.tables.extend(update.tables.iter().map(|table| (table.id, table.clone())));
+        self.table_cells.extend(update.table_cells.iter().map(|cell| (cell.id, cell.clone())));
+        self.polylines.extend(update.polylines.iter().map(|polyline| (polyline.id, polyline.clone())));
+    }
+
+    /// Bar index of an `x` coordinate, `None` until the server has sent the study's indexes.
+    pub fn bar_index(&self, x: u64) -> Option<u64> {
+        self.indexes.get(x as usize).copied()
+    }
+
+    /// Cells of a table, row by row.
+    pub fn table_cells(&self, table_id: u64) -> Vec<&GraphicsTableCell> {
+        let mut cells = self.table_cells.values().filter(|cell| cell.table_id == table_id).collect::<Vec<_>>();
+        cells.sort_by_key(|cell| (cell.row, cell.column));
+        cells
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.lines.is_empty() && self.boxes.is_empty() && self.tables.is_empty() && self.table_cells.is_empty() && self.polylines.is_empty()
+    }
+}
+
+fn color(object: &Object, key: &str) -> Option<u64> {
+    object.get(key).and_then(|value| json_utilities::value_to_u64_cast(value).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use miniserde::json::Array;
+
+    use super::*;
+
+    fn update(graphics_commands: &str) -> StudyGraphicsUpdate {
+        let mut ns = Object::new();
+        ns.insert("d".to_string(), Value::String(format!(r#"{{"graphicsCmds":{graphics_commands}}}"#)));
+        ns.insert("indexes".to_string(), Value::Array(Array::new()));
+        StudyGraphicsUpdate::from_ns(&ns).unwrap().unwrap()
+    }
+
+    fn graphics() -> StudyGraphics {
+        let mut graphics = StudyGraphics::default();
+        graphics.apply(&update(r#"{"create":{"dwglabels":[{"styleId":0,"data":[{"id":1,"x":0,"y":10.5,"t":"Buy"},{"id":2,"x":1,"y":11.0,"t":"Sell"}]}],"dwglines":[{"styleId":0,"data":[{"id":1,"x1":0,"y1":1.0,"x2":1,"y2":2.0}]}]}}"#));
+        graphics
+    }
+
+    #[test]
+    fn parses_creates() {
+        let graphics = graphics();
+        assert_eq!(graphics.labels.len(), 2);
+        assert_eq!(graphics.labels[&1].text, "Buy");
+        assert_eq!(graphics.labels[&1].y, 10.5);
+        assert_eq!(graphics.lines[&1].y2, 2.0);
+    }
+
+    #[test]
+    fn erases_one_kind_or_everything() {
+        let mut graphics = graphics();
+        graphics.apply(&update(r#"{"erase":[{"action":"one","type":"dwglabels","id":1}]}"#));
+        assert_eq!(graphics.labels.keys().collect::<Vec<_>>(), vec![&2]);
+        graphics.apply(&update(r#"{"erase":[{"action":"all","type":"dwglabels"}]}"#));
+        assert!(graphics.labels.is_empty());
+        assert_eq!(graphics.lines.len(), 1);
+        graphics.apply(&update(r#"{"erase":[{"action":"all"}]}"#));
+        assert!(graphics.is_empty());
+    }
+
+    #[test]
+    fn ignores_unknown_type_and_one_without_id() {
+        let erase = update(r#"{"erase":[{"action":"all","type":"dwgfuture"},{"action":"one","type":"dwglabels"},{"action":"one","id":1}]}"#).erase;
+        assert!(erase.is_empty());
+        let mut graphics = graphics();
+        graphics.apply(&update(r#"{"erase":[{"action":"all","type":"dwgfuture"}]}"#));
+        assert_eq!(graphics.labels.len(), 2);
+    }
+}
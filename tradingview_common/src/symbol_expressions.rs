@@ -0,0 +1,271 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+
+use simple_error::{box_err, SimpleResult};
+
+use crate::SymbolSpec;
+
+/// Arithmetic on symbols and constants, e.g. `AMEX:SPY/AMEX:QQQ` or `NYMEX:CL1!-NYMEX:CL2!`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolExpression {
+    Symbol(String),
+    Constant(f64),
+    Add(Box<SymbolExpression>, Box<SymbolExpression>),
+    Subtract(Box<SymbolExpression>, Box<SymbolExpression>),
+    Multiply(Box<SymbolExpression>, Box<SymbolExpression>),
+    Divide(Box<SymbolExpression>, Box<SymbolExpression>),
+}
+
+impl SymbolExpression {
+    /// `EXCHANGE:TICKER`, validated so it cannot be mistaken for an operator.
+    pub fn symbol(symbol: &str) -> SimpleResult<Self> {
+        validate_symbol(symbol)?;
+        Ok(SymbolExpression::Symbol(symbol.to_string()))
+    }
+
+    pub fn constant(value: f64) -> SimpleResult<Self> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(box_err!(format!("invalid constant {value}")));
+        }
+        Ok(SymbolExpression::Constant(value))
+    }
+
+    /// Parses and validates an expression string.
+    pub fn from_string(value: &str) -> SimpleResult<Self> {
+        let tokens = tokenize(value)?;
+        let mut parser = Parser { tokens, position: 0 };
+        let expression = parser.expression()?;
+        if parser.position != parser.tokens.len() {
+            return Err(box_err!(format!("unexpected trailing input in {value}")));
+        }
+        expression.validate()?;
+        Ok(expression)
+    }
+
+    /// Rejects expressions TradingView cannot evaluate (no symbol at all, division by zero).
+    pub fn validate(&self) -> SimpleResult<()> {
+        if self.symbols().is_empty() {
+            return Err(box_err!("expression has no symbols"));
+        }
+        self.validate_node()
+    }
+
+    fn validate_node(&self) -> SimpleResult<()> {
+        match self {
+            SymbolExpression::Symbol(symbol) => validate_symbol(symbol),
+            SymbolExpression::Constant(value) => SymbolExpression::constant(*value).map(|_| ()),
+            SymbolExpression::Divide(_, right) if **right == SymbolExpression::Constant(0.0) => Err(box_err!("division by zero")),
+            SymbolExpression::Add(left, right) | SymbolExpression::Subtract(left, right) | SymbolExpression::Multiply(left, right) | SymbolExpression::Divide(left, right) => {
+                left.validate_node()?;
+                right.validate_node()
+            }
+        }
+    }
+
+    /// Every symbol leg, left to right.
+    pub fn symbols(&self) -> Vec<String> {
+        match self {
+            SymbolExpression::Symbol(symbol) => vec![symbol.clone()],
+            SymbolExpression::Constant(_) => vec![],
+            SymbolExpression::Add(left, right) | SymbolExpression::Subtract(left, right) | SymbolExpression::Multiply(left, right) | SymbolExpression::Divide(left, right) => {
+                let mut symbols = left.symbols();
+                symbols.extend(right.symbols());
+                symbols
+            }
+        }
+    }
+
+    pub fn is_single_symbol(&self) -> bool {
+        matches!(self, SymbolExpression::Symbol(_))
+    }
+
+    /// Spec for `resolve_symbol`; the expression is escaped along with the rest of the spec.
+    pub fn to_spec(&self) -> SimpleResult<SymbolSpec> {
+        self.validate()?;
+        Ok(SymbolSpec::new(&self.to_string()))
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            SymbolExpression::Symbol(_) | SymbolExpression::Constant(_) => 3,
+            SymbolExpression::Multiply(_, _) | SymbolExpression::Divide(_, _) => 2,
+            SymbolExpression::Add(_, _) | SymbolExpression::Subtract(_, _) => 1,
+        }
+    }
+
+    fn is_leaf(&self) -> bool {
+        matches!(self, SymbolExpression::Symbol(_) | SymbolExpression::Constant(_))
+    }
+}
+
+impl fmt::Display for SymbolExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (left, operator, right) = match self {
+            SymbolExpression::Symbol(symbol) => return write!(f, "{symbol}"),
+            SymbolExpression::Constant(value) => return write!(f, "{value}"),
+            SymbolExpression::Add(left, right) => (left, '+', right),
+            SymbolExpression::Subtract(left, right) => (left, '-', right),
+            SymbolExpression::Multiply(left, right) => (left, '*', right),
+            SymbolExpression::Divide(left, right) => (left, '/', right),
+        };
+        // left associative: the right side needs parentheses at equal precedence too
+        let precedence = self.precedence();
+        if left.precedence() < precedence {
+            write!(f, "({left})")?;
+        } else {
+            write!(f, "{left}")?;
+        }
+        write!(f, "{operator}")?;
+        if right.precedence() <= precedence && !right.is_leaf() {
+            write!(f, "({right})")
+        } else {
+            write!(f, "{right}")
+        }
+    }
+}
+
+impl Add for SymbolExpression {
+    type Output = SymbolExpression;
+
+    fn add(self, rhs: SymbolExpression) -> SymbolExpression {
+        SymbolExpression::Add(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl Sub for SymbolExpression {
+    type Output = SymbolExpression;
+
+    fn sub(self, rhs: SymbolExpression) -> SymbolExpression {
+        SymbolExpression::Subtract(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl Mul for SymbolExpression {
+    type Output = SymbolExpression;
+
+    fn mul(self, rhs: SymbolExpression) -> SymbolExpression {
+        SymbolExpression::Multiply(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl Div for SymbolExpression {
+    type Output = SymbolExpression;
+
+    fn div(self, rhs: SymbolExpression) -> SymbolExpression {
+        SymbolExpression::Divide(Box::new(self), Box::new(rhs))
+    }
+}
+
+fn validate_symbol(symbol: &str) -> SimpleResult<()> {
+    let (exchange, ticker) = symbol.split_once(':').ok_or(box_err!(format!("symbol {symbol} is not EXCHANGE:TICKER")))?;
+    let valid_exchange = !exchange.is_empty() && exchange.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let valid_ticker = !ticker.is_empty() && ticker.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '!');
+    if !valid_exchange || !valid_ticker {
+        return Err(box_err!(format!("invalid symbol {symbol}")));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Symbol(String),
+    Number(f64),
+    Operator(char),
+    Open,
+    Close,
+}
+
+fn tokenize(value: &str) -> SimpleResult<Vec<Token>> {
+    let mut tokens = vec![];
+    let chars = value.chars().collect::<Vec<_>>();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        match c {
+            ' ' => index += 1,
+            '+' | '-' | '*' | '/' => {
+                tokens.push(Token::Operator(c));
+                index += 1;
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                index += 1;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                index += 1;
+            }
+            _ => {
+                let start = index;
+                while index < chars.len() && !matches!(chars[index], ' ' | '+' | '-' | '*' | '/' | '(' | ')') {
+                    index += 1;
+                }
+                let word = chars[start..index].iter().collect::<String>();
+                if word.contains(':') {
+                    validate_symbol(&word)?;
+                    tokens.push(Token::Symbol(word));
+                } else {
+                    let number = word.parse::<f64>().map_err(|_| box_err!(format!("invalid token {word}")))?;
+                    tokens.push(Token::Number(number));
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// expression := term (('+' | '-') term)*, term := factor (('*' | '/') factor)*
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_operator(&self, operators: &[char]) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(Token::Operator(operator)) if operators.contains(operator) => Some(*operator),
+            _ => None,
+        }
+    }
+
+    fn expression(&mut self) -> SimpleResult<SymbolExpression> {
+        let mut left = self.term()?;
+        while let Some(operator) = self.peek_operator(&['+', '-']) {
+            self.position += 1;
+            let right = self.term()?;
+            left = if operator == '+' { left + right } else { left - right };
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> SimpleResult<SymbolExpression> {
+        let mut left = self.factor()?;
+        while let Some(operator) = self.peek_operator(&['*', '/']) {
+            self.position += 1;
+            let right = self.factor()?;
+            left = if operator == '*' { left * right } else { left / right };
+        }
+        Ok(left)
+    }
+
+    fn factor(&mut self) -> SimpleResult<SymbolExpression> {
+        match self.next() {
+            Some(Token::Symbol(symbol)) => Ok(SymbolExpression::Symbol(symbol)),
+            Some(Token::Number(number)) => SymbolExpression::constant(number),
+            Some(Token::Open) => {
+                let expression = self.expression()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expression),
+                    _ => Err(box_err!("missing closing parenthesis")),
+                }
+            }
+            token => Err(box_err!(format!("unexpected token {token:?}"))),
+        }
+    }
+}
@@ -1,11 +1,258 @@
-pub struct TradingViewSymbols;
+use std::fmt;
 
-impl TradingViewSymbols {
-    pub fn build_symbol(adjustment: &str, currency_id: Option<&str>, session: &str, symbol: &str) -> String {
-        // crypto does not use currency_id?
-        match currency_id {
-            Some(currency_id) => format!(r#"={{\"adjustment\":\"{adjustment}\",\"currency-id\":\"{currency_id}\",\"session\":\"{session}\",\"symbol\":\"{symbol}\"}}"#),
-            None => format!(r#"={{\"adjustment\":\"{adjustment}\",\"session\":\"{session}\",\"symbol\":\"{symbol}\"}}"#),
+use miniserde::json::{Object, Value};
+use simple_error::{box_err, SimpleResult};
+
+use crate::{json_utilities, ChartType};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Adjustment {
+    Splits,
+    Dividends,
+    None,
+}
+
+impl Adjustment {
+    pub fn from_string(value: &str) -> SimpleResult<Self> {
+        match value {
+            "splits" => Ok(Adjustment::Splits),
+            "dividends" => Ok(Adjustment::Dividends),
+            "none" => Ok(Adjustment::None),
+            _ => Err(box_err!(format!("unknown adjustment {value}"))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Adjustment::Splits => "splits",
+            Adjustment::Dividends => "dividends",
+            Adjustment::None => "none",
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolSession {
+    Regular,
+    Extended,
+}
+
+impl SymbolSession {
+    pub fn from_string(value: &str) -> SimpleResult<Self> {
+        match value {
+            "regular" => Ok(SymbolSession::Regular),
+            "extended" => Ok(SymbolSession::Extended),
+            _ => Err(box_err!(format!("unknown session {value}"))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SymbolSession::Regular => "regular",
+            SymbolSession::Extended => "extended",
+        }
+    }
+}
+
+/// Continuous futures notation: `CME_MINI:ES1!` is the front month, `ES2!` the next one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContinuousContract {
+    pub root: String, // CME_MINI:ES
+    pub nth: u32, // 1 = front month
+}
+
+impl ContinuousContract {
+    pub fn new(root: &str, nth: u32) -> Self {
+        Self { root: root.to_string(), nth }
+    }
+
+    /// `None` unless the symbol ends in `<n>!`.
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        let without_bang = symbol.strip_suffix('!')?;
+        let digits_start = without_bang.rfind(|c: char| !c.is_ascii_digit())? + 1;
+        let nth = without_bang[digits_start..].parse::<u32>().ok()?;
+        if nth == 0 {
+            return None;
+        }
+        Some(Self::new(&without_bang[..digits_start], nth))
+    }
+
+    pub fn symbol(&self) -> String {
+        format!("{}{}!", self.root, self.nth)
+    }
+}
+
+/// Structured form of the `={...}` symbol strings sent in `resolve_symbol` / `quote_add_symbols`.
+#[derive(Debug, Clone)]
+pub struct SymbolSpec {
+    pub symbol: String,
+    pub adjustment: Option<Adjustment>,
+    pub currency_id: Option<String>,
+    pub session: Option<SymbolSession>,
+    pub backadjustment: Option<bool>, // futures, "default" when back adjusted
+    pub settlement_as_close: Option<bool>, // futures
+    pub chart_type: Option<String>, // e.g. BarSetHeikenAshi@tv-basicstudies-60!, wraps the symbol
+    pub chart_inputs: Object,
+    pub extra: Object, // keys we do not model, kept for round trips
+}
+
+impl SymbolSpec {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            adjustment: None,
+            currency_id: None,
+            session: None,
+            backadjustment: None,
+            settlement_as_close: None,
+            chart_type: None,
+            chart_inputs: Object::new(),
+            extra: Object::new(),
+        }
+    }
+
+    /// Continuous contract with the usual futures settings; back adjustment removes roll gaps.
+    pub fn continuous(contract: &ContinuousContract, backadjustment: bool) -> Self {
+        Self::new(&contract.symbol())
+            .with_adjustment(Adjustment::Splits)
+            .with_backadjustment(backadjustment)
+    }
+
+    pub fn continuous_contract(&self) -> Option<ContinuousContract> {
+        ContinuousContract::from_symbol(&self.symbol)
+    }
+
+    pub fn with_adjustment(mut self, adjustment: Adjustment) -> Self {
+        self.adjustment = Some(adjustment);
+        self
+    }
+
+    pub fn with_currency_id(mut self, currency_id: &str) -> Self {
+        self.currency_id = Some(currency_id.to_string());
+        self
+    }
+
+    pub fn with_session(mut self, session: SymbolSession) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    pub fn with_backadjustment(mut self, backadjustment: bool) -> Self {
+        self.backadjustment = Some(backadjustment);
+        self
+    }
+
+    pub fn with_settlement_as_close(mut self, settlement_as_close: bool) -> Self {
+        self.settlement_as_close = Some(settlement_as_close);
+        self
+    }
+
+    pub fn with_chart_type(mut self, chart_type: &str, chart_inputs: Object) -> Self {
+        self.chart_type = Some(chart_type.to_string());
+        self.chart_inputs = chart_inputs;
+        self
+    }
+
+    /// Requests server-built bars (Heikin Ashi, Renko, ...); `ChartType::Standard` clears the chart type.
+    pub fn with_chart(mut self, chart_type: &ChartType) -> Self {
+        self.chart_type = chart_type.type_id().map(|type_id| type_id.to_string());
+        self.chart_inputs = chart_type.inputs();
+        self
+    }
+
+    pub fn chart(&self) -> SimpleResult<ChartType> {
+        ChartType::from_type_id(self.chart_type.as_deref(), &self.chart_inputs)
+    }
+
+    /// Parses `={...}` (escaped or not, as found in configs and logs) or a bare `EXCHANGE:TICKER`.
+    pub fn from_string(value: &str) -> SimpleResult<Self> {
+        let value = value.trim();
+        let json = match value.strip_prefix('=') {
+            Some(json) => json,
+            None if value.starts_with('{') => value,
+            None => return Ok(Self::new(value)),
+        };
+        let json = if json.contains(r#"\""#) {
+            json.replace(r#"\""#, "\"").replace(r"\\", r"\")
+        } else {
+            json.to_string()
+        };
+        let object: Object = miniserde::json::from_str(&json)?;
+        Self::from_object(&object)
+    }
+
+    pub fn from_object(object: &Object) -> SimpleResult<Self> {
+        let symbol = object.get("symbol").ok_or(box_err!("symbol spec has no symbol"))?;
+
+        // chart types wrap the real spec: {"inputs":{...},"symbol":{...},"type":"..."}
+        if let Value::Object(inner) = symbol {
+            let mut spec = Self::from_object(inner)?;
+            let chart_type = object.get("type").ok_or(box_err!("chart type spec has no type"))?;
+            spec.chart_type = Some(json_utilities::value_to_string(chart_type)?);
+            spec.chart_inputs = match object.get("inputs") {
+                Some(inputs) => json_utilities::value_to_object(inputs)?,
+                None => Object::new(),
+            };
+            return Ok(spec);
+        }
+
+        let mut spec = Self::new(&json_utilities::value_to_string(symbol)?);
+        for (key, value) in object {
+            match key.as_str() {
+                "symbol" => (),
+                "adjustment" => spec.adjustment = Some(Adjustment::from_string(&json_utilities::value_to_string(value)?)?),
+                "currency-id" => spec.currency_id = Some(json_utilities::value_to_string(value)?),
+                "session" => spec.session = Some(SymbolSession::from_string(&json_utilities::value_to_string(value)?)?),
+                "backadjustment" => spec.backadjustment = Some(json_utilities::value_to_string(value)? == "default"),
+                "settlement-as-close" => spec.settlement_as_close = Some(json_utilities::value_to_bool(value)?),
+                _ => {
+                    spec.extra.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        Ok(spec)
+    }
+
+    pub fn to_object(&self) -> Object {
+        let mut object = self.extra.clone();
+        object.insert("symbol".to_string(), Value::String(self.symbol.clone()));
+        if let Some(adjustment) = &self.adjustment {
+            object.insert("adjustment".to_string(), Value::String(adjustment.as_str().to_string()));
+        }
+        if let Some(currency_id) = &self.currency_id {
+            object.insert("currency-id".to_string(), Value::String(currency_id.clone()));
+        }
+        if let Some(session) = &self.session {
+            object.insert("session".to_string(), Value::String(session.as_str().to_string()));
+        }
+        if let Some(backadjustment) = self.backadjustment {
+            let backadjustment = if backadjustment { "default" } else { "none" };
+            object.insert("backadjustment".to_string(), Value::String(backadjustment.to_string()));
+        }
+        if let Some(settlement_as_close) = self.settlement_as_close {
+            object.insert("settlement-as-close".to_string(), Value::Bool(settlement_as_close));
+        }
+        match &self.chart_type {
+            Some(chart_type) => {
+                let mut wrapper = Object::new();
+                wrapper.insert("inputs".to_string(), Value::Object(self.chart_inputs.clone()));
+                wrapper.insert("symbol".to_string(), Value::Object(object));
+                wrapper.insert("type".to_string(), Value::String(chart_type.clone()));
+                wrapper
+            }
+            None => object,
+        }
+    }
+
+    /// `={"adjustment":"splits",...}` with plain quotes.
+    pub fn to_json(&self) -> String {
+        format!("={}", miniserde::json::to_string(&Value::Object(self.to_object())))
+    }
+}
+
+/// Escaped form that is embedded as-is in outbound messages (what `chart_symbols` / `quote_symbols` hold).
+impl fmt::Display for SymbolSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_json().replace('\\', r"\\").replace('"', r#"\""#))
+    }
+}
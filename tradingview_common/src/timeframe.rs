@@ -0,0 +1,217 @@
+use std::fmt;
+
+use miniserde::{Deserialize, Serialize};
+use simple_error::{box_err, SimpleResult};
+use time::{Date, Month};
+
+use crate::exchange_time::{self, utc_offset};
+use crate::SessionCalendar;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TimeframeUnit {
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+    Months,
+}
+
+/// Chart resolution, e.g. `30S`, `5`, `4H` (sent as `240`), `1D`, `1W`, `1M`. Units other than months are also
+/// accepted in lower case.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Timeframe {
+    pub multiplier: u32,
+    pub unit: TimeframeUnit,
+}
+
+impl Timeframe {
+    pub fn new(multiplier: u32, unit: TimeframeUnit) -> Self {
+        Self { multiplier, unit }
+    }
+
+    pub fn from_string(value: &str) -> SimpleResult<Self> {
+        let value = value.trim();
+        let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+        let (digits, suffix) = value.split_at(split);
+        let multiplier = match digits {
+            "" => 1, // "D", "W", "M"
+            digits => digits.parse::<u32>().map_err(|_| box_err!(format!("invalid timeframe {value}")))?,
+        };
+        if multiplier == 0 {
+            return Err(box_err!(format!("invalid timeframe {value}")));
+        }
+        let unit = match suffix {
+            "" if !digits.is_empty() => TimeframeUnit::Minutes,
+            "S" | "s" => TimeframeUnit::Seconds,
+            "H" | "h" => TimeframeUnit::Hours,
+            "D" | "d" => TimeframeUnit::Days,
+            "W" | "w" => TimeframeUnit::Weeks,
+            "M" => TimeframeUnit::Months, // "m" would be ambiguous with minutes
+            _ => return Err(box_err!(format!("invalid timeframe {value}"))),
+        };
+        Ok(Self { multiplier, unit })
+    }
+
+    /// Nominal bar length; months have none.
+    pub fn seconds(&self) -> Option<u64> {
+        let multiplier = self.multiplier as u64;
+        match self.unit {
+            TimeframeUnit::Seconds => Some(multiplier),
+            TimeframeUnit::Minutes => Some(multiplier * 60),
+            TimeframeUnit::Hours => Some(multiplier * 3600),
+            TimeframeUnit::Days => Some(multiplier * 86_400),
+            TimeframeUnit::Weeks => Some(multiplier * 7 * 86_400),
+            TimeframeUnit::Months => None,
+        }
+    }
+
+    /// Last second of the bar containing `timestamp`, from the calendar's regular sessions (pass
+    /// `SymbolResolvedMessage::chart_calendar()` for the chart's own hours). Intraday bars are aligned to the open of
+    /// the session they fall in and cut short at its close, so a lunch break ends a bar; bars outside every session are
+    /// aligned to the exchange clock. Daily and longer bars span the open of their first trading day to the close of
+    /// their last, skipping holidays.
+    pub fn bar_end(&self, timestamp: u64, calendar: &SessionCalendar) -> SimpleResult<u64> {
+        Ok(self.bar_bounds(timestamp, calendar)?.1)
+    }
+
+    fn bar_bounds(&self, timestamp: u64, calendar: &SessionCalendar) -> SimpleResult<(u64, u64)> {
+        let timestamp = timestamp as i64;
+        let multiplier = self.multiplier as i64;
+        let trading_day = calendar.trading_day(timestamp)?;
+        let (start, end) = match self.unit {
+            TimeframeUnit::Seconds | TimeframeUnit::Minutes | TimeframeUnit::Hours => {
+                let length = self.seconds().ok_or(box_err!("intraday timeframe without length"))? as i64;
+                let sessions = calendar.regular_sessions_on(trading_day)?;
+                match sessions.iter().find(|(open, close)| timestamp >= *open && timestamp < *close) {
+                    Some((open, close)) => {
+                        let start = open + (timestamp - open).div_euclid(length) * length;
+                        (start, (start + length).min(*close) - 1)
+                    }
+                    None => {
+                        let local = timestamp + utc_offset(&calendar.timezone, timestamp)?;
+                        let start = timestamp - local.rem_euclid(length);
+                        (start, start + length - 1)
+                    }
+                }
+            }
+            TimeframeUnit::Days => {
+                // group calendar days since the epoch
+                let index = trading_day.to_julian_day() as i64;
+                let first = exchange_time::add_days(trading_day, -index.rem_euclid(multiplier))?;
+                self.period_bounds(calendar, first, multiplier)?
+            }
+            TimeframeUnit::Weeks => {
+                // weeks start on monday, grouped since the first monday after the epoch
+                let monday = exchange_time::add_days(trading_day, -(trading_day.weekday().number_days_from_monday() as i64))?;
+                let index = (exchange_time::day_start(monday) / 86_400 - 4) / 7;
+                let first = exchange_time::add_days(monday, -7 * index.rem_euclid(multiplier))?;
+                self.period_bounds(calendar, first, 7 * multiplier)?
+            }
+            TimeframeUnit::Months => {
+                let index = trading_day.year() as i64 * 12 + trading_day.month() as i64 - 1;
+                let first_index = index - index.rem_euclid(multiplier);
+                let first = month_start(first_index)?;
+                let next = month_start(first_index + multiplier)?;
+                let days = (exchange_time::day_start(next) - exchange_time::day_start(first)) / 86_400;
+                self.period_bounds(calendar, first, days)?
+            }
+        };
+        Ok((start.max(0) as u64, end.max(0) as u64))
+    }
+
+    // first trading day open -> last trading day close within [first, first + days)
+    fn period_bounds(&self, calendar: &SessionCalendar, first: Date, days: i64) -> SimpleResult<(i64, i64)> {
+        let mut sessions = vec![];
+        for offset in 0..days {
+            sessions.extend(calendar.regular_sessions_on(exchange_time::add_days(first, offset)?)?);
+        }
+        let (open, _) = sessions.first().ok_or(box_err!("no trading days in period"))?;
+        let (_, close) = sessions.last().ok_or(box_err!("no trading days in period"))?;
+        Ok((*open, close - 1))
+    }
+}
+
+fn month_start(index: i64) -> SimpleResult<Date> {
+    let year = index.div_euclid(12) as i32;
+    let month = Month::try_from(index.rem_euclid(12) as u8 + 1)?;
+    Ok(Date::from_calendar_date(year, month, 1)?)
+}
+
+/// Prints the resolution string TradingView expects in `create_series`.
+impl fmt::Display for Timeframe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unit {
+            TimeframeUnit::Seconds => write!(f, "{}S", self.multiplier),
+            TimeframeUnit::Minutes => write!(f, "{}", self.multiplier),
+            TimeframeUnit::Hours => write!(f, "{}", self.multiplier * 60),
+            TimeframeUnit::Days => write!(f, "{}D", self.multiplier),
+            TimeframeUnit::Weeks => write!(f, "{}W", self.multiplier),
+            TimeframeUnit::Months => write!(f, "{}M", self.multiplier),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_string_parses_units() {
+        assert_eq!(Timeframe::from_string("30S").unwrap(), Timeframe::new(30, TimeframeUnit::Seconds));
+        assert_eq!(Timeframe::from_string("15s").unwrap(), Timeframe::new(15, TimeframeUnit::Seconds));
+        assert_eq!(Timeframe::from_string("5").unwrap(), Timeframe::new(5, TimeframeUnit::Minutes));
+        assert_eq!(Timeframe::from_string("4h").unwrap(), Timeframe::new(4, TimeframeUnit::Hours));
+        assert_eq!(Timeframe::from_string("D").unwrap(), Timeframe::new(1, TimeframeUnit::Days));
+        assert_eq!(Timeframe::from_string("2d").unwrap(), Timeframe::new(2, TimeframeUnit::Days));
+        assert_eq!(Timeframe::from_string("1w").unwrap(), Timeframe::new(1, TimeframeUnit::Weeks));
+        assert_eq!(Timeframe::from_string("1M").unwrap(), Timeframe::new(1, TimeframeUnit::Months));
+        assert!(Timeframe::from_string("1m").is_err());
+        assert!(Timeframe::from_string("0").is_err());
+        assert!(Timeframe::from_string("").is_err());
+    }
+
+    #[test]
+    fn intraday_bars_end_at_the_lunch_break() {
+        // 2024-07-15 (monday) 09:00 Asia/Tokyo
+        let open = 1_721_001_600;
+        let calendar = SessionCalendar::from_string("0900-1130,1230-1500", "Asia/Tokyo").unwrap();
+        let hourly = Timeframe::from_string("60").unwrap();
+        assert_eq!(hourly.bar_end(open as u64 + 7200, &calendar).unwrap(), open as u64 + 9000 - 1);
+        // the afternoon aligns to its own open
+        assert_eq!(hourly.bar_end(open as u64 + 12600, &calendar).unwrap(), open as u64 + 16200 - 1);
+        // bars during the break follow the exchange clock
+        let five = Timeframe::from_string("5").unwrap();
+        assert_eq!(five.bar_end(open as u64 + 9900 + 60, &calendar).unwrap(), open as u64 + 10200 - 1);
+    }
+
+    #[test]
+    fn daily_bars_span_the_trading_day() {
+        let open = 1_721_001_600;
+        let calendar = SessionCalendar::from_string("0900-1130,1230-1500", "Asia/Tokyo").unwrap();
+        let daily = Timeframe::from_string("1D").unwrap();
+        assert_eq!(daily.bar_end(open as u64 + 3600, &calendar).unwrap(), open as u64 + 21600 - 1);
+        // a friday holiday ends the week on thursday
+        let weekly = Timeframe::from_string("1W").unwrap();
+        let holiday = calendar.with_holidays("20240719").unwrap();
+        assert_eq!(weekly.bar_end(open as u64 + 3600, &holiday).unwrap(), (open + 3 * 86400 + 21600 - 1) as u64);
+    }
+
+    #[test]
+    fn around_the_clock_bars_align_to_midnight() {
+        // 2024-07-15 12:00 utc
+        let noon = 1_721_044_800;
+        let calendar = SessionCalendar::from_string("24x7", "Etc/UTC").unwrap();
+        let four_hours = Timeframe::from_string("4H").unwrap();
+        assert_eq!(four_hours.bar_end(noon + 100, &calendar).unwrap(), noon + 4 * 3600 - 1);
+        let daily = Timeframe::from_string("1D").unwrap();
+        assert_eq!(daily.bar_end(noon, &calendar).unwrap(), noon + 12 * 3600 - 1);
+    }
+
+    #[test]
+    fn display_is_the_create_series_resolution() {
+        assert_eq!(Timeframe::from_string("30S").unwrap().to_string(), "30S");
+        assert_eq!(Timeframe::from_string("4H").unwrap().to_string(), "240");
+        assert_eq!(Timeframe::from_string("1d").unwrap().to_string(), "1D");
+    }
+}
@@ -1,15 +1,72 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
 use simple_error::SimpleResult;
 use time::{macros::format_description, OffsetDateTime};
 
 pub fn now() -> SimpleResult<u64> {
+    Ok(now_millis()? / 1000)
+}
+
+pub fn now_millis() -> SimpleResult<u64> {
     let start = std::time::SystemTime::now();
     let since = start.duration_since(std::time::UNIX_EPOCH)?;
-    Ok(since.as_secs())
+    Ok(since.as_millis() as u64)
+}
+
+/// Where a client and the code reading its data get "now" from: the wall clock, or the timestamps of the capture
+/// it is replaying.
+///
+/// Clones share the same time, give every client its own `Clock` so concurrent replays don't move each other's.
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    replay_millis: Arc<AtomicU64>, // 0 = wall clock
+}
+
+impl Clock {
+    pub fn now(&self) -> SimpleResult<u64> {
+        Ok(self.now_millis()? / 1000)
+    }
+
+    pub fn now_millis(&self) -> SimpleResult<u64> {
+        match self.replay_millis.load(Ordering::SeqCst) {
+            0 => now_millis(),
+            replay_millis => Ok(replay_millis),
+        }
+    }
+
+    /// Follows a replayed message's timestamp, `None` goes back to the wall clock.
+    pub fn set_replay_time(&self, timestamp_millis: Option<u64>) {
+        self.replay_millis.store(timestamp_millis.unwrap_or(0), Ordering::SeqCst);
+    }
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved bytes, for query values, form fields and path segments.
+pub fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
 }
 
 pub fn get_current_date() -> SimpleResult<String> {
-    let now = OffsetDateTime::now_utc();
+    let now = OffsetDateTime::from_unix_timestamp(now()? as i64)?;
     let format = format_description!("[year]-[month]-[day]");
     let date_string = now.format(&format)?;
     Ok(date_string)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_encode_escapes_reserved_bytes() {
+        assert_eq!(url_encode("user@example.com"), "user%40example.com");
+        assert_eq!(url_encode("p&ss word"), "p%26ss%20word");
+        assert_eq!(url_encode("a-b_c.d~"), "a-b_c.d~");
+    }
+}
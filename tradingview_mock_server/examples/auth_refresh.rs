@@ -0,0 +1,107 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_lock::{Mutex, RwLock};
+use simple_error::SimpleResult;
+use async_executor::{with_thread_pool, Executor};
+use tradingview_common::{ConnectionOptions, Timeframe, TimeframeUnit, TradingViewClientConfig, TradingViewHost, TradingViewIndicators};
+use tradingview_client::{AuthCredentials, LoggingMessageProcessor, TradingViewAuthOptions, TradingViewAuthenticator, TradingViewClient, TradingViewMessageProcessor};
+use tradingview_mock_server::{MockAuthConfig, MockFaults, MockServerConfig, TradingViewMockAuthServer, TradingViewMockServer};
+
+async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
+    // init logging
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // start mock auth server
+    let auth_server = Arc::new(TradingViewMockAuthServer::bind("127.0.0.1:0", MockAuthConfig::default())?);
+    let auth_base_url = auth_server.base_url()?;
+    let auth_executor = executor.clone();
+    let local_auth_server = auth_server.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_auth_server.run(auth_executor).await {
+            log::error!("mock auth server failed: {err:?}");
+        }
+    }).detach();
+
+    // start mock server that revokes the token after a few seconds
+    let server = Arc::new(TradingViewMockServer::bind("127.0.0.1:0", MockServerConfig {
+        faults: MockFaults {
+            revoke_auth_after: Some(Duration::from_secs(3)),
+            ..MockFaults::default()
+        },
+        ..MockServerConfig::default()
+    })?);
+    let endpoint = server.endpoint()?;
+    let server_executor = executor.clone();
+    let local_server = server.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_server.run(server_executor).await {
+            log::error!("mock server failed: {err:?}");
+        }
+    }).detach();
+
+    // build indicator
+    let vwap_mvwap_ema_crossover = TradingViewIndicators::generate_vwap_mvwap_ema_crossover(
+        1,
+        "close".to_string(),
+        7,
+        "close".to_string(),
+        25,
+        65,
+        51,
+        21
+    );
+
+    // build config pointed at the mock
+    let config = TradingViewClientConfig {
+        name: "MOCK:SPY".to_string(),
+        auth_token: String::new(), // provided by the authenticator
+        anonymous: false,
+        chart_symbols: vec![
+            "MOCK:SPY".to_string()
+        ],
+        quote_symbols: vec![
+            "MOCK:SPY".to_string()
+        ],
+        indicators: vec![
+            vwap_mvwap_ema_crossover.to_indicator()
+        ],
+        timeframe: Some(Timeframe::new(5, TimeframeUnit::Minutes)),
+        range: Some(10),
+        capture_path: None,
+        connection: ConnectionOptions {
+            host: TradingViewHost::Custom,
+            custom_endpoint: Some(endpoint),
+            ..ConnectionOptions::default()
+        },
+    };
+
+    // build authenticator
+    let mut auth_options = TradingViewAuthOptions::new(AuthCredentials::Password {
+        username: "mock".to_string(),
+        password: "mock".to_string(),
+    });
+    auth_options.base_url = auth_base_url;
+    let authenticator = Arc::new(Mutex::new(TradingViewAuthenticator::new(auth_options)));
+
+    // build client
+    let message_processor = Arc::new(RwLock::new(LoggingMessageProcessor::default())) as Arc<RwLock<dyn TradingViewMessageProcessor + Send + Sync>>;
+    let client = TradingViewClient::new(config, message_processor).with_authenticator(authenticator);
+
+    // report tokens the server has seen
+    let local_server = server.clone();
+    executor.spawn(async move {
+        loop {
+            async_io::Timer::after(Duration::from_secs(5)).await;
+            log::info!("tokens issued = {} received = {:?}", auth_server.issued_count(), local_server.received_auth_tokens());
+        }
+    }).detach();
+
+    // subscribe
+    client.subscribe(executor.clone()).await
+}
+
+fn main() -> SimpleResult<()> {
+    let ex = Arc::new(Executor::new());
+    with_thread_pool(&ex, || async_io::block_on(async_main(&ex)))
+}
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_lock::RwLock;
+use simple_error::SimpleResult;
+use async_executor::{with_thread_pool, Executor};
+use tradingview_common::{ConnectionOptions, ContinuousContract, SymbolSpec, Timeframe, TimeframeUnit, TradingViewClientConfig, TradingViewHost};
+use tradingview_client::{LoggingMessageProcessor, TradingViewClient, TradingViewMessageProcessor};
+use tradingview_mock_server::{MockContractRoll, MockServerConfig, TradingViewMockServer};
+
+async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
+    // init logging
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // start mock server that rolls the front contract after a few seconds
+    let server_config = MockServerConfig {
+        timezone: "America/Chicago".to_string(),
+        session: "1700-1600:23456".to_string(),
+        front_contract: Some("ESH2025".to_string()),
+        contract_roll: Some(MockContractRoll {
+            after: Duration::from_secs(5),
+            front_contract: "ESM2025".to_string(),
+        }),
+        ..MockServerConfig::default()
+    };
+    let server = Arc::new(TradingViewMockServer::bind("127.0.0.1:0", server_config)?);
+    let endpoint = server.endpoint()?;
+    let server_executor = executor.clone();
+    let local_server = server.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_server.run(server_executor).await {
+            log::error!("mock server failed: {err:?}");
+        }
+    }).detach();
+
+    // back adjusted front month
+    let es1 = SymbolSpec::continuous(&ContinuousContract::new("CME_MINI:ES", 1), true)
+        .with_settlement_as_close(false);
+
+    // build config pointed at the mock
+    let config = TradingViewClientConfig {
+        name: "MOCK:ES1!".to_string(),
+        auth_token: String::new(),
+        anonymous: true,
+        chart_symbols: vec![es1.to_string()],
+        quote_symbols: vec![],
+        indicators: vec![],
+        timeframe: Some(Timeframe::new(1, TimeframeUnit::Minutes)),
+        range: Some(10),
+        capture_path: None,
+        connection: ConnectionOptions {
+            host: TradingViewHost::Custom,
+            custom_endpoint: Some(endpoint),
+            ..ConnectionOptions::default()
+        },
+    };
+
+    // the logging processor prints ContractRolled when the mock rolls
+    let message_processor = Arc::new(RwLock::new(LoggingMessageProcessor::default())) as Arc<RwLock<dyn TradingViewMessageProcessor + Send + Sync>>;
+    let client = TradingViewClient::new(config, message_processor);
+    client.subscribe(executor.clone()).await
+}
+
+fn main() -> SimpleResult<()> {
+    let ex = Arc::new(Executor::new());
+    with_thread_pool(&ex, || async_io::block_on(async_main(&ex)))
+}
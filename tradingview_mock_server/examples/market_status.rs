@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_lock::RwLock;
+use simple_error::SimpleResult;
+use async_executor::{with_thread_pool, Executor};
+use tradingview_common::{ConnectionOptions, Timeframe, TimeframeUnit, TradingViewClientConfig, TradingViewHost};
+use tradingview_client::{LoggingMessageProcessor, TradingViewClient, TradingViewMessageProcessor};
+use tradingview_mock_server::{MockServerConfig, MockSessionChange, TradingViewMockServer};
+
+async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
+    // init logging
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // start mock server that walks the quote through a trading day
+    let session_change = |secs: u64, current_session: &str| MockSessionChange {
+        after: Duration::from_secs(secs),
+        current_session: current_session.to_string(),
+    };
+    let server_config = MockServerConfig {
+        session_changes: vec![
+            session_change(3, "post_market"),
+            session_change(6, "out_of_session"),
+            session_change(9, "pre_market"),
+            session_change(12, "market"),
+        ],
+        ..MockServerConfig::default()
+    };
+    let server = Arc::new(TradingViewMockServer::bind("127.0.0.1:0", server_config)?);
+    let endpoint = server.endpoint()?;
+    let server_executor = executor.clone();
+    let local_server = server.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_server.run(server_executor).await {
+            log::error!("mock server failed: {err:?}");
+        }
+    }).detach();
+
+    // build config pointed at the mock
+    let config = TradingViewClientConfig {
+        name: "MOCK:SPY".to_string(),
+        auth_token: String::new(),
+        anonymous: true,
+        chart_symbols: vec![
+            "MOCK:SPY".to_string()
+        ],
+        quote_symbols: vec![
+            "MOCK:SPY".to_string()
+        ],
+        indicators: vec![],
+        timeframe: Some(Timeframe::new(1, TimeframeUnit::Minutes)),
+        range: Some(10),
+        capture_path: None,
+        connection: ConnectionOptions {
+            host: TradingViewHost::Custom,
+            custom_endpoint: Some(endpoint),
+            ..ConnectionOptions::default()
+        },
+    };
+
+    // the logging processor prints MarketStatusChanged on every transition
+    let message_processor = Arc::new(RwLock::new(LoggingMessageProcessor::default())) as Arc<RwLock<dyn TradingViewMessageProcessor + Send + Sync>>;
+    let client = TradingViewClient::new(config, message_processor);
+    client.subscribe(executor.clone()).await
+}
+
+fn main() -> SimpleResult<()> {
+    let ex = Arc::new(Executor::new());
+    with_thread_pool(&ex, || async_io::block_on(async_main(&ex)))
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use async_lock::RwLock;
+use simple_error::SimpleResult;
+use async_executor::{with_thread_pool, Executor};
+use tradingview_common::{ConnectionOptions, Timeframe, TimeframeUnit, TradingViewClientConfig, TradingViewHost, TradingViewIndicators};
+use tradingview_client::{LoggingMessageProcessor, TradingViewClient, TradingViewMessageProcessor};
+use tradingview_mock_server::{MockServerConfig, TradingViewMockServer};
+
+async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
+    // init logging
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // start mock server on a random port
+    let server = Arc::new(TradingViewMockServer::bind("127.0.0.1:0", MockServerConfig::default())?);
+    let endpoint = server.endpoint()?;
+    let server_executor = executor.clone();
+    let local_server = server.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_server.run(server_executor).await {
+            log::error!("mock server failed: {err:?}");
+        }
+    }).detach();
+
+    // build indicator
+    let vwap_mvwap_ema_crossover = TradingViewIndicators::generate_vwap_mvwap_ema_crossover(
+        1,
+        "close".to_string(),
+        7,
+        "close".to_string(),
+        25,
+        65,
+        51,
+        21
+    );
+
+    // build config pointed at the mock
+    let config = TradingViewClientConfig {
+        name: "MOCK:SPY".to_string(),
+        auth_token: String::new(),
+        anonymous: true,
+        chart_symbols: vec![
+            "MOCK:SPY".to_string()
+        ],
+        quote_symbols: vec![
+            "MOCK:SPY".to_string()
+        ],
+        indicators: vec![
+            vwap_mvwap_ema_crossover.to_indicator()
+        ],
+        timeframe: Some(Timeframe::new(5, TimeframeUnit::Minutes)),
+        range: Some(10),
+        capture_path: None,
+        connection: ConnectionOptions {
+            host: TradingViewHost::Custom,
+            custom_endpoint: Some(endpoint),
+            ..ConnectionOptions::default()
+        },
+    };
+
+    // build client
+    let message_processor = Arc::new(RwLock::new(LoggingMessageProcessor::default())) as Arc<RwLock<dyn TradingViewMessageProcessor + Send + Sync>>;
+    let client = TradingViewClient::new(config, message_processor);
+
+    // subscribe
+    client.subscribe(executor.clone()).await
+}
+
+fn main() -> SimpleResult<()> {
+    let ex = Arc::new(Executor::new());
+    with_thread_pool(&ex, || async_io::block_on(async_main(&ex)))
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use async_lock::RwLock;
+use simple_error::SimpleResult;
+use async_executor::{with_thread_pool, Executor};
+use tradingview_common::{ConnectionOptions, StudyGraph, Timeframe, TimeframeUnit, TradingViewClientConfig, TradingViewHost, TradingViewIndicators};
+use tradingview_client::{PineFacadeClient, PineFacadeOptions, StatefulMessageProcessor, TradingViewClient};
+use tradingview_mock_server::{MockPineFacadeConfig, MockServerConfig, TradingViewMockPineFacade, TradingViewMockServer};
+
+async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
+    // init logging
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // start mock pine-facade
+    let pine_facade = Arc::new(TradingViewMockPineFacade::bind("127.0.0.1:0", MockPineFacadeConfig::default())?);
+    let base_url = pine_facade.base_url()?;
+    let pine_facade_executor = executor.clone();
+    let local_pine_facade = pine_facade.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_pine_facade.run(pine_facade_executor).await {
+            log::error!("mock pine-facade failed: {err:?}");
+        }
+    }).detach();
+
+    // plot layout comes from the script metadata
+    let pine_facade_client = PineFacadeClient::new(PineFacadeOptions {
+        base_url,
+        ..PineFacadeOptions::default()
+    });
+    let metadata = pine_facade_client.fetch_metadata("PUB;N16MOYK6AEJGGAoy40axs0S48GRFYcNn", "last").await?;
+
+    // start mock server with one value per plot
+    let server_config = MockServerConfig {
+        study_plot_count: metadata.plots.len(),
+        ..MockServerConfig::default()
+    };
+    let server = Arc::new(TradingViewMockServer::bind("127.0.0.1:0", server_config)?);
+    let endpoint = server.endpoint()?;
+    let server_executor = executor.clone();
+    let local_server = server.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_server.run(server_executor).await {
+            log::error!("mock server failed: {err:?}");
+        }
+    }).detach();
+
+    // build config pointed at the mock, starting from the default inputs
+    let config = TradingViewClientConfig {
+        name: "MOCK:SPY".to_string(),
+        auth_token: String::new(),
+        anonymous: true,
+        chart_symbols: vec!["MOCK:SPY".to_string()],
+        quote_symbols: vec![],
+        indicators: vec![
+            TradingViewIndicators::generate_vwap_mvwap_ema_crossover(1, "close".to_string(), 7, "close".to_string(), 25, 65, 51, 21).with_plots(metadata.plots.clone()).to_indicator()
+        ],
+        timeframe: Some(Timeframe::new(5, TimeframeUnit::Minutes)),
+        range: Some(10),
+        capture_path: None,
+        connection: ConnectionOptions {
+            host: TradingViewHost::Custom,
+            custom_endpoint: Some(endpoint),
+            ..ConnectionOptions::default()
+        },
+    };
+
+    // subscribe in the background
+    let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
+    let client = Arc::new(TradingViewClient::new(config, message_processor));
+    let handle = client.handle();
+    let client_executor = executor.clone();
+    executor.spawn(async move {
+        if let Err(err) = client.subscribe(client_executor).await {
+            log::error!("client failed: {err:?}");
+        }
+    }).detach();
+
+    // sweep the EMA lengths over the one connection
+    let chart_session_id = TradingViewClient::chart_session_id(0);
+    let study_id = StudyGraph::study_id(0);
+    for ema1_length in [5, 7, 9] {
+        for ema2_length in [20, 25, 30] {
+            let indicator = TradingViewIndicators::generate_vwap_mvwap_ema_crossover(1, "close".to_string(), ema1_length, "close".to_string(), ema2_length, 65, 51, 21).with_plots(metadata.plots.clone()).to_indicator();
+            let study_updates = handle.modify_study(&chart_session_id, &study_id, &indicator).await?;
+            let study_update = study_updates.last().ok_or("no study updates")?;
+            log::info!("ema1 {ema1_length} ema2 {ema2_length}: EMA 1 = {:.4}, EMA 2 = {:.4}", study_update.plot("EMA 1")?, study_update.plot("EMA 2")?);
+        }
+    }
+    handle.shutdown().await;
+    Ok(())
+}
+
+fn main() -> SimpleResult<()> {
+    let ex = Arc::new(Executor::new());
+    with_thread_pool(&ex, || async_io::block_on(async_main(&ex)))
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use simple_error::SimpleResult;
+use async_executor::{with_thread_pool, Executor};
+use tradingview_common::PineInputValue;
+use tradingview_client::{PineFacadeClient, PineFacadeOptions};
+use tradingview_mock_server::{MockPineFacadeConfig, TradingViewMockPineFacade};
+
+async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
+    // init logging
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // start mock pine-facade
+    let pine_facade = Arc::new(TradingViewMockPineFacade::bind("127.0.0.1:0", MockPineFacadeConfig::default())?);
+    let base_url = pine_facade.base_url()?;
+    let pine_facade_executor = executor.clone();
+    let local_pine_facade = pine_facade.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_pine_facade.run(pine_facade_executor).await {
+            log::error!("mock pine-facade failed: {err:?}");
+        }
+    }).detach();
+
+    // fetch metadata
+    let client = PineFacadeClient::new(PineFacadeOptions {
+        base_url,
+        ..PineFacadeOptions::default()
+    });
+    let metadata = client.fetch_metadata("PUB;N16MOYK6AEJGGAoy40axs0S48GRFYcNn", "last").await?;
+    log::info!("{} {}@{}", metadata.description, metadata.pine_id, metadata.pine_version);
+    for input in &metadata.inputs {
+        log::info!("input {} {:?} ({}) default={:?}", input.id, input.name, input.type_name, input.default);
+    }
+    for plot in &metadata.plots {
+        log::info!("plot {} {:?} ({})", plot.id, plot.title, plot.plot_type);
+    }
+
+    // build study from input names, the rest keep their defaults
+    let study = metadata.study(&HashMap::from([
+        ("EMA 1 Length".to_string(), PineInputValue::Integer(9)),
+        ("EMA 2 Source".to_string(), PineInputValue::Source("hl2".to_string())),
+    ]))?;
+    log::info!("study: {study}");
+    Ok(())
+}
+
+fn main() -> SimpleResult<()> {
+    let ex = Arc::new(Executor::new());
+    with_thread_pool(&ex, || async_io::block_on(async_main(&ex)))
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_lock::RwLock;
+use simple_error::SimpleResult;
+use async_executor::{with_thread_pool, Executor};
+use tradingview_common::{ConnectionOptions, PineStudy, Timeframe, TimeframeUnit, TradingViewClientConfig, TradingViewHost};
+use tradingview_client::{StatefulMessageProcessor, TradingViewClient};
+use tradingview_mock_server::{MockServerConfig, TradingViewMockServer};
+
+async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
+    // init logging
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // start mock server that attaches a backtest to study updates, one closed and one open trade
+    let server_config = MockServerConfig {
+        strategy_report: Some(r#"{
+            "currency": "USD",
+            "trades": [
+                {"e": {"c": "Long", "tp": "l", "p": 100.0, "tm": 1700000000000}, "x": {"c": "Exit", "p": 104.0, "tm": 1700003600000}, "q": 10, "tp": {"v": 40.0, "p": 4.0}, "cp": {"v": 40.0, "p": 0.4}, "rn": {"v": 50.0, "p": 5.0}, "dd": {"v": 10.0, "p": 1.0}},
+                {"e": {"c": "Short", "tp": "s", "p": 105.0, "tm": 1700007200000}, "q": 10, "tp": {"v": -5.0, "p": -0.5}, "cp": {"v": 35.0, "p": 0.35}, "rn": {"v": 0.0, "p": 0.0}, "dd": {"v": 5.0, "p": 0.5}}
+            ],
+            "history": {"equity": [10000.0, 10040.0], "drawDown": [0.0, 10.0]},
+            "performance": {
+                "all": {"netProfit": 40.0, "netProfitPercent": 0.4, "grossProfit": 40.0, "grossLoss": 0.0, "totalTrades": 1, "numberOfWiningTrades": 1, "numberOfLosingTrades": 0, "percentProfitable": 1.0, "avgTrade": 40.0},
+                "maxStrategyDrawDown": 10.0,
+                "maxStrategyDrawDownPercent": 0.1,
+                "openPL": -5.0
+            }
+        }"#.to_string()),
+        ..MockServerConfig::default()
+    };
+    let server = Arc::new(TradingViewMockServer::bind("127.0.0.1:0", server_config)?);
+    let endpoint = server.endpoint()?;
+    let server_executor = executor.clone();
+    let local_server = server.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_server.run(server_executor).await {
+            log::error!("mock server failed: {err:?}");
+        }
+    }).detach();
+
+    // any strategy script, the mock does not run it
+    let strategy = PineStudy::new("USER;mock_strategy", "1.0", "bmI9Ks46_mock");
+
+    // build config pointed at the mock
+    let config = TradingViewClientConfig {
+        name: "MOCK:SPY".to_string(),
+        auth_token: String::new(),
+        anonymous: true,
+        chart_symbols: vec!["MOCK:SPY".to_string()],
+        quote_symbols: vec![],
+        indicators: vec![strategy.to_indicator()],
+        timeframe: Some(Timeframe::new(1, TimeframeUnit::Hours)),
+        range: Some(10),
+        capture_path: None,
+        connection: ConnectionOptions {
+            host: TradingViewHost::Custom,
+            custom_endpoint: Some(endpoint),
+            ..ConnectionOptions::default()
+        },
+    };
+
+    // subscribe in the background
+    let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
+    let client = Arc::new(TradingViewClient::new(config, message_processor.clone()));
+    let client_executor = executor.clone();
+    executor.spawn(async move {
+        if let Err(err) = client.subscribe(client_executor).await {
+            log::error!("client failed: {err:?}");
+        }
+    }).detach();
+
+    // print the reports once they arrive
+    loop {
+        async_io::Timer::after(Duration::from_secs(1)).await;
+        let message_processor = message_processor.read().await;
+        if message_processor.strategy_reports.is_empty() {
+            continue;
+        }
+        for ((chart_session_id, study_id), strategy_report) in &message_processor.strategy_reports {
+            log::info!(
+                "{chart_session_id} {study_id}: net profit {:.2} {}, win rate {:.0}%, max drawdown {:.2}, open profit {:.2}",
+                strategy_report.net_profit(),
+                strategy_report.currency.as_deref().unwrap_or_default(),
+                strategy_report.win_rate() * 100.0,
+                strategy_report.max_drawdown,
+                strategy_report.open_profit
+            );
+            for trade in strategy_report.closed_trades() {
+                log::info!("closed {trade:?}");
+            }
+            for trade in strategy_report.open_trades() {
+                log::info!("open {trade:?}");
+            }
+        }
+        return Ok(());
+    }
+}
+
+fn main() -> SimpleResult<()> {
+    let ex = Arc::new(Executor::new());
+    with_thread_pool(&ex, || async_io::block_on(async_main(&ex)))
+}
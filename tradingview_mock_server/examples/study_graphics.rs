@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_lock::RwLock;
+use simple_error::SimpleResult;
+use async_executor::{with_thread_pool, Executor};
+use tradingview_common::{ConnectionOptions, PineStudy, Timeframe, TimeframeUnit, TradingViewClientConfig, TradingViewHost};
+use tradingview_client::{StatefulMessageProcessor, TradingViewClient};
+use tradingview_mock_server::{MockServerConfig, TradingViewMockServer};
+
+async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
+    // init logging
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // start mock server that draws an entry label, a session box and a status table on every study update
+    let server_config = MockServerConfig {
+        study_graphics: Some(r#"{
+            "erase": [{"action": "all"}],
+            "create": {
+                "dwglabels": [{"styleId": 0, "data": [{"id": 1, "x": 0, "y": 100.0, "yl": "bl", "t": "LONG", "st": "label_up", "ci": 4278255360, "tci": 4294967295, "sz": "normal", "tt": "entry"}]}],
+                "dwgboxes": [{"styleId": 0, "data": [{"id": 2, "x1": 0, "y1": 101.0, "x2": 0, "y2": 99.0, "c": 4282811060, "bc": 855651580, "w": 1, "st": "solid", "ex": "n", "t": "RTH", "tci": 4294967295}]}],
+                "dwgtables": [{"styleId": 0, "data": [{"id": 3, "pos": "top_right", "bgc": 4278190080, "frmw": 1, "brdw": 1}]}],
+                "dwgtablecells": [{"styleId": 0, "data": [{"id": 4, "tid": 3, "row": 0, "col": 0, "t": "Trend"}, {"id": 5, "tid": 3, "row": 0, "col": 1, "t": "Up"}]}]
+            }
+        }"#.to_string()),
+        ..MockServerConfig::default()
+    };
+    let server = Arc::new(TradingViewMockServer::bind("127.0.0.1:0", server_config)?);
+    let endpoint = server.endpoint()?;
+    let server_executor = executor.clone();
+    let local_server = server.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_server.run(server_executor).await {
+            log::error!("mock server failed: {err:?}");
+        }
+    }).detach();
+
+    // any script, the mock does not run it
+    let script = PineStudy::new("USER;mock_drawings", "1.0", "bmI9Ks46_mock");
+
+    // build config pointed at the mock
+    let config = TradingViewClientConfig {
+        name: "MOCK:SPY".to_string(),
+        auth_token: String::new(),
+        anonymous: true,
+        chart_symbols: vec!["MOCK:SPY".to_string()],
+        quote_symbols: vec![],
+        indicators: vec![script.to_indicator()],
+        timeframe: Some(Timeframe::new(1, TimeframeUnit::Hours)),
+        range: Some(10),
+        capture_path: None,
+        connection: ConnectionOptions {
+            host: TradingViewHost::Custom,
+            custom_endpoint: Some(endpoint),
+            ..ConnectionOptions::default()
+        },
+    };
+
+    // subscribe in the background
+    let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
+    let client = Arc::new(TradingViewClient::new(config, message_processor.clone()));
+    let client_executor = executor.clone();
+    executor.spawn(async move {
+        if let Err(err) = client.subscribe(client_executor).await {
+            log::error!("client failed: {err:?}");
+        }
+    }).detach();
+
+    // print the drawings once they arrive
+    loop {
+        async_io::Timer::after(Duration::from_secs(1)).await;
+        let message_processor = message_processor.read().await;
+        for ((chart_session_id, study_id), study_graphics) in &message_processor.study_graphics {
+            if study_graphics.is_empty() {
+                continue;
+            }
+            for label in study_graphics.labels.values() {
+                log::info!("{chart_session_id} {study_id}: label {:?} at bar {:?}, {}", label.text, study_graphics.bar_index(label.x), label.y);
+            }
+            for graphics_box in study_graphics.boxes.values() {
+                log::info!("{chart_session_id} {study_id}: box {:?} {}..{}", graphics_box.text, graphics_box.y2, graphics_box.y1);
+            }
+            for table in study_graphics.tables.values() {
+                let cells = study_graphics.table_cells(table.id).iter().map(|cell| cell.text.as_str()).collect::<Vec<_>>();
+                log::info!("{chart_session_id} {study_id}: table {} {cells:?}", table.position);
+            }
+            return Ok(());
+        }
+    }
+}
+
+fn main() -> SimpleResult<()> {
+    let ex = Arc::new(Executor::new());
+    with_thread_pool(&ex, || async_io::block_on(async_main(&ex)))
+}
@@ -0,0 +1,179 @@
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_executor::Executor;
+use async_io::Async;
+use base64::Engine;
+use futures_lite::io::{AsyncReadExt, AsyncWriteExt};
+use simple_error::{box_err, SimpleResult};
+
+#[derive(Debug, Clone)]
+pub struct MockAuthConfig {
+    pub username: String,
+    pub password: String,
+    pub sessionid: String,
+    /// `exp` of issued tokens relative to issue time.
+    pub token_lifetime: Duration,
+}
+
+impl Default for MockAuthConfig {
+    fn default() -> Self {
+        Self {
+            username: "mock".to_string(),
+            password: "mock".to_string(),
+            sessionid: "mock-session".to_string(),
+            token_lifetime: Duration::from_secs(3600),
+        }
+    }
+}
+
+/// Local stand-in for `/accounts/signin/` and the logged in homepage, issuing JWT-shaped tokens.
+pub struct TradingViewMockAuthServer {
+    listener: Async<TcpListener>,
+    config: MockAuthConfig,
+    issued_count: Arc<AtomicUsize>,
+}
+
+impl TradingViewMockAuthServer {
+    pub fn bind(address: &str, config: MockAuthConfig) -> SimpleResult<Self> {
+        let address: SocketAddr = address.parse()?;
+        let listener = Async::<TcpListener>::bind(address)?;
+        Ok(Self {
+            listener,
+            config,
+            issued_count: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn local_addr(&self) -> SimpleResult<SocketAddr> {
+        Ok(self.listener.get_ref().local_addr()?)
+    }
+
+    /// Value for `TradingViewAuthOptions::base_url`.
+    pub fn base_url(&self) -> SimpleResult<String> {
+        Ok(format!("http://{}", self.local_addr()?))
+    }
+
+    /// Number of tokens handed out so far.
+    pub fn issued_count(&self) -> usize {
+        self.issued_count.load(Ordering::SeqCst)
+    }
+
+    pub async fn run(&self, executor: Arc<Executor<'static>>) -> SimpleResult<()> {
+        log::info!("mock auth: listening on {}", self.local_addr()?);
+        loop {
+            let (stream, peer) = self.listener.accept().await?;
+            let config = self.config.clone();
+            let issued_count = self.issued_count.clone();
+            executor.spawn(async move {
+                if let Err(err) = serve_request(stream, config, issued_count).await {
+                    log::warn!("mock auth: {peer} failed: {err:?}");
+                }
+            }).detach();
+        }
+    }
+}
+
+async fn serve_request(stream: Async<TcpStream>, config: MockAuthConfig, issued_count: Arc<AtomicUsize>) -> SimpleResult<()> {
+    let mut stream = &stream;
+
+    // read request head + body
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte).await? == 0 {
+            return Err(box_err!("mock auth: connection closed during request"));
+        }
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8(head)?;
+    let header = |name: &str| {
+        head.lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .find(|(header_name, _)| header_name.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().to_string())
+    };
+    let content_length = header("content-length").and_then(|value| value.parse::<usize>().ok()).unwrap_or(0);
+    let mut body = vec![0u8; content_length];
+    stream.read_exact(&mut body).await?;
+    let body = String::from_utf8(body)?;
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+
+    // route
+    let (status, content_type, response_body) = match (method, path) {
+        ("POST", "/accounts/signin/") => {
+            let form = parse_form(&body);
+            let field = |name: &str| form.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+            if field("username") == Some(config.username.as_str()) && field("password") == Some(config.password.as_str()) {
+                let auth_token = issue_token(&config, &issued_count)?;
+                ("200 OK", "application/json", format!(r#"{{"error":"","user":{{"username":"{}","auth_token":"{auth_token}"}}}}"#, config.username))
+            } else {
+                ("200 OK", "application/json", r#"{"error":"Invalid username or password","code":"invalid_credentials"}"#.to_string())
+            }
+        }
+        ("GET", "/") => {
+            let cookie = header("cookie").unwrap_or_default();
+            let logged_in = cookie.split(';').any(|pair| pair.trim() == format!("sessionid={}", config.sessionid));
+            if logged_in {
+                let auth_token = issue_token(&config, &issued_count)?;
+                ("200 OK", "text/html", format!(r#"<html><script>window.user = {{"username":"{}","auth_token":"{auth_token}"}};</script></html>"#, config.username))
+            } else {
+                ("200 OK", "text/html", r#"<html><script>window.user = {"is_pro":false};</script></html>"#.to_string())
+            }
+        }
+        _ => ("404 Not Found", "text/plain", "not found".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response_body}",
+        response_body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+fn issue_token(config: &MockAuthConfig, issued_count: &AtomicUsize) -> SimpleResult<String> {
+    let serial = issued_count.fetch_add(1, Ordering::SeqCst) + 1;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    let exp = now + config.token_lifetime.as_secs();
+    let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    let header = engine.encode(r#"{"alg":"none","typ":"JWT"}"#);
+    let payload = engine.encode(format!(r#"{{"user_id":1,"iat":{now},"exp":{exp},"serial":{serial}}}"#));
+    Ok(format!("{header}.{payload}.mock"))
+}
+
+fn parse_form(body: &str) -> Vec<(String, String)> {
+    body.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (percent_decode(key), percent_decode(value)))
+        .collect()
+}
+
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => decoded.push(b' '),
+            b'%' if index + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        index += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+/// One OHLCV bar served by the mock.
+#[derive(Debug, Clone)]
+pub struct MockBar {
+    pub timestamp: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+/// Rolls the continuous contract reported in `symbol_resolved` once.
+#[derive(Debug, Clone)]
+pub struct MockContractRoll {
+    pub after: Duration,
+    pub front_contract: String,
+}
+
+/// Moves every quote to another `current_session` once (`pre_market`, `market`, `post_market`, `out_of_session`).
+#[derive(Debug, Clone)]
+pub struct MockSessionChange {
+    pub after: Duration,
+    pub current_session: String,
+}
+
+/// Faults to inject into every connection.
+#[derive(Debug, Clone, Default)]
+pub struct MockFaults {
+    /// Close the connection after this many outbound messages.
+    pub disconnect_after_messages: Option<usize>,
+    /// Close the connection this long after it was accepted.
+    pub disconnect_after: Option<Duration>,
+    /// Delay every reply to a client command.
+    pub response_delay: Option<Duration>,
+    /// Answer `set_auth_token` with a `critical_error`.
+    pub reject_auth_token: bool,
+    /// Send an unauthorized `critical_error` this long after the connection was accepted.
+    pub revoke_auth_after: Option<Duration>,
+    /// Answer `create_study` with a `study_error` instead of data.
+    pub study_error: bool,
+    /// Stop sending heartbeats.
+    pub suppress_heartbeats: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockServerConfig {
+    pub heartbeat_interval: Duration,
+    pub update_interval: Duration,
+    /// Scripted bars; when empty a seeded random walk is generated.
+    pub bars: Vec<MockBar>,
+    /// Seed for synthetic data so runs are reproducible.
+    pub seed: u64,
+    /// Number of plot values (excluding the timestamp) in synthetic study updates.
+    pub study_plot_count: usize,
+    /// `report` object sent in the `ns.d` of study updates, as for Pine strategies.
+    pub strategy_report: Option<String>,
+    /// `graphicsCmds` object sent in the `ns.d` of study updates; `x` 0 is the bar being updated.
+    pub study_graphics: Option<String>,
+    /// Timezone / session reported in `symbol_resolved`.
+    pub timezone: String,
+    pub session: String,
+    pub pricescale: u64,
+    /// `front_contract` reported in `symbol_resolved` (continuous futures).
+    pub front_contract: Option<String>,
+    /// Re-send `symbol_resolved` with a new `front_contract` this long after the connection was accepted.
+    pub contract_roll: Option<MockContractRoll>,
+    /// Quote `current_session` changes, each this long after the connection was accepted (starts at `market`).
+    pub session_changes: Vec<MockSessionChange>,
+    /// `update_mode` reported to connections that authenticate with `unauthorized_user_token`.
+    pub anonymous_update_mode: String,
+    pub faults: MockFaults,
+}
+
+impl Default for MockServerConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(10),
+            update_interval: Duration::from_secs(1),
+            bars: vec![],
+            seed: 42,
+            study_plot_count: 12,
+            strategy_report: None,
+            study_graphics: None,
+            timezone: "America/New_York".to_string(),
+            session: "0930-1600".to_string(),
+            pricescale: 100,
+            front_contract: None,
+            contract_roll: None,
+            session_changes: vec![],
+            anonymous_update_mode: "delayed_streaming_900".to_string(),
+            faults: MockFaults::default(),
+        }
+    }
+}
@@ -0,0 +1,710 @@
+use std::collections::BTreeMap;
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_executor::Executor;
+use async_io::{Async, Timer};
+use async_lock::Mutex;
+use miniserde::json::{Object, Value};
+use simple_error::{box_err, SimpleResult};
+use tradingview_codec::TradingViewMessageWrapper;
+
+use crate::config::{MockBar, MockServerConfig};
+use crate::websocket;
+
+struct MockChart {
+    resolved_symbol: Option<(String, String)>, // symbol_id, symbol
+    series_id: Option<String>,
+    timeframe_secs: u64,
+    bars: Vec<MockBar>,
+    scripted_cursor: usize,
+    studies: Vec<String>,
+    study_parents: BTreeMap<String, String>, // study_id -> series or study it is applied to
+    study_inputs: BTreeMap<String, String>, // study_id -> create_study / modify_study value
+}
+
+struct MockQuote {
+    quote_session_id: String,
+    symbol: String,
+}
+
+struct MockState {
+    rng: fastrand::Rng,
+    charts: BTreeMap<String, MockChart>,
+    quotes: Vec<MockQuote>,
+    update_mode: String,
+    current_session: String,
+    front_contract: Option<String>,
+}
+
+/// One accepted client connection.
+pub(crate) struct MockConnection {
+    stream: Arc<Async<TcpStream>>,
+    write_lock: Mutex<()>,
+    config: MockServerConfig,
+    sent_messages: AtomicUsize,
+    closed: AtomicBool,
+    state: Mutex<MockState>,
+    auth_tokens: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl MockConnection {
+    pub(crate) async fn serve(executor: Arc<Executor<'static>>, stream: Async<TcpStream>, config: MockServerConfig, auth_tokens: Arc<std::sync::Mutex<Vec<String>>>) -> SimpleResult<()> {
+        let stream = Arc::new(stream);
+
+        // websocket upgrade
+        let target = websocket::accept_handshake(&mut &*stream).await?;
+        log::debug!("mock: accepted websocket for {target}");
+
+        let connection = Arc::new(MockConnection {
+            stream: stream.clone(),
+            write_lock: Mutex::new(()),
+            sent_messages: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+            state: Mutex::new(MockState {
+                rng: fastrand::Rng::with_seed(config.seed),
+                charts: BTreeMap::new(),
+                quotes: vec![],
+                update_mode: "streaming".to_string(),
+                current_session: "market".to_string(),
+                front_contract: config.front_contract.clone(),
+            }),
+            config,
+            auth_tokens,
+        });
+
+        // server hello
+        let now = now_secs();
+        connection.send(&format!(
+            r#"{{"session_id":"<0:mock>","timestamp":{now},"timestampMs":{0},"release":"mock","studies_metadata_hash":"mock","auth_scheme_vsn":2,"protocol":"json","via":"127.0.0.1","javastudies":["3.66"]}}"#,
+            now * 1000
+        )).await?;
+
+        // heartbeats
+        let heartbeat_connection = connection.clone();
+        executor.spawn(async move {
+            let mut nonce = 1;
+            loop {
+                Timer::after(heartbeat_connection.config.heartbeat_interval).await;
+                if heartbeat_connection.is_closed() {
+                    break;
+                }
+                if heartbeat_connection.config.faults.suppress_heartbeats {
+                    continue;
+                }
+                if let Err(err) = heartbeat_connection.send(&format!("~h~{nonce}")).await {
+                    log::warn!("mock: heartbeat failed: {err:?}");
+                    break;
+                }
+                nonce += 1;
+            }
+        }).detach();
+
+        // streaming updates
+        let ticker_connection = connection.clone();
+        executor.spawn(async move {
+            loop {
+                Timer::after(ticker_connection.config.update_interval).await;
+                if ticker_connection.is_closed() {
+                    break;
+                }
+                if let Err(err) = ticker_connection.tick().await {
+                    log::warn!("mock: update failed: {err:?}");
+                    break;
+                }
+            }
+        }).detach();
+
+        // scheduled token revocation
+        if let Some(revoke_auth_after) = connection.config.faults.revoke_auth_after {
+            let revoke_connection = connection.clone();
+            executor.spawn(async move {
+                Timer::after(revoke_auth_after).await;
+                log::info!("mock: revoking auth token");
+                if let Err(err) = revoke_connection.send(r#"{"m":"critical_error","p":["unauthorized_access","auth token expired"]}"#).await {
+                    log::warn!("mock: revoke failed: {err:?}");
+                }
+            }).detach();
+        }
+
+        // scheduled session changes
+        for session_change in connection.config.session_changes.clone() {
+            let session_connection = connection.clone();
+            executor.spawn(async move {
+                Timer::after(session_change.after).await;
+                log::info!("mock: current_session -> {}", session_change.current_session);
+                if let Err(err) = session_connection.change_session(&session_change.current_session).await {
+                    log::warn!("mock: session change failed: {err:?}");
+                }
+            }).detach();
+        }
+
+        // scheduled contract roll
+        if let Some(contract_roll) = connection.config.contract_roll.clone() {
+            let roll_connection = connection.clone();
+            executor.spawn(async move {
+                Timer::after(contract_roll.after).await;
+                log::info!("mock: rolling to {}", contract_roll.front_contract);
+                if let Err(err) = roll_connection.roll_contract(&contract_roll.front_contract).await {
+                    log::warn!("mock: contract roll failed: {err:?}");
+                }
+            }).detach();
+        }
+
+        // scheduled disconnect
+        if let Some(disconnect_after) = connection.config.faults.disconnect_after {
+            let disconnect_connection = connection.clone();
+            executor.spawn(async move {
+                Timer::after(disconnect_after).await;
+                log::info!("mock: injecting disconnect");
+                disconnect_connection.close().await;
+            }).detach();
+        }
+
+        // read client frames
+        let mut buffer: Vec<u8> = Vec::new();
+        loop {
+            let frame = websocket::read_frame(&mut &*stream).await;
+            let (opcode, payload) = match frame {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(err) => {
+                    if connection.is_closed() {
+                        break;
+                    }
+                    return Err(err);
+                }
+            };
+            match opcode {
+                websocket::OPCODE_TEXT | websocket::OPCODE_CONTINUATION => {
+                    buffer.extend_from_slice(&payload);
+                    loop {
+                        let (remaining, message) = match TradingViewMessageWrapper::parse_payload(&buffer) {
+                            Ok((remaining, message)) => (remaining.len(), message),
+                            Err(nom::Err::Incomplete(_)) => break,
+                            Err(err) => return Err(box_err!(format!("mock: failed to parse client frame: {err:?}"))),
+                        };
+                        buffer.drain(..buffer.len() - remaining);
+                        connection.handle_command(&message).await?;
+                    }
+                }
+                websocket::OPCODE_PING => {
+                    let _guard = connection.write_lock.lock().await;
+                    websocket::write_frame(&mut &*stream, websocket::OPCODE_PONG, &payload).await?;
+                }
+                websocket::OPCODE_CLOSE => break,
+                _ => log::warn!("mock: ignoring opcode {opcode}"),
+            }
+        }
+        connection.closed.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    async fn close(&self) {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let _guard = self.write_lock.lock().await;
+        let _ = websocket::write_frame(&mut &*self.stream, websocket::OPCODE_CLOSE, &[]).await;
+        let _ = self.stream.get_ref().shutdown(Shutdown::Both);
+    }
+
+    async fn send(&self, payload: &str) -> SimpleResult<()> {
+        if self.is_closed() {
+            return Ok(());
+        }
+        log::trace!("mock: send {payload}");
+        let framed = TradingViewMessageWrapper::serialize(payload);
+        {
+            let _guard = self.write_lock.lock().await;
+            websocket::write_frame(&mut &*self.stream, websocket::OPCODE_TEXT, framed.as_bytes()).await?;
+        }
+        let sent_messages = self.sent_messages.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(disconnect_after_messages) = self.config.faults.disconnect_after_messages {
+            if sent_messages >= disconnect_after_messages {
+                log::info!("mock: injecting disconnect after {sent_messages} messages");
+                self.close().await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_command(&self, message: &str) -> SimpleResult<()> {
+        // heartbeat replies
+        if message.starts_with("~h~") {
+            log::trace!("mock: pong {message}");
+            return Ok(());
+        }
+
+        let command: Object = miniserde::json::from_str(message)?;
+        let method = match command.get("m") {
+            Some(Value::String(method)) => method.clone(),
+            _ => return Err(box_err!("mock: command without m")),
+        };
+        let params = match command.get("p") {
+            Some(Value::Array(params)) => params.iter().map(value_to_param).collect::<Vec<_>>(),
+            _ => vec![],
+        };
+        let param = |index: usize| params.get(index).cloned().unwrap_or_default();
+        log::debug!("mock: {method} {params:?}");
+
+        if let Some(response_delay) = self.config.faults.response_delay {
+            Timer::after(response_delay).await;
+        }
+
+        match method.as_str() {
+            "set_auth_token" => {
+                let auth_token = param(0);
+                self.state.lock().await.update_mode = if auth_token == "unauthorized_user_token" {
+                    self.config.anonymous_update_mode.clone()
+                } else {
+                    "streaming".to_string()
+                };
+                self.auth_tokens.lock().expect("auth token lock poisoned").push(auth_token);
+                if self.config.faults.reject_auth_token {
+                    self.send(r#"{"m":"critical_error","p":["unauthorized_access","invalid auth token"]}"#).await?;
+                }
+            }
+            "chart_create_session" => {
+                let mut state = self.state.lock().await;
+                state.charts.insert(param(0), MockChart {
+                    resolved_symbol: None,
+                    series_id: None,
+                    timeframe_secs: 60,
+                    bars: vec![],
+                    scripted_cursor: 0,
+                    studies: vec![],
+                    study_parents: BTreeMap::new(),
+                    study_inputs: BTreeMap::new(),
+                });
+            }
+            "resolve_symbol" => {
+                let chart_session_id = param(0);
+                let symbol_id = param(1);
+                let symbol = extract_symbol(&param(2));
+                let front_contract = {
+                    let mut state = self.state.lock().await;
+                    if let Some(chart) = state.charts.get_mut(&chart_session_id) {
+                        chart.resolved_symbol = Some((symbol_id.clone(), symbol.clone()));
+                    }
+                    state.front_contract.clone()
+                };
+                self.send(&self.format_symbol_resolved(&chart_session_id, &symbol_id, &symbol, front_contract.as_deref())).await?;
+            }
+            "create_series" | "modify_series" => {
+                let chart_session_id = param(0);
+                let series_id = param(1);
+                let series_name = param(2);
+                let timeframe = param(4);
+                let range = param(5).parse::<usize>().unwrap_or(300).max(1);
+                let timeframe_secs = timeframe_seconds(&timeframe);
+                let bars = {
+                    let mut state = self.state.lock().await;
+                    let bars = self.history(&mut state.rng, timeframe_secs, range);
+                    let chart = state.charts.get_mut(&chart_session_id).ok_or(box_err!("mock: unknown chart session"))?;
+                    chart.series_id = Some(series_id.clone());
+                    chart.timeframe_secs = timeframe_secs;
+                    chart.scripted_cursor = bars.len();
+                    chart.bars = bars.clone();
+                    bars
+                };
+                self.send(&format!(r#"{{"m":"series_loading","p":["{chart_session_id}","{series_id}","{series_name}"]}}"#)).await?;
+                let formatted_bars = bars.iter().enumerate().map(|(index, bar)| self.format_bar(index, bar)).collect::<Vec<_>>().join(",");
+                let last_index = bars.len() - 1;
+                let bar_close_time = bars[last_index].timestamp + timeframe_secs;
+                self.send(&format!(
+                    r#"{{"m":"timescale_update","p":["{chart_session_id}",{{"{series_id}":{{"node":"mock","s":[{formatted_bars}],"ns":{{"d":"","indexes":[]}},"t":"{series_name}","lbs":{{"bar_close_time":{bar_close_time}}}}}}},{{"index":{last_index},"zoffset":0,"changes":[],"marks":[],"index_diff":[]}}]}}"#
+                )).await?;
+                let update_mode = self.state.lock().await.update_mode.clone();
+                self.send(&format!(r#"{{"m":"series_completed","p":["{chart_session_id}","{series_id}","{update_mode}","{series_name}"]}}"#)).await?;
+            }
+            "create_study" => {
+                let chart_session_id = param(0);
+                let study_id = param(1);
+                if self.config.faults.study_error {
+                    self.send(&format!(r#"{{"m":"study_error","p":["{chart_session_id}","{study_id}","mock","injected study error"]}}"#)).await?;
+                    return Ok(());
+                }
+                let parent_id = param(3);
+                let update = {
+                    let mut state = self.state.lock().await;
+                    let chart = state.charts.get_mut(&chart_session_id).ok_or(box_err!("mock: unknown chart session"))?;
+                    if chart.series_id.as_ref() != Some(&parent_id) && !chart.studies.contains(&parent_id) {
+                        None
+                    } else {
+                        chart.studies.push(study_id.clone());
+                        chart.study_parents.insert(study_id.clone(), parent_id.clone());
+                        chart.study_inputs.insert(study_id.clone(), param(5));
+                        Some(self.study_update(&chart_session_id, chart, &study_id))
+                    }
+                };
+                // studies applied to another study need it to exist first
+                let update = match update {
+                    Some(update) => update,
+                    None => {
+                        self.send(&format!(r#"{{"m":"study_error","p":["{chart_session_id}","{study_id}","mock","parent {parent_id} not found"]}}"#)).await?;
+                        return Ok(());
+                    }
+                };
+                self.send(&format!(r#"{{"m":"study_loading","p":["{chart_session_id}","{study_id}","{study_id}_mock"]}}"#)).await?;
+                self.send(&format!(r#"{{"m":"study_completed","p":["{chart_session_id}","{study_id}","{study_id}_mock"]}}"#)).await?;
+                if let Some(update) = update {
+                    self.send(&update).await?;
+                }
+            }
+            "modify_study" => {
+                let chart_session_id = param(0);
+                let study_id = param(1);
+                let update = {
+                    let mut state = self.state.lock().await;
+                    match state.charts.get_mut(&chart_session_id) {
+                        Some(chart) if chart.studies.contains(&study_id) => {
+                            chart.study_inputs.insert(study_id.clone(), param(3));
+                            Some(self.study_update(&chart_session_id, chart, &study_id))
+                        }
+                        _ => None,
+                    }
+                };
+                let update = match update {
+                    Some(update) => update,
+                    None => {
+                        self.send(&format!(r#"{{"m":"study_error","p":["{chart_session_id}","{study_id}","mock","unknown study"]}}"#)).await?;
+                        return Ok(());
+                    }
+                };
+                // recompute
+                self.send(&format!(r#"{{"m":"study_loading","p":["{chart_session_id}","{study_id}","{study_id}_mock"]}}"#)).await?;
+                if let Some(update) = update {
+                    self.send(&update).await?;
+                }
+                self.send(&format!(r#"{{"m":"study_completed","p":["{chart_session_id}","{study_id}","{study_id}_mock"]}}"#)).await?;
+            }
+            "remove_study" => {
+                let chart_session_id = param(0);
+                let study_id = param(1);
+                let has_children = {
+                    let mut state = self.state.lock().await;
+                    match state.charts.get_mut(&chart_session_id) {
+                        Some(chart) if chart.study_parents.values().any(|parent_id| *parent_id == study_id) => true,
+                        Some(chart) => {
+                            chart.studies.retain(|existing| *existing != study_id);
+                            chart.study_parents.remove(&study_id);
+                            chart.study_inputs.remove(&study_id);
+                            false
+                        }
+                        None => false,
+                    }
+                };
+                if has_children {
+                    self.send(&format!(r#"{{"m":"study_error","p":["{chart_session_id}","{study_id}","mock","studies are still applied to {study_id}"]}}"#)).await?;
+                }
+            }
+            "quote_add_symbols" => {
+                let quote_session_id = param(0);
+                let symbols = params.iter().skip(1).cloned().collect::<Vec<_>>();
+                for symbol in symbols {
+                    let (price, update_mode, current_session) = {
+                        let mut state = self.state.lock().await;
+                        state.quotes.push(MockQuote {
+                            quote_session_id: quote_session_id.clone(),
+                            symbol: symbol.clone(),
+                        });
+                        (self.last_price(&state), state.update_mode.clone(), state.current_session.clone())
+                    };
+                    self.send(&self.format_quote(&quote_session_id, &symbol, price, Some((&update_mode, &current_session)))).await?;
+                    self.send(&format!(r#"{{"m":"quote_completed","p":["{quote_session_id}","{}"]}}"#, escape(&symbol))).await?;
+                }
+            }
+            "quote_remove_symbols" => {
+                let mut state = self.state.lock().await;
+                let quote_session_id = param(0);
+                let symbol = param(1);
+                state.quotes.retain(|quote| quote.quote_session_id != quote_session_id || quote.symbol != symbol);
+            }
+            _ => {
+                // set_locale, switch_timezone, quote_create_session, quote_set_fields, quote_fast_symbols, ...
+                log::trace!("mock: no reply for {method}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Switches the front contract and re-resolves every chart, like TradingView does on a roll.
+    async fn roll_contract(&self, front_contract: &str) -> SimpleResult<()> {
+        let messages = {
+            let mut state = self.state.lock().await;
+            state.front_contract = Some(front_contract.to_string());
+            state.charts.iter()
+                .filter_map(|(chart_session_id, chart)| {
+                    let (symbol_id, symbol) = chart.resolved_symbol.as_ref()?;
+                    Some(self.format_symbol_resolved(chart_session_id, symbol_id, symbol, Some(front_contract)))
+                })
+                .collect::<Vec<_>>()
+        };
+        for message in messages {
+            self.send(&message).await?;
+        }
+        Ok(())
+    }
+
+    async fn change_session(&self, current_session: &str) -> SimpleResult<()> {
+        let messages = {
+            let mut state = self.state.lock().await;
+            state.current_session = current_session.to_string();
+            state.quotes.iter()
+                .map(|quote| format!(
+                    r#"{{"m":"qsd","p":["{}",{{"n":"{}","s":"ok","v":{{"current_session":"{current_session}"}}}}]}}"#,
+                    quote.quote_session_id,
+                    escape(&quote.symbol)
+                ))
+                .collect::<Vec<_>>()
+        };
+        for message in messages {
+            self.send(&message).await?;
+        }
+        Ok(())
+    }
+
+    /// Advances every chart / quote by one update.
+    async fn tick(&self) -> SimpleResult<()> {
+        let mut messages = vec![];
+        {
+            let mut state = self.state.lock().await;
+            let MockState { rng, charts, quotes, .. } = &mut *state;
+            for (chart_session_id, chart) in charts.iter_mut() {
+                let series_id = match &chart.series_id {
+                    Some(series_id) => series_id.clone(),
+                    None => continue,
+                };
+                if !self.advance(rng, chart) {
+                    continue;
+                }
+                let last_index = chart.bars.len() - 1;
+                let bar = self.format_bar(last_index, &chart.bars[last_index]);
+                let bar_close_time = chart.bars[last_index].timestamp + chart.timeframe_secs;
+                messages.push(format!(
+                    r#"{{"m":"du","p":["{chart_session_id}",{{"{series_id}":{{"s":[{bar}],"ns":{{"d":"","indexes":"nochange"}},"t":"s1","lbs":{{"bar_close_time":{bar_close_time}}}}}}}]}}"#
+                ));
+                for study_id in &chart.studies {
+                    if let Some(update) = self.study_update(chart_session_id, chart, study_id) {
+                        messages.push(update);
+                    }
+                }
+            }
+            let price = charts.values().find_map(|chart| chart.bars.last().map(|bar| bar.close));
+            for quote in quotes.iter() {
+                let price = price.unwrap_or_else(|| 100.0 * (1.0 + (rng.f64() - 0.5) * 0.01));
+                messages.push(self.format_quote(&quote.quote_session_id, &quote.symbol, price, None));
+            }
+        }
+        for message in messages {
+            self.send(&message).await?;
+        }
+        Ok(())
+    }
+
+    fn history(&self, rng: &mut fastrand::Rng, timeframe_secs: u64, range: usize) -> Vec<MockBar> {
+        if !self.config.bars.is_empty() {
+            let count = range.min(self.config.bars.len());
+            return self.config.bars[..count].to_vec();
+        }
+        let now = now_secs();
+        let last_start = now - now % timeframe_secs;
+        let mut close = 100.0;
+        let mut bars = Vec::with_capacity(range);
+        for offset in (0..range as u64).rev() {
+            let bar = self.synthetic_bar(rng, last_start - offset * timeframe_secs, close);
+            close = bar.close;
+            bars.push(bar);
+        }
+        bars
+    }
+
+    /// Returns false once scripted data is exhausted.
+    fn advance(&self, rng: &mut fastrand::Rng, chart: &mut MockChart) -> bool {
+        if !self.config.bars.is_empty() {
+            match self.config.bars.get(chart.scripted_cursor) {
+                Some(bar) => {
+                    // same timestamp revises the forming bar, a new one appends
+                    let revises_last = chart.bars.last().map(|last| last.timestamp == bar.timestamp).unwrap_or(false);
+                    if revises_last {
+                        chart.bars.pop();
+                    }
+                    chart.bars.push(bar.clone());
+                    chart.scripted_cursor += 1;
+                    return true;
+                }
+                None => return false,
+            }
+        }
+        let now = now_secs();
+        let last = chart.bars.last().cloned();
+        match last {
+            Some(last) if now < last.timestamp + chart.timeframe_secs => {
+                let close = self.round(last.close * (1.0 + (rng.f64() - 0.5) * 0.001));
+                let bar = chart.bars.last_mut().expect("checked above");
+                bar.close = close;
+                bar.high = bar.high.max(close);
+                bar.low = bar.low.min(close);
+                bar.volume += rng.u64(1..100) as f64;
+            }
+            last => {
+                let previous_close = last.map(|last| last.close).unwrap_or(100.0);
+                let bar = self.synthetic_bar(rng, now - now % chart.timeframe_secs, previous_close);
+                chart.bars.push(bar);
+            }
+        }
+        true
+    }
+
+    fn synthetic_bar(&self, rng: &mut fastrand::Rng, timestamp: u64, previous_close: f64) -> MockBar {
+        let open = previous_close;
+        let close = self.round(open * (1.0 + (rng.f64() - 0.5) * 0.004));
+        let high = self.round(open.max(close) * (1.0 + rng.f64() * 0.001));
+        let low = self.round(open.min(close) * (1.0 - rng.f64() * 0.001));
+        MockBar {
+            timestamp,
+            open,
+            high,
+            low,
+            close,
+            volume: rng.u64(1_000..10_000) as f64,
+        }
+    }
+
+    fn study_update(&self, chart_session_id: &str, chart: &MockChart, study_id: &str) -> Option<String> {
+        let last_index = chart.bars.len().checked_sub(1)?;
+        let bar = &chart.bars[last_index];
+        // different inputs give different values
+        let input_offset = chart.study_inputs.get(study_id).map(|inputs| (inputs.bytes().map(u64::from).sum::<u64>() % 100) as f64 * 0.0001).unwrap_or(0.0);
+        let values = (0..self.config.study_plot_count)
+            .map(|plot| format!("{:.4}", bar.close * (1.0 + plot as f64 * 0.001 + input_offset)))
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut ns_data = vec![];
+        if let Some(strategy_report) = &self.config.strategy_report {
+            ns_data.push(format!(r#""report":{strategy_report}"#));
+        }
+        if let Some(study_graphics) = &self.config.study_graphics {
+            ns_data.push(format!(r#""graphicsCmds":{study_graphics}"#));
+        }
+        let ns_data = if ns_data.is_empty() { String::new() } else { escape(&format!("{{{}}}", ns_data.join(","))) };
+        let indexes = match self.config.study_graphics {
+            Some(_) => format!("[{last_index}]"),
+            None => r#""nochange""#.to_string(),
+        };
+        Some(format!(
+            r#"{{"m":"du","p":["{chart_session_id}",{{"{study_id}":{{"st":[{{"i":{last_index},"v":[{0}.0,{values}]}}],"ns":{{"d":"{ns_data}","indexes":{indexes}}},"t":"{study_id}_mock"}}}}]}}"#,
+            bar.timestamp
+        ))
+    }
+
+    fn last_price(&self, state: &MockState) -> f64 {
+        state.charts.values().find_map(|chart| chart.bars.last().map(|bar| bar.close)).unwrap_or(100.0)
+    }
+
+    /// Full snapshot when `update_mode` + `current_session` are given, otherwise a price-only update.
+    fn format_quote(&self, quote_session_id: &str, symbol: &str, price: f64, snapshot: Option<(&str, &str)>) -> String {
+        let now = now_secs();
+        let precision = self.precision();
+        let symbol = escape(symbol);
+        if let Some((update_mode, current_session)) = snapshot {
+            format!(
+                r#"{{"m":"qsd","p":["{quote_session_id}",{{"n":"{symbol}","s":"ok","v":{{"lp":{price:.precision$},"lp_time":{now},"ch":0.0,"chp":0.0,"volume":1000,"update_mode":"{update_mode}","current_session":"{current_session}","pricescale":{0},"minmov":1,"fractional":false,"trade_loaded":true}}}}]}}"#,
+                self.config.pricescale
+            )
+        } else {
+            format!(
+                r#"{{"m":"qsd","p":["{quote_session_id}",{{"n":"{symbol}","s":"ok","v":{{"lp":{price:.precision$},"lp_time":{now}}}}}]}}"#
+            )
+        }
+    }
+
+    fn format_symbol_resolved(&self, chart_session_id: &str, symbol_id: &str, symbol: &str, front_contract: Option<&str>) -> String {
+        let (exchange, ticker) = symbol.split_once(':').unwrap_or(("MOCK", symbol));
+        let front_contract = match front_contract {
+            Some(front_contract) => format!(r#","front_contract":"{front_contract}""#),
+            None => String::new(),
+        };
+        format!(
+            r#"{{"m":"symbol_resolved","p":["{chart_session_id}","{symbol_id}",{{"name":"{ticker}","full_name":"{symbol}","pro_name":"{symbol}","exchange":"{exchange}","listed_exchange":"{exchange}","description":"{ticker} (mock)","type":"stock","session":"{0}","timezone":"{1}","pricescale":{2},"minmov":1,"minmove2":0,"fractional":false,"currency_code":"USD","has_intraday":true,"data_status":"streaming"{front_contract}}}]}}"#,
+            self.config.session, self.config.timezone, self.config.pricescale
+        )
+    }
+
+    fn format_bar(&self, index: usize, bar: &MockBar) -> String {
+        let precision = self.precision();
+        format!(
+            r#"{{"i":{index},"v":[{0}.0,{1:.precision$},{2:.precision$},{3:.precision$},{4:.precision$},{5:.1}]}}"#,
+            bar.timestamp, bar.open, bar.high, bar.low, bar.close, bar.volume
+        )
+    }
+
+    fn precision(&self) -> usize {
+        // always print a decimal point so prices parse as floats
+        ((self.config.pricescale as f64).log10().round() as usize).max(1)
+    }
+
+    fn round(&self, price: f64) -> f64 {
+        let pricescale = self.config.pricescale.max(1) as f64;
+        (price * pricescale).round() / pricescale
+    }
+}
+
+fn value_to_param(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        Value::Null => String::new(),
+        value => miniserde::json::to_string(value),
+    }
+}
+
+/// Pulls the ticker out of a plain or `={...}` symbol spec.
+fn extract_symbol(symbol: &str) -> String {
+    match symbol.strip_prefix('=') {
+        Some(spec) => match miniserde::json::from_str::<Value>(spec) {
+            Ok(value) => symbol_from_value(&value).unwrap_or_else(|| spec.to_string()),
+            Err(_) => spec.to_string(),
+        },
+        None => symbol.to_string(),
+    }
+}
+
+fn symbol_from_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(symbol) => Some(symbol.clone()),
+        Value::Object(object) => symbol_from_value(object.get("symbol")?),
+        _ => None,
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// TradingView resolution string -> bar length in seconds.
+fn timeframe_seconds(timeframe: &str) -> u64 {
+    let (digits, unit) = timeframe.split_at(timeframe.find(|c: char| !c.is_ascii_digit()).unwrap_or(timeframe.len()));
+    let multiplier = digits.parse::<u64>().unwrap_or(1).max(1);
+    match unit {
+        "" => multiplier * 60,
+        "S" => multiplier,
+        "H" => multiplier * 3600,
+        "D" => multiplier * 86_400,
+        "W" => multiplier * 7 * 86_400,
+        "M" => multiplier * 30 * 86_400,
+        _ => 60,
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0)
+}
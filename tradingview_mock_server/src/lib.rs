@@ -0,0 +1,13 @@
+mod auth;
+mod config;
+mod connection;
+mod pine_facade;
+mod proxy;
+mod server;
+mod websocket;
+
+pub use auth::*;
+pub use config::*;
+pub use pine_facade::*;
+pub use proxy::*;
+pub use server::*;
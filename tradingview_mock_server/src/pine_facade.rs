@@ -0,0 +1,135 @@
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+
+use async_executor::Executor;
+use async_io::Async;
+use futures_lite::io::{AsyncReadExt, AsyncWriteExt};
+use simple_error::{box_err, SimpleResult};
+
+use crate::auth::percent_decode;
+
+/// A script served by `translate/{pine_id}/{pine_version}`; `last` matches any version.
+#[derive(Debug, Clone)]
+pub struct MockPineScript {
+    pub pine_id: String,
+    pub pine_version: String,
+    pub translate_response: String, // {"success":true,"result":{"metaInfo":{...},"ilTemplate":"..."}}
+}
+
+impl MockPineScript {
+    /// Metadata shaped like the public VWAP/MVWAP/EMA crossover script.
+    pub fn vwap_mvwap_ema_crossover() -> Self {
+        let pine_id = "PUB;N16MOYK6AEJGGAoy40axs0S48GRFYcNn";
+        let inputs = [
+            ("in_0", "VWAP Length", "integer", "1"),
+            ("in_1", "EMA 1 Source", "source", r#""close""#),
+            ("in_2", "EMA 1 Length", "integer", "7"),
+            ("in_3", "EMA 2 Source", "source", r#""close""#),
+            ("in_4", "EMA 2 Length", "integer", "25"),
+            ("in_5", "RSI Limit", "integer", "65"),
+            ("in_6", "RSI Minimum", "integer", "51"),
+            ("in_7", "MVWAP Length", "integer", "21"),
+        ];
+        let inputs = inputs
+            .iter()
+            .map(|(id, name, type_name, defval)| format!(r#"{{"id":"{id}","name":"{name}","type":"{type_name}","defval":{defval}}}"#))
+            .collect::<Vec<_>>()
+            .join(",");
+        let translate_response = format!(
+            r#"{{"success":true,"result":{{"metaInfo":{{"description":"VWAP MVWAP EMA Crossover","inputs":[{{"id":"text","name":"ILScript","type":"text","defval":"bmI9Ks46_mock","isHidden":true}},{{"id":"pineId","name":"pineId","type":"text","defval":"{pine_id}","isHidden":true}},{{"id":"pineVersion","name":"pineVersion","type":"text","defval":"1.0","isHidden":true}},{inputs}],"plots":[{{"id":"plot_0","type":"line"}},{{"id":"plot_1","type":"line"}},{{"id":"plot_2","type":"line"}},{{"id":"plot_3","type":"shapes"}}],"styles":{{"plot_0":{{"title":"VWAP"}},"plot_1":{{"title":"EMA 1"}},"plot_2":{{"title":"EMA 2"}},"plot_3":{{"title":"Crossover"}}}}}},"ilTemplate":"bmI9Ks46_mock"}}}}"#
+        );
+        Self {
+            pine_id: pine_id.to_string(),
+            pine_version: "1.0".to_string(),
+            translate_response,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockPineFacadeConfig {
+    pub scripts: Vec<MockPineScript>,
+}
+
+impl Default for MockPineFacadeConfig {
+    fn default() -> Self {
+        Self {
+            scripts: vec![MockPineScript::vwap_mvwap_ema_crossover()],
+        }
+    }
+}
+
+/// Local stand-in for `pine-facade.tradingview.com/pine-facade/translate/{pineId}/{version}`.
+pub struct TradingViewMockPineFacade {
+    listener: Async<TcpListener>,
+    config: MockPineFacadeConfig,
+}
+
+impl TradingViewMockPineFacade {
+    pub fn bind(address: &str, config: MockPineFacadeConfig) -> SimpleResult<Self> {
+        let address: SocketAddr = address.parse()?;
+        let listener = Async::<TcpListener>::bind(address)?;
+        Ok(Self { listener, config })
+    }
+
+    pub fn local_addr(&self) -> SimpleResult<SocketAddr> {
+        Ok(self.listener.get_ref().local_addr()?)
+    }
+
+    /// Value for `PineFacadeOptions::base_url`.
+    pub fn base_url(&self) -> SimpleResult<String> {
+        Ok(format!("http://{}", self.local_addr()?))
+    }
+
+    pub async fn run(&self, executor: Arc<Executor<'static>>) -> SimpleResult<()> {
+        log::info!("mock pine-facade: listening on {}", self.local_addr()?);
+        loop {
+            let (stream, peer) = self.listener.accept().await?;
+            let config = self.config.clone();
+            executor.spawn(async move {
+                if let Err(err) = serve_request(stream, config).await {
+                    log::warn!("mock pine-facade: {peer} failed: {err:?}");
+                }
+            }).detach();
+        }
+    }
+}
+
+async fn serve_request(stream: Async<TcpStream>, config: MockPineFacadeConfig) -> SimpleResult<()> {
+    let mut stream = &stream;
+
+    // read request head, translate requests have no body
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte).await? == 0 {
+            return Err(box_err!("mock pine-facade: connection closed during request"));
+        }
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8(head)?;
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+
+    // route
+    let script = match (method, path.strip_prefix("/pine-facade/translate/")) {
+        ("GET", Some(rest)) => rest.split_once('/').and_then(|(pine_id, pine_version)| {
+            let pine_id = percent_decode(pine_id);
+            let pine_version = percent_decode(pine_version);
+            config.scripts.iter().find(|script| script.pine_id == pine_id && (pine_version == "last" || script.pine_version == pine_version))
+        }),
+        _ => None,
+    };
+    let (status, response_body) = match script {
+        Some(script) => ("200 OK", script.translate_response.clone()),
+        None => ("404 Not Found", r#"{"success":false,"reason":"script not found"}"#.to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response_body}",
+        response_body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_executor::Executor;
+use async_io::Async;
+use base64::Engine;
+use futures_lite::io::{AsyncReadExt, AsyncWriteExt};
+use simple_error::{box_err, SimpleResult};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockProxyKind {
+    HttpConnect,
+    Socks5,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockProxyConfig {
+    pub kind: MockProxyKind,
+    /// Credentials the proxy demands; `None` accepts anyone.
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Minimal HTTP CONNECT / SOCKS5 proxy for exercising proxied connections locally.
+pub struct TradingViewMockProxy {
+    listener: Async<TcpListener>,
+    config: MockProxyConfig,
+    tunnel_count: Arc<AtomicUsize>,
+}
+
+impl TradingViewMockProxy {
+    pub fn bind(address: &str, config: MockProxyConfig) -> SimpleResult<Self> {
+        let address: std::net::SocketAddr = address.parse()?;
+        let listener = Async::<TcpListener>::bind(address)?;
+        Ok(Self {
+            listener,
+            config,
+            tunnel_count: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn local_addr(&self) -> SimpleResult<std::net::SocketAddr> {
+        Ok(self.listener.get_ref().local_addr()?)
+    }
+
+    /// Number of tunnels opened so far.
+    pub fn tunnel_count(&self) -> usize {
+        self.tunnel_count.load(Ordering::SeqCst)
+    }
+
+    pub async fn run(&self, executor: Arc<Executor<'static>>) -> SimpleResult<()> {
+        log::info!("mock proxy: listening on {}", self.local_addr()?);
+        loop {
+            let (stream, peer) = self.listener.accept().await?;
+            let config = self.config.clone();
+            let tunnel_count = self.tunnel_count.clone();
+            executor.spawn(async move {
+                if let Err(err) = serve_tunnel(stream, config, tunnel_count).await {
+                    log::warn!("mock proxy: {peer} failed: {err:?}");
+                }
+            }).detach();
+        }
+    }
+}
+
+async fn serve_tunnel(client: Async<TcpStream>, config: MockProxyConfig, tunnel_count: Arc<AtomicUsize>) -> SimpleResult<()> {
+    // negotiate target
+    let target = match config.kind {
+        MockProxyKind::HttpConnect => accept_http_connect(&client, &config).await?,
+        MockProxyKind::Socks5 => accept_socks5(&client, &config).await?,
+    };
+    let target = match target {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+    let address = target.to_socket_addrs()?.next().ok_or(box_err!("mock proxy: could not resolve target"))?;
+    let upstream = Async::<TcpStream>::connect(address).await?;
+    match config.kind {
+        MockProxyKind::HttpConnect => (&client).write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").await?,
+        MockProxyKind::Socks5 => (&client).write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).await?,
+    }
+    tunnel_count.fetch_add(1, Ordering::SeqCst);
+    log::info!("mock proxy: tunnel to {target}");
+
+    // pipe until either side closes
+    let (mut client_writer, mut upstream_writer) = (&client, &upstream);
+    let client_to_upstream = futures_lite::io::copy(&client, &mut upstream_writer);
+    let upstream_to_client = futures_lite::io::copy(&upstream, &mut client_writer);
+    let _ = futures_lite::future::or(client_to_upstream, upstream_to_client).await;
+    let _ = client.get_ref().shutdown(Shutdown::Both);
+    let _ = upstream.get_ref().shutdown(Shutdown::Both);
+    Ok(())
+}
+
+async fn accept_http_connect(client: &Async<TcpStream>, config: &MockProxyConfig) -> SimpleResult<Option<String>> {
+    // read request head
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if (&*client).read(&mut byte).await? == 0 {
+            return Err(box_err!("mock proxy: connection closed during CONNECT"));
+        }
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8(head)?;
+    let mut parts = head.lines().next().unwrap_or_default().split(' ');
+    if parts.next() != Some("CONNECT") {
+        (&*client).write_all(b"HTTP/1.1 405 Method Not Allowed\r\n\r\n").await?;
+        return Ok(None);
+    }
+    let target = parts.next().ok_or(box_err!("mock proxy: missing CONNECT target"))?.to_string();
+
+    // check basic auth
+    if let Some(username) = &config.username {
+        let password = config.password.as_deref().unwrap_or("");
+        let expected = format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}")));
+        let provided = head
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("proxy-authorization"))
+            .map(|(_, value)| value.trim().to_string());
+        if provided.as_deref() != Some(expected.as_str()) {
+            (&*client).write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\nProxy-Authenticate: Basic\r\n\r\n").await?;
+            return Ok(None);
+        }
+    }
+    Ok(Some(target))
+}
+
+async fn accept_socks5(client: &Async<TcpStream>, config: &MockProxyConfig) -> SimpleResult<Option<String>> {
+    let mut stream = client;
+
+    // greeting
+    let mut greeting = [0u8; 2];
+    stream.read_exact(&mut greeting).await?;
+    if greeting[0] != 0x05 {
+        return Err(box_err!("mock proxy: not a SOCKS5 greeting"));
+    }
+    let mut methods = vec![0u8; greeting[1] as usize];
+    stream.read_exact(&mut methods).await?;
+    let wanted_method = if config.username.is_some() { 0x02 } else { 0x00 };
+    if !methods.contains(&wanted_method) {
+        stream.write_all(&[0x05, 0xFF]).await?;
+        return Ok(None);
+    }
+    stream.write_all(&[0x05, wanted_method]).await?;
+
+    // username / password subnegotiation
+    if wanted_method == 0x02 {
+        let mut version_and_len = [0u8; 2];
+        stream.read_exact(&mut version_and_len).await?;
+        let mut username = vec![0u8; version_and_len[1] as usize];
+        stream.read_exact(&mut username).await?;
+        let mut password_len = [0u8; 1];
+        stream.read_exact(&mut password_len).await?;
+        let mut password = vec![0u8; password_len[0] as usize];
+        stream.read_exact(&mut password).await?;
+        let accepted = config.username.as_deref().map(str::as_bytes) == Some(username.as_slice())
+            && config.password.as_deref().unwrap_or("").as_bytes() == password.as_slice();
+        if !accepted {
+            stream.write_all(&[0x01, 0x01]).await?;
+            return Ok(None);
+        }
+        stream.write_all(&[0x01, 0x00]).await?;
+    }
+
+    // connect request
+    let mut request = [0u8; 4];
+    stream.read_exact(&mut request).await?;
+    if request[1] != 0x01 {
+        stream.write_all(&[0x05, 0x07, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).await?;
+        return Ok(None);
+    }
+    let host = match request[3] {
+        0x01 => {
+            let mut address = [0u8; 4];
+            stream.read_exact(&mut address).await?;
+            std::net::Ipv4Addr::from(address).to_string()
+        }
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            let mut domain = vec![0u8; len[0] as usize];
+            stream.read_exact(&mut domain).await?;
+            String::from_utf8(domain)?
+        }
+        0x04 => {
+            let mut address = [0u8; 16];
+            stream.read_exact(&mut address).await?;
+            format!("[{}]", std::net::Ipv6Addr::from(address))
+        }
+        address_type => return Err(box_err!(format!("mock proxy: unknown address type {address_type}"))),
+    };
+    let mut port = [0u8; 2];
+    stream.read_exact(&mut port).await?;
+    Ok(Some(format!("{host}:{}", u16::from_be_bytes(port))))
+}
@@ -0,0 +1,60 @@
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+
+use async_executor::Executor;
+use async_io::Async;
+use simple_error::SimpleResult;
+
+use crate::config::MockServerConfig;
+use crate::connection::MockConnection;
+
+/// Local stand-in for `wss://data.tradingview.com/socket.io/websocket`.
+pub struct TradingViewMockServer {
+    listener: Async<TcpListener>,
+    config: MockServerConfig,
+    auth_tokens: Arc<Mutex<Vec<String>>>,
+}
+
+impl TradingViewMockServer {
+    pub fn bind(address: &str, config: MockServerConfig) -> SimpleResult<Self> {
+        let address: SocketAddr = address.parse()?;
+        let listener = Async::<TcpListener>::bind(address)?;
+        Ok(Self {
+            listener,
+            config,
+            auth_tokens: Arc::new(Mutex::new(vec![])),
+        })
+    }
+
+    /// Every token received through `set_auth_token`, across connections, in order.
+    pub fn received_auth_tokens(&self) -> Vec<String> {
+        self.auth_tokens.lock().expect("auth token lock poisoned").clone()
+    }
+
+    pub fn local_addr(&self) -> SimpleResult<SocketAddr> {
+        Ok(self.listener.get_ref().local_addr()?)
+    }
+
+    /// Websocket URL to use as `ConnectionOptions::custom_endpoint`.
+    pub fn endpoint(&self) -> SimpleResult<String> {
+        Ok(format!("ws://{}/socket.io/websocket", self.local_addr()?))
+    }
+
+    /// Accepts connections forever, serving each one on the executor.
+    pub async fn run(&self, executor: Arc<Executor<'static>>) -> SimpleResult<()> {
+        log::info!("mock: listening on {}", self.local_addr()?);
+        loop {
+            let (stream, peer) = self.listener.accept().await?;
+            log::info!("mock: accepted {peer}");
+            let config = self.config.clone();
+            let auth_tokens = self.auth_tokens.clone();
+            let connection_executor = executor.clone();
+            executor.spawn(async move {
+                match MockConnection::serve(connection_executor, stream, config, auth_tokens).await {
+                    Ok(()) => log::info!("mock: {peer} disconnected"),
+                    Err(err) => log::warn!("mock: {peer} failed: {err:?}"),
+                }
+            }).detach();
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use base64::Engine;
+use futures_lite::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use sha1::{Digest, Sha1};
+use simple_error::{box_err, SimpleResult};
+
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+pub const OPCODE_CONTINUATION: u8 = 0x0;
+pub const OPCODE_TEXT: u8 = 0x1;
+pub const OPCODE_CLOSE: u8 = 0x8;
+pub const OPCODE_PING: u8 = 0x9;
+pub const OPCODE_PONG: u8 = 0xA;
+
+/// Reads the HTTP upgrade request and answers with `101 Switching Protocols`.
+///
+/// Returns the request target (path + query) so callers can inspect it.
+pub async fn accept_handshake<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S) -> SimpleResult<String> {
+    // read request head byte by byte so nothing past the blank line is consumed
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        let read = stream.read(&mut byte).await?;
+        if read == 0 {
+            return Err(box_err!("connection closed during handshake"));
+        }
+        head.push(byte[0]);
+        if head.len() > 64 * 1024 {
+            return Err(box_err!("handshake request too large"));
+        }
+    }
+    let head = String::from_utf8(head)?;
+    let mut lines = head.split("\r\n");
+    let request_line = lines.next().ok_or(box_err!("missing request line"))?;
+    let target = request_line.split(' ').nth(1).ok_or(box_err!("missing request target"))?.to_string();
+    let key = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("sec-websocket-key"))
+        .map(|(_, value)| value.trim().to_string())
+        .ok_or(box_err!("missing Sec-WebSocket-Key"))?;
+
+    // compute accept key
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    let accept = base64::engine::general_purpose::STANDARD.encode(hasher.finalize());
+
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(target)
+}
+
+/// Reads one (client, masked) frame. Returns `None` on EOF.
+pub async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> SimpleResult<Option<(u8, Vec<u8>)>> {
+    let mut header = [0u8; 2];
+    match stream.read_exact(&mut header).await {
+        Ok(()) => (),
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let opcode = header[0] & 0x0F;
+    let masked = header[1] & 0x80 != 0;
+    let payload_len = match header[1] & 0x7F {
+        126 => {
+            let mut extended = [0u8; 2];
+            stream.read_exact(&mut extended).await?;
+            u16::from_be_bytes(extended) as usize
+        }
+        127 => {
+            let mut extended = [0u8; 8];
+            stream.read_exact(&mut extended).await?;
+            u64::from_be_bytes(extended) as usize
+        }
+        len => len as usize,
+    };
+    let mut mask = [0u8; 4];
+    if masked {
+        stream.read_exact(&mut mask).await?;
+    }
+    let mut payload = vec![0u8; payload_len];
+    stream.read_exact(&mut payload).await?;
+    if masked {
+        for (index, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[index % 4];
+        }
+    }
+    Ok(Some((opcode, payload)))
+}
+
+/// Writes one unmasked (server) frame.
+pub async fn write_frame<S: AsyncWrite + Unpin>(stream: &mut S, opcode: u8, payload: &[u8]) -> SimpleResult<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    if payload.len() < 126 {
+        frame.push(payload.len() as u8);
+    } else if payload.len() <= u16::MAX as usize {
+        frame.push(126);
+        frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    } else {
+        frame.push(127);
+        frame.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+    }
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame).await?;
+    stream.flush().await?;
+    Ok(())
+}
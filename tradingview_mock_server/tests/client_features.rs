@@ -0,0 +1,374 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_lock::{Mutex, RwLock};
+use async_trait::async_trait;
+use simple_error::{box_err, SimpleResult};
+use async_executor::Executor;
+use tradingview_common::{ConnectionOptions, IndicatorConfig, MarketSession, ParsedTradingViewMessage, PinePlotDefinition, PineStudy, ProxyKind, ProxyOptions, StudyGraph, Timeframe, TimeframeUnit, TradingViewClientConfig, TradingViewHost, TradingViewIndicators, UpdateMode};
+use tradingview_client::{AuthCredentials, PineFacadeClient, PineFacadeOptions, ReplaySpeed, StatefulMessageProcessor, TradingViewAuthOptions, TradingViewAuthenticator, TradingViewClient, TradingViewClientEvent, TradingViewMessageProcessor};
+use tradingview_mock_server::{MockAuthConfig, MockContractRoll, MockFaults, MockPineFacadeConfig, MockProxyConfig, MockProxyKind, MockServerConfig, MockSessionChange, TradingViewMockAuthServer, TradingViewMockPineFacade, TradingViewMockProxy, TradingViewMockServer};
+
+const WAIT_TIMEOUT: Duration = Duration::from_secs(15);
+
+fn run<F: Future<Output = SimpleResult<()>>>(test: impl FnOnce(Arc<Executor<'static>>) -> F) {
+    let _ = env_logger::builder().is_test(true).try_init();
+    // the test drives the executor, so the mock servers and the client run alongside it
+    let executor = Arc::new(Executor::new());
+    async_io::block_on(executor.run(test(executor.clone()))).unwrap();
+}
+
+fn spawn_server(executor: &Arc<Executor<'static>>, config: MockServerConfig) -> SimpleResult<Arc<TradingViewMockServer>> {
+    let server = Arc::new(TradingViewMockServer::bind("127.0.0.1:0", config)?);
+    let server_executor = executor.clone();
+    let local_server = server.clone();
+    executor.spawn(async move {
+        if let Err(err) = local_server.run(server_executor).await {
+            log::error!("mock server failed: {err:?}");
+        }
+    }).detach();
+    Ok(server)
+}
+
+fn spawn_client(executor: &Arc<Executor<'static>>, client: Arc<TradingViewClient>) {
+    let client_executor = executor.clone();
+    executor.spawn(async move {
+        if let Err(err) = client.subscribe(client_executor).await {
+            log::error!("client failed: {err:?}");
+        }
+    }).detach();
+}
+
+fn client_config(endpoint: String, chart_symbols: Vec<&str>, quote_symbols: Vec<&str>, indicators: Vec<IndicatorConfig>) -> TradingViewClientConfig {
+    TradingViewClientConfig {
+        name: "MOCK:SPY".to_string(),
+        auth_token: String::new(),
+        anonymous: true,
+        chart_symbols: chart_symbols.into_iter().map(str::to_string).collect(),
+        quote_symbols: quote_symbols.into_iter().map(str::to_string).collect(),
+        indicators,
+        timeframe: Some(Timeframe::new(5, TimeframeUnit::Minutes)),
+        range: Some(10),
+        capture_path: None,
+        connection: ConnectionOptions {
+            host: TradingViewHost::Custom,
+            custom_endpoint: Some(endpoint),
+            ..ConnectionOptions::default()
+        },
+    }
+}
+
+// plot layout of the mock pine-facade's crossover script
+fn crossover(ema1_length: usize, ema2_length: usize) -> IndicatorConfig {
+    let plots = ["VWAP", "EMA 1", "EMA 2", "Crossover"]
+        .iter()
+        .enumerate()
+        .map(|(index, title)| PinePlotDefinition::new(&format!("plot_{index}"), "line", title))
+        .collect();
+    TradingViewIndicators::generate_vwap_mvwap_ema_crossover(1, "close".to_string(), ema1_length, "close".to_string(), ema2_length, 65, 51, 21)
+        .with_plots(plots)
+        .to_indicator()
+}
+
+// polls until `ready` holds or the wait times out
+async fn wait_for(ready: impl Fn() -> bool) -> SimpleResult<()> {
+    let deadline = Instant::now() + WAIT_TIMEOUT;
+    while Instant::now() < deadline {
+        if ready() {
+            return Ok(());
+        }
+        async_io::Timer::after(Duration::from_millis(50)).await;
+    }
+    Err(box_err!("timed out waiting for the mock session"))
+}
+
+/// Keeps the client's events, ignores the messages.
+#[derive(Default)]
+struct EventRecorder {
+    events: Vec<TradingViewClientEvent>,
+}
+
+#[async_trait]
+impl TradingViewMessageProcessor for EventRecorder {
+    async fn process_message(&mut self, _name: String, _message: ParsedTradingViewMessage) -> SimpleResult<()> {
+        Ok(())
+    }
+
+    async fn process_event(&mut self, _name: String, event: TradingViewClientEvent) -> SimpleResult<()> {
+        self.events.push(event);
+        Ok(())
+    }
+}
+
+#[test]
+fn streams_quotes_bars_and_studies() {
+    run(|executor| async move {
+        let server = spawn_server(&executor, MockServerConfig {
+            study_plot_count: 4,
+            ..MockServerConfig::default()
+        })?;
+        let config = client_config(server.endpoint()?, vec!["MOCK:SPY"], vec!["MOCK:SPY"], vec![crossover(7, 25)]);
+        let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
+        let client = Arc::new(TradingViewClient::new(config, message_processor.clone()));
+        let handle = client.handle();
+        spawn_client(&executor, client);
+
+        wait_for(|| message_processor.try_read().is_some_and(|processor| {
+            processor.quote_update.is_some() && processor.study_update.is_some() && processor.bar_history.values().any(|bars| bars.len() >= 10)
+        })).await?;
+        let processor = message_processor.read().await;
+        let chart_session_id = TradingViewClient::chart_session_id(0);
+        assert!(processor.symbol_info.contains_key(&chart_session_id));
+        assert!(processor.study_update.as_ref().unwrap().plot("EMA 1")?.is_finite());
+        // anonymous sessions are served delayed
+        assert_eq!(processor.series_freshness.get(&chart_session_id).map(|freshness| freshness.update_mode), Some(UpdateMode::DelayedStreaming));
+        drop(processor);
+        handle.shutdown().await;
+        Ok(())
+    });
+}
+
+#[test]
+fn modifies_study_inputs_on_the_live_chart() {
+    run(|executor| async move {
+        let server = spawn_server(&executor, MockServerConfig {
+            study_plot_count: 4,
+            ..MockServerConfig::default()
+        })?;
+        let config = client_config(server.endpoint()?, vec!["MOCK:SPY"], vec![], vec![crossover(7, 25)]);
+        let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
+        let client = Arc::new(TradingViewClient::new(config, message_processor.clone()));
+        let handle = client.handle();
+        spawn_client(&executor, client);
+        wait_for(|| message_processor.try_read().is_some_and(|processor| processor.study_update.is_some())).await?;
+
+        let indicator = crossover(9, 30);
+        let study_updates = handle.modify_study(&TradingViewClient::chart_session_id(0), &StudyGraph::study_id(0), &indicator).await?;
+        assert!(!study_updates.is_empty());
+        // unknown studies are refused
+        assert!(handle.modify_study(&TradingViewClient::chart_session_id(0), "st99", &indicator).await.is_err());
+        handle.shutdown().await;
+        Ok(())
+    });
+}
+
+#[test]
+fn records_a_capture_that_replays_offline() {
+    let capture_path = std::env::temp_dir().join(format!("tradingview_mock_capture_{}.jsonl", std::process::id()));
+    let capture_path = capture_path.to_str().unwrap().to_string();
+    let local_capture_path = capture_path.clone();
+    run(|executor| async move {
+        let started_at = tradingview_common::utilities::now()?;
+        let server = spawn_server(&executor, MockServerConfig::default())?;
+        let mut config = client_config(server.endpoint()?, vec!["MOCK:SPY"], vec!["MOCK:SPY"], vec![]);
+        config.capture_path = Some(local_capture_path.clone());
+        let live_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
+        let client = Arc::new(TradingViewClient::new(config.clone(), live_processor.clone()));
+        let handle = client.handle();
+        spawn_client(&executor, client);
+        wait_for(|| live_processor.try_read().is_some_and(|processor| processor.quote_update.is_some() && processor.bar_history.values().any(|bars| bars.len() >= 10))).await?;
+        handle.shutdown().await;
+
+        // replay through the full client stack
+        config.capture_path = None;
+        let replay_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
+        let replay_client = TradingViewClient::new(config, replay_processor.clone());
+        replay_client.replay(executor.clone(), &local_capture_path, ReplaySpeed::AsFastAsPossible).await?;
+        let live = live_processor.read().await;
+        let replayed = replay_processor.read().await;
+        assert_eq!(replayed.symbol_info.keys().collect::<Vec<_>>(), live.symbol_info.keys().collect::<Vec<_>>());
+        assert!(replayed.quote_update.is_some());
+        assert!(replayed.bar_history.values().any(|bars| bars.len() >= 10));
+        // the clock is back on the wall clock once the replay ends
+        assert!(replay_client.clock().now()? >= started_at);
+        Ok(())
+    });
+    std::fs::remove_file(&capture_path).unwrap();
+}
+
+#[test]
+fn connects_through_http_connect_and_socks5_proxies() {
+    for (mock_kind, kind) in [(MockProxyKind::HttpConnect, ProxyKind::HttpConnect), (MockProxyKind::Socks5, ProxyKind::Socks5)] {
+        run(|executor| async move {
+            let server = spawn_server(&executor, MockServerConfig::default())?;
+            let proxy = Arc::new(TradingViewMockProxy::bind("127.0.0.1:0", MockProxyConfig {
+                kind: mock_kind,
+                username: Some("collector".to_string()),
+                password: Some("hunter2".to_string()),
+            })?);
+            let proxy_port = proxy.local_addr()?.port();
+            let proxy_executor = executor.clone();
+            let local_proxy = proxy.clone();
+            executor.spawn(async move {
+                if let Err(err) = local_proxy.run(proxy_executor).await {
+                    log::error!("mock proxy failed: {err:?}");
+                }
+            }).detach();
+
+            let mut config = client_config(server.endpoint()?, vec![], vec!["MOCK:SPY"], vec![]);
+            config.connection.proxy = Some(ProxyOptions {
+                kind,
+                host: "127.0.0.1".to_string(),
+                port: proxy_port,
+                username: Some("collector".to_string()),
+                password: Some("hunter2".to_string()),
+            });
+            let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
+            let client = Arc::new(TradingViewClient::new(config, message_processor.clone()));
+            let handle = client.handle();
+            spawn_client(&executor, client);
+            wait_for(|| message_processor.try_read().is_some_and(|processor| processor.quote_update.is_some())).await?;
+            assert_eq!(proxy.tunnel_count(), 1);
+            handle.shutdown().await;
+            Ok(())
+        });
+    }
+}
+
+#[test]
+fn refreshes_a_revoked_auth_token() {
+    run(|executor| async move {
+        let auth_server = Arc::new(TradingViewMockAuthServer::bind("127.0.0.1:0", MockAuthConfig::default())?);
+        let auth_base_url = auth_server.base_url()?;
+        let auth_executor = executor.clone();
+        let local_auth_server = auth_server.clone();
+        executor.spawn(async move {
+            if let Err(err) = local_auth_server.run(auth_executor).await {
+                log::error!("mock auth server failed: {err:?}");
+            }
+        }).detach();
+        let server = spawn_server(&executor, MockServerConfig {
+            heartbeat_interval: Duration::from_secs(1),
+            faults: MockFaults {
+                revoke_auth_after: Some(Duration::from_secs(1)),
+                ..MockFaults::default()
+            },
+            ..MockServerConfig::default()
+        })?;
+
+        let mut config = client_config(server.endpoint()?, vec![], vec!["MOCK:SPY"], vec![]);
+        config.anonymous = false;
+        let mut auth_options = TradingViewAuthOptions::new(AuthCredentials::Password {
+            username: "mock".to_string(),
+            password: "mock".to_string(),
+        });
+        auth_options.base_url = auth_base_url;
+        let authenticator = Arc::new(Mutex::new(TradingViewAuthenticator::new(auth_options)));
+        let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
+        let client = Arc::new(TradingViewClient::new(config, message_processor.clone()).with_authenticator(authenticator));
+        let handle = client.handle();
+        spawn_client(&executor, client);
+
+        // the revoked token is replaced on the same connection
+        wait_for(|| server.received_auth_tokens().len() >= 2).await?;
+        let tokens = server.received_auth_tokens();
+        assert_ne!(tokens[0], tokens[1]);
+        assert!(auth_server.issued_count() >= 2);
+        handle.shutdown().await;
+        Ok(())
+    });
+}
+
+#[test]
+fn fetches_pine_metadata_for_the_plot_layout() {
+    run(|executor| async move {
+        let pine_facade = Arc::new(TradingViewMockPineFacade::bind("127.0.0.1:0", MockPineFacadeConfig::default())?);
+        let base_url = pine_facade.base_url()?;
+        let pine_facade_executor = executor.clone();
+        executor.spawn(async move {
+            if let Err(err) = pine_facade.run(pine_facade_executor).await {
+                log::error!("mock pine-facade failed: {err:?}");
+            }
+        }).detach();
+
+        let pine_facade_client = PineFacadeClient::new(PineFacadeOptions {
+            base_url,
+            ..PineFacadeOptions::default()
+        });
+        let metadata = pine_facade_client.fetch_metadata("PUB;N16MOYK6AEJGGAoy40axs0S48GRFYcNn", "last").await?;
+        let titles = metadata.plots.iter().map(|plot| plot.title.as_deref().unwrap_or_default()).collect::<Vec<_>>();
+        assert_eq!(titles, vec!["VWAP", "EMA 1", "EMA 2", "Crossover"]);
+        assert!(!metadata.inputs.is_empty());
+        assert!(pine_facade_client.fetch_metadata("PUB;missing", "last").await.is_err());
+        Ok(())
+    });
+}
+
+#[test]
+fn parses_strategy_reports_and_study_graphics() {
+    run(|executor| async move {
+        let server = spawn_server(&executor, MockServerConfig {
+            strategy_report: Some(r#"{
+                "currency": "USD",
+                "trades": [{"e": {"c": "Long", "tp": "l", "p": 100.0, "tm": 1700000000000}, "x": {"c": "Exit", "p": 104.0, "tm": 1700003600000}, "q": 10, "tp": {"v": 40.0, "p": 4.0}}],
+                "history": {"equity": [10000.0, 10040.0], "drawDown": [0.0, 10.0]},
+                "performance": {"all": {"netProfit": 40.0, "totalTrades": 1}, "maxStrategyDrawDown": 10.0, "openPL": 0.0}
+            }"#.to_string()),
+            study_graphics: Some(r#"{
+                "erase": [{"action": "all"}],
+                "create": {"dwglabels": [{"styleId": 0, "data": [{"id": 1, "x": 0, "y": 100.0, "t": "LONG"}]}]}
+            }"#.to_string()),
+            ..MockServerConfig::default()
+        })?;
+        let strategy = PineStudy::new("USER;mock_strategy", "1.0", "bmI9Ks46_mock");
+        let config = client_config(server.endpoint()?, vec!["MOCK:SPY"], vec![], vec![strategy.to_indicator()]);
+        let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
+        let client = Arc::new(TradingViewClient::new(config, message_processor.clone()));
+        let handle = client.handle();
+        spawn_client(&executor, client);
+
+        wait_for(|| message_processor.try_read().is_some_and(|processor| !processor.strategy_reports.is_empty() && !processor.study_graphics.is_empty())).await?;
+        let processor = message_processor.read().await;
+        let key = (TradingViewClient::chart_session_id(0), StudyGraph::study_id(0));
+        let strategy_report = processor.strategy_reports.get(&key).unwrap();
+        assert_eq!(strategy_report.trades.len(), 1);
+        assert_eq!(strategy_report.equity, vec![10000.0, 10040.0]);
+        let study_graphics = processor.study_graphics.get(&key).unwrap();
+        assert_eq!(study_graphics.labels.len(), 1);
+        drop(processor);
+        handle.shutdown().await;
+        Ok(())
+    });
+}
+
+#[test]
+fn reports_contract_rolls_and_market_status_changes() {
+    run(|executor| async move {
+        let server = spawn_server(&executor, MockServerConfig {
+            front_contract: Some("ESH2025".to_string()),
+            contract_roll: Some(MockContractRoll {
+                after: Duration::from_secs(1),
+                front_contract: "ESM2025".to_string(),
+            }),
+            session_changes: vec![MockSessionChange {
+                after: Duration::from_secs(1),
+                current_session: "post_market".to_string(),
+            }],
+            ..MockServerConfig::default()
+        })?;
+        let mut config = client_config(server.endpoint()?, vec!["MOCK:ES1!"], vec!["MOCK:ES1!"], vec![]);
+        config.timeframe = Some(Timeframe::new(1, TimeframeUnit::Minutes));
+        let event_recorder = Arc::new(RwLock::new(EventRecorder::default()));
+        let client = Arc::new(TradingViewClient::new(config, event_recorder.clone()));
+        let handle = client.handle();
+        spawn_client(&executor, client);
+
+        wait_for(|| event_recorder.try_read().is_some_and(|recorder| {
+            let rolled = recorder.events.iter().any(|event| matches!(event, TradingViewClientEvent::ContractRolled { .. }));
+            let status_changed = recorder.events.iter().any(|event| matches!(event, TradingViewClientEvent::MarketStatusChanged { .. }));
+            rolled && status_changed
+        })).await?;
+        for event in &event_recorder.read().await.events {
+            match event {
+                TradingViewClientEvent::ContractRolled { previous_contract, front_contract, .. } => {
+                    assert_eq!((previous_contract.as_str(), front_contract.as_str()), ("ESH2025", "ESM2025"));
+                }
+                TradingViewClientEvent::MarketStatusChanged { market_session, .. } => assert_eq!(*market_session, MarketSession::PostMarket),
+                TradingViewClientEvent::BarClosed { .. } => (),
+            }
+        }
+        handle.shutdown().await;
+        Ok(())
+    });
+}
@@ -1,81 +1,121 @@
 use std::{future::Future, sync::Arc};
 use std::pin::Pin;
 
-use async_lock::RwLock;
+use async_lock::{Mutex, RwLock};
 use csv_scraper::ScrapeOperation;
 use simple_error::SimpleResult;
 use async_executor::Executor;
-use tradingview_common::{TradingViewClientConfig, TradingViewSymbols};
-use tradingview_client::{StatefulMessageProcessor, TradingViewClient};
+use tradingview_common::{Clock, Timeframe, TradingViewClientConfig};
+use crate::config::{Config, DelayedDataPolicy, PricePrecision, SessionFilter};
+use tradingview_client::{StatefulMessageProcessor, TradingViewAuthenticator, TradingViewClient};
 
 pub struct CandleScraper {
-    pub symbol: String,
+    pub symbol: String, // file name safe, see Config::file_symbol
     pub session: String,
-    pub timeframe: String,
+    pub timeframe: Timeframe,
+    pub delayed_data: DelayedDataPolicy,
+    pub price_precision: PricePrecision,
+    pub session_filter: SessionFilter,
+    pub clock: Clock,
     pub message_processor: Arc<RwLock<StatefulMessageProcessor>>,
     pub client: Arc<TradingViewClient>,
 }
 
 impl CandleScraper {
-    pub fn new(auth_token: String, symbol: String, session: String, timeframe: String, range: usize) -> Self {
-        let built_symbol = TradingViewSymbols::build_symbol("splits", None, &session, &symbol);
-        let config = TradingViewClientConfig {
+    pub fn new(config: &Config, range: usize, capture_path: Option<String>, authenticator: Option<Arc<Mutex<TradingViewAuthenticator>>>) -> Self {
+        let symbol = config.file_symbol();
+        let session = config.session.clone();
+        let timeframe = config.timeframe;
+        let built_symbol = config.symbol_spec.to_string();
+        let delayed_data = config.delayed_data.clone();
+        let price_precision = config.price_precision.clone();
+        let session_filter = config.session_filter.clone();
+        let client_config = TradingViewClientConfig {
             name: "client".to_string(),
-            auth_token: auth_token.to_string(),
+            auth_token: config.auth_token.clone().unwrap_or_default(),
+            anonymous: config.anonymous,
             chart_symbols: vec![built_symbol.to_string()],
             quote_symbols: vec![],
             indicators: vec![],
-            timeframe: Some(timeframe.to_string()),
+            timeframe: Some(config.timeframe),
             range: Some(range),
+            capture_path,
+            connection: config.connection.clone(),
+        };
+        // rows are stamped with the client's clock, the capture's time when replaying
+        let clock = Clock::default();
+        let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default().with_clock(clock.clone())));
+        let client = TradingViewClient::new(client_config, message_processor.clone()).with_clock(clock.clone());
+        let client = match authenticator {
+            Some(authenticator) => client.with_authenticator(authenticator),
+            None => client,
         };
-        let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
-        let client = TradingViewClient::new(config, message_processor.clone());
         let client = Arc::new(client);
-        Self { symbol, session, timeframe, client, message_processor }
+        Self { symbol, session, timeframe, delayed_data, price_precision, session_filter, client, clock, message_processor }
     }
 }
 
 impl ScrapeOperation for CandleScraper {
     fn execute(&self, _executor: Arc<Executor<'static>>) -> Pin<Box<dyn Future<Output = SimpleResult<String>> + Send + 'static>> {
-        let timeframe = self.timeframe.clone();
+        let timeframe = self.timeframe;
+        let clock = self.clock.clone();
         let stateful_message_processor = self.message_processor.clone();
+        let delayed_data = self.delayed_data.clone();
+        let price_precision = self.price_precision.clone();
+        let session_filter = self.session_filter.clone();
         Box::pin(async move {
-            let stateful_message_processor = stateful_message_processor.read().await;
-            if let Some(series_update) = &stateful_message_processor.series_update {
+            // one row per closed bar, nothing while a bar is forming
+            let mut stateful_message_processor = stateful_message_processor.write().await;
+            let closed_bars = stateful_message_processor.take_closed_bars();
+            if closed_bars.is_empty() {
+                log::debug!("no closed candle");
+                return Ok(String::new());
+            }
+            let now = clock.now()?;
+            let mut lines = String::new();
+            for (chart_session_id, series_update) in closed_bars {
                 let candle_start = series_update.timestamp;
-                let timeframe_secs = if timeframe == "1" {
-                    60 // 1 minute
-                } else if timeframe == "5" {
-                    300 // 5 minutes
-                }else {
-                    unimplemented!()
+                let symbol_info = match stateful_message_processor.symbol_info.get(&chart_session_id) {
+                    Some(symbol_info) => symbol_info,
+                    None => {
+                        log::warn!("[candle] skipping {candle_start}: no symbol info for {chart_session_id}");
+                        continue;
+                    }
+                };
+                let chart_calendar = symbol_info.chart_calendar()?;
+                let price_format = symbol_info.price_format();
+                let session_calendar = symbol_info.session_calendar()?;
+                let session = match session_filter.apply(&session_calendar, candle_start) {
+                    Ok(session) => session,
+                    Err(err) => {
+                        log::debug!("[candle] skipping {candle_start}: {err}");
+                        continue;
+                    }
+                };
+                let (freshness, delay_secs) = match delayed_data.apply(series_update.freshness.as_ref()) {
+                    Ok(freshness) => freshness,
+                    Err(err) => {
+                        log::debug!("[candle] skipping {candle_start}: {err}");
+                        continue;
+                    }
                 };
-                let candle_end = candle_start + timeframe_secs - 1;
-                let now = tradingview_common::utilities::now()?;
+                let candle_end = timeframe.bar_end(candle_start, &chart_calendar)?;
                 let candle_age = now - candle_start;
-                let candle_remaining = (candle_end as i64) - (now as i64); // watch out for underflow?
-                let open = series_update.open;
-                let high = series_update.high;
-                let low = series_update.low;
-                let close = series_update.close;
-                let volume = series_update.volume;
+                let open = price_precision.format(series_update.open, price_format.as_ref());
+                let high = price_precision.format(series_update.high, price_format.as_ref());
+                let low = price_precision.format(series_update.low, price_format.as_ref());
+                let close = price_precision.format(series_update.close, price_format.as_ref());
+                let volume = series_update.volume.map(|volume| format!("{volume:.4}")).unwrap_or_default(); // empty without volume
 
                 // log
-                let line = format!("{now},{candle_start},{candle_end},{candle_age},{candle_remaining},{open:.2},{high:.2},{low:.2},{close:.2},{volume:.4}");              
+                let line = format!("{now},{candle_start},{candle_end},{candle_age},{open},{high},{low},{close},{volume},{freshness},{delay_secs}{session}");
                 log::info!("[candle] {line}");
-
-                // return
-                Ok(format!("{line}\n"))
-            } else {
-                log::warn!("no candle update");
-                
-                // log
-                let line = format!(",,,,,,,,");              
-                log::warn!("[candle] {line}");
-
-                // return
-                Ok(format!("{line}\n"))
+                lines.push_str(&line);
+                lines.push('\n');
             }
+
+            // return
+            Ok(lines)
         })
     }
 }
\ No newline at end of file
@@ -1,66 +1,117 @@
 use std::{future::Future, sync::Arc};
 use std::pin::Pin;
 
-use async_lock::RwLock;
+use async_lock::{Mutex, RwLock};
 use csv_scraper::ScrapeOperation;
-use simple_error::SimpleResult;
+use simple_error::{box_err, SimpleResult};
 use async_executor::Executor;
-use tradingview_common::{TradingViewClientConfig, TradingViewSymbols};
-use tradingview_client::{StatefulMessageProcessor, TradingViewClient};
+use tradingview_common::{Clock, IndicatorConfig, Timeframe, TradingViewClientConfig};
+use crate::config::{Config, DelayedDataPolicy, PricePrecision, SessionFilter};
+use tradingview_client::{StatefulMessageProcessor, TradingViewAuthenticator, TradingViewClient};
+
+// data columns of a row, before the optional session column
+const COLUMNS: usize = 10;
+
+/// Plot titles the csv columns are read from, checked against the script metadata at startup.
+pub const PLOT_TITLES: [&str; 6] = ["MVWAP", "VWAP", "Long Entry", "Short Entry", "EMA 1", "EMA 2"];
 
 pub struct IndicatorScraper {
-    pub symbol: String,
+    pub symbol: String, // file name safe, see Config::file_symbol
     pub session: String,
-    pub timeframe: String,
+    pub timeframe: Timeframe,
+    pub delayed_data: DelayedDataPolicy,
+    pub price_precision: PricePrecision,
+    pub session_filter: SessionFilter,
+    pub clock: Clock,
     pub message_processor: Arc<RwLock<StatefulMessageProcessor>>,
     pub client: Arc<TradingViewClient>,
 }
 
 impl IndicatorScraper {
-    pub fn new(auth_token: String, symbol: String, session: String, timeframe: String, range: usize, indicator: String) -> Self {
-        let built_symbol = TradingViewSymbols::build_symbol("splits", None, &session, &symbol);
-        let config = TradingViewClientConfig {
+    pub fn new(config: &Config, range: usize, indicator: IndicatorConfig, capture_path: Option<String>, authenticator: Option<Arc<Mutex<TradingViewAuthenticator>>>) -> Self {
+        let symbol = config.file_symbol();
+        let session = config.session.clone();
+        let timeframe = config.timeframe;
+        let built_symbol = config.symbol_spec.to_string();
+        let delayed_data = config.delayed_data.clone();
+        let price_precision = config.price_precision.clone();
+        let session_filter = config.session_filter.clone();
+        let client_config = TradingViewClientConfig {
             name: "client".to_string(),
-            auth_token: auth_token.to_string(),
+            auth_token: config.auth_token.clone().unwrap_or_default(),
+            anonymous: config.anonymous,
             chart_symbols: vec![built_symbol.to_string()],
             quote_symbols: vec![],
             indicators: vec![
-                indicator.to_string()
+                indicator
             ],
-            timeframe: Some(timeframe.to_string()),
+            timeframe: Some(config.timeframe),
             range: Some(range),
+            capture_path,
+            connection: config.connection.clone(),
+        };
+        // rows are stamped with the client's clock, the capture's time when replaying
+        let clock = Clock::default();
+        let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default().with_clock(clock.clone())));
+        let client = TradingViewClient::new(client_config, message_processor.clone()).with_clock(clock.clone());
+        let client = match authenticator {
+            Some(authenticator) => client.with_authenticator(authenticator),
+            None => client,
         };
-        let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
-        let client = TradingViewClient::new(config, message_processor.clone());
         let client = Arc::new(client);
-        Self { symbol, session, timeframe, client, message_processor }
+        Self { symbol, session, timeframe, delayed_data, price_precision, session_filter, client, clock, message_processor }
     }
 }
 
 impl ScrapeOperation for IndicatorScraper {
     fn execute(&self, _executor: Arc<Executor<'static>>) -> Pin<Box<dyn Future<Output = SimpleResult<String>> + Send + 'static>> {
+        let clock = self.clock.clone();
         let stateful_message_processor = self.message_processor.clone();
+        let delayed_data = self.delayed_data.clone();
+        let price_precision = self.price_precision.clone();
+        let session_filter = self.session_filter.clone();
         Box::pin(async move {
             let stateful_message_processor = stateful_message_processor.read().await;
             if let Some(study_update) = &stateful_message_processor.study_update {
-                let values = &study_update.values;
-                let now = tradingview_common::utilities::now()?;
-                let candle_timestamp = &values[0];
-                let mvwap = &values[1];
-                let vwap = &values[2];
-                let long_entry = &values[3];
-                let short_entry = &values[4];
-                let _buy_alert = &values[5];
-                let _risky = &values[6];
-                let _wait = &values[7];
-                let _enter_here = &values[8];
-                let ema1 = &values[9];
-                let ema2 = &values[10];
-                let _senkou_a = &values[11];
-                let _senkou_b = &values[12];
+                let now = clock.now()?;
+                // addressed by plot title so a changed script layout errors instead of shifting columns
+                let candle_timestamp = study_update.timestamp().ok_or(box_err!("study update has no timestamp"))?;
+                let mvwap = study_update.plot("MVWAP")?;
+                let vwap = study_update.plot("VWAP")?;
+                let long_entry = study_update.plot("Long Entry")?;
+                let short_entry = study_update.plot("Short Entry")?;
+                let ema1 = study_update.plot("EMA 1")?;
+                let ema2 = study_update.plot("EMA 2")?;
               
+                // studies run on the chart series, so they share its freshness
+                let chart_session_id = TradingViewClient::chart_session_id(0);
+                let (freshness, delay_secs) = match delayed_data.apply(stateful_message_processor.series_freshness.get(&chart_session_id)) {
+                    Ok(freshness) => freshness,
+                    Err(err) => {
+                        log::debug!("[indicator] skipping {candle_timestamp}: {err}");
+                        return Ok(String::new());
+                    }
+                };
+                let symbol_info = stateful_message_processor.symbol_info.get(&chart_session_id).ok_or(box_err!("no symbol info"))?;
+                let session = match session_filter.apply(&symbol_info.session_calendar()?, candle_timestamp as u64) {
+                    Ok(session) => session,
+                    Err(err) => {
+                        log::debug!("[indicator] skipping {candle_timestamp}: {err}");
+                        return Ok(String::new());
+                    }
+                };
+
+                // the lines are prices of the chart symbol, the entries are signals
+                let price_format = symbol_info.price_format();
+                let (mvwap, vwap, ema1, ema2) = (
+                    price_precision.format(mvwap, price_format.as_ref()),
+                    price_precision.format(vwap, price_format.as_ref()),
+                    price_precision.format(ema1, price_format.as_ref()),
+                    price_precision.format(ema2, price_format.as_ref()),
+                );
+
                 // build line
-                let line = format!("{now},{candle_timestamp},{mvwap:.2},{vwap:.2},{long_entry},{short_entry},{ema1:.2},{ema2:.2}");
+                let line = format!("{now},{candle_timestamp},{mvwap},{vwap},{long_entry},{short_entry},{ema1},{ema2},{freshness},{delay_secs}{session}");
                 log::info!("[indicator] {line}");
               
                 // return
@@ -69,7 +120,7 @@ impl ScrapeOperation for IndicatorScraper {
                 log::warn!("no study update");
                 
                 // log
-                let line = format!(",,,,,,,");
+                let line = session_filter.empty_row(COLUMNS);
                 log::warn!("[indictator] {line}");
 
                 // return
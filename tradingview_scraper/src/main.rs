@@ -8,30 +8,85 @@ use std::time::Duration;
 
 use async_executor::Executor;
 use csv_scraper::CsvScraper;
-use simple_error::SimpleResult;
+use async_lock::Mutex;
+use simple_error::{box_err, SimpleResult};
 use smol_base::smol_main;
-use tradingview_common::TradingViewIndicators;
+use tradingview_common::{PineScriptMetadata, TradingViewIndicators};
+use tradingview_client::{AuthCredentials, PineFacadeClient, PineFacadeOptions, ReplaySpeed, TradingViewAuthOptions, TradingViewAuthenticator, TradingViewClient};
 
 use crate::candle_scraper::CandleScraper;
-use crate::indicator_scraper::IndicatorScraper;
+use crate::indicator_scraper::{IndicatorScraper, PLOT_TITLES};
 use crate::quote_scraper::QuoteScraper;
 use crate::config::Config;
 
-async fn spawn_quote_csv_scraper(executor: Arc<Executor<'static>>, config: Config) -> SimpleResult<()> {
+fn spawn_client(executor: Arc<Executor<'static>>, config: &Config, client: Arc<TradingViewClient>, capture_file_name: String) -> SimpleResult<()> {
+    // replay a capture instead of connecting when REPLAY_DIR is set
+    let replay_path = config.replay_dir.as_ref().map(|replay_dir| format!("{replay_dir}/{capture_file_name}"));
+    let replay_speed = ReplaySpeed::from_string(&config.replay_speed)?;
+    let executor_clone = executor.clone();
+    let handle = executor.spawn(async move {
+        match replay_path {
+            Some(replay_path) => client.replay(executor_clone, &replay_path, replay_speed).await,
+            None => client.subscribe(executor_clone).await,
+        }
+    });
+    handle.detach();
+    Ok(())
+}
+
+fn build_authenticator(config: &Config) -> Option<Arc<Mutex<TradingViewAuthenticator>>> {
+    // username/password wins over a session cookie, AUTH_TOKEN alone means no refresh
+    let credentials = match (&config.tv_username, &config.tv_password, &config.tv_sessionid) {
+        (Some(username), Some(password), _) => AuthCredentials::Password {
+            username: username.clone(),
+            password: password.clone(),
+        },
+        (_, _, Some(sessionid)) => AuthCredentials::SessionId {
+            sessionid: sessionid.clone(),
+            sessionid_sign: config.tv_sessionid_sign.clone(),
+        },
+        _ => return None,
+    };
+    let mut options = TradingViewAuthOptions::new(credentials);
+    options.cache_path = config.auth_cache_path.clone();
+    options.proxy = config.connection.proxy.clone();
+    Some(Arc::new(Mutex::new(TradingViewAuthenticator::new(options))))
+}
+
+fn capture_path(config: &Config, capture_file_name: &str) -> Option<String> {
+    config.capture_dir.as_ref().map(|capture_dir| format!("{capture_dir}/{capture_file_name}"))
+}
+
+async fn load_pine_metadata(config: &Config, pine_id: &str, pine_version: &str, layout_file_name: &str) -> SimpleResult<PineScriptMetadata> {
+    // replays read the layout saved next to the capture, so they work offline and match the captured plots
+    if let Some(replay_dir) = &config.replay_dir {
+        let layout_path = format!("{replay_dir}/{layout_file_name}");
+        let response = async_fs::read_to_string(&layout_path).await
+            .map_err(|err| box_err!(format!("failed to read plot layout {layout_path} (written when capturing): {err}")))?;
+        return PineScriptMetadata::from_translate_response(&response);
+    }
+    let options = PineFacadeOptions {
+        proxy: config.connection.proxy.clone(),
+        ..PineFacadeOptions::default()
+    };
+    let response = PineFacadeClient::new(options).fetch_translate_response(pine_id, pine_version).await?;
+    if let Some(layout_path) = capture_path(config, layout_file_name) {
+        async_fs::write(&layout_path, &response).await?;
+    }
+    PineScriptMetadata::from_translate_response(&response)
+}
+
+async fn spawn_quote_csv_scraper(executor: Arc<Executor<'static>>, config: Config, authenticator: Option<Arc<Mutex<TradingViewAuthenticator>>>) -> SimpleResult<()> {
     // build scraper
+    let capture_file_name = format!("{0}-{1}-quote.jsonl", config.file_symbol(), config.session);
     let quote_scraper = QuoteScraper::new(
-        config.auth_token.clone(),
-        config.symbol.clone(),
-        config.session.clone(),
+        &config,
+        capture_path(&config, &capture_file_name),
+        authenticator,
     );
 
     // spawn
-    let client = quote_scraper.client.clone();
-    let executor_clone = executor.clone();
-    let handle = executor.spawn(async move {
-        client.subscribe(executor_clone).await
-    });
-    handle.detach();
+    spawn_client(executor.clone(), &config, quote_scraper.client.clone(), capture_file_name)?;
 
     // build csv scraper
     let date = tradingview_common::utilities::get_current_date()?;
@@ -41,23 +96,18 @@ async fn spawn_quote_csv_scraper(executor: Arc<Executor<'static>>, config: Confi
     quote_csv_scraper.scrape(executor_clone, Duration::from_secs(5)).await
 }
 
-async fn spawn_candle_csv_scraper(executor: Arc<Executor<'static>>, config: Config) -> SimpleResult<()> {
+async fn spawn_candle_csv_scraper(executor: Arc<Executor<'static>>, config: Config, authenticator: Option<Arc<Mutex<TradingViewAuthenticator>>>) -> SimpleResult<()> {
     // build scraper
+    let capture_file_name = format!("{0}-{1}-{2}-candle.jsonl", config.file_symbol(), config.session, config.timeframe);
     let candle_scraper = CandleScraper::new(
-        config.auth_token.clone(),
-        config.symbol.clone(),
-        config.session.clone(),
-        config.timeframe.clone(),
+        &config,
         1,
+        capture_path(&config, &capture_file_name),
+        authenticator,
     );
 
     // spawn
-    let client = candle_scraper.client.clone();
-    let executor_clone = executor.clone();
-    let handle = executor.spawn(async move {
-        client.subscribe(executor_clone).await
-    });
-    handle.detach();
+    spawn_client(executor.clone(), &config, candle_scraper.client.clone(), capture_file_name)?;
 
     // build csv scraper
     let date = tradingview_common::utilities::get_current_date()?;
@@ -67,7 +117,7 @@ async fn spawn_candle_csv_scraper(executor: Arc<Executor<'static>>, config: Conf
     candle_csv_scraper.scrape(executor_clone, Duration::from_secs(5)).await
 }
 
-async fn spawn_indicator_csv_scraper(executor: Arc<Executor<'static>>, config: Config) -> SimpleResult<()> {
+async fn spawn_indicator_csv_scraper(executor: Arc<Executor<'static>>, config: Config, authenticator: Option<Arc<Mutex<TradingViewAuthenticator>>>) -> SimpleResult<()> {
     // build scraper
     let indicator = TradingViewIndicators::generate_vwap_mvwap_ema_crossover(
         1,
@@ -79,22 +129,25 @@ async fn spawn_indicator_csv_scraper(executor: Arc<Executor<'static>>, config: C
         51,
         21     
     );
+    // plot layout comes from the published script so a changed script errors here instead of shifting columns
+    let capture_file_name = format!("{0}-{1}-{2}-indicator.jsonl", config.file_symbol(), config.session, config.timeframe);
+    let layout_file_name = format!("{0}-{1}-{2}-indicator-plots.json", config.file_symbol(), config.session, config.timeframe);
+    let metadata = load_pine_metadata(&config, &indicator.pine_id, &indicator.pine_version, &layout_file_name).await?;
+    if let Some(missing) = PLOT_TITLES.iter().find(|title| metadata.plot(title).is_none()) {
+        let available = metadata.plots.iter().map(|plot| plot.title.as_deref().unwrap_or(&plot.id)).collect::<Vec<_>>().join(", ");
+        return Err(box_err!(format!("{} has no plot {missing}, available: {available}", indicator.pine_id)));
+    }
+    let indicator = indicator.with_plots(metadata.plots);
     let indicator_scraper = IndicatorScraper::new(
-        config.auth_token.clone(),
-        config.symbol.clone(),
-        config.session.clone(),
-        config.timeframe.clone(),
+        &config,
         1,
-        indicator
+        indicator.to_indicator(),
+        capture_path(&config, &capture_file_name),
+        authenticator,
     );
 
     // spawn
-    let client = indicator_scraper.client.clone();
-    let executor_clone = executor.clone();
-    let handle = executor.spawn(async move {
-        client.subscribe(executor_clone).await
-    });
-    handle.detach();
+    spawn_client(executor.clone(), &config, indicator_scraper.client.clone(), capture_file_name)?;
 
     // build csv scraper
     let date = tradingview_common::utilities::get_current_date()?;
@@ -111,11 +164,15 @@ async fn async_main(executor: &Arc<Executor<'static>>) -> SimpleResult<()> {
 
     // load config
     let config = Config::from_env()?;
+    let authenticator = build_authenticator(&config);
+    if authenticator.is_none() && config.auth_token.is_none() && !config.anonymous {
+        return Err(box_err!("set AUTH_TOKEN, TV_USERNAME + TV_PASSWORD, TV_SESSIONID or ANONYMOUS=true"));
+    }
 
     // spawn handles
-    let quote_handle = executor.spawn(spawn_quote_csv_scraper(executor.clone(), config.clone()));
-    let candle_handle = executor.spawn(spawn_candle_csv_scraper(executor.clone(), config.clone()));
-    let indicator_handle = executor.spawn(spawn_indicator_csv_scraper(executor.clone(), config.clone()));
+    let quote_handle = executor.spawn(spawn_quote_csv_scraper(executor.clone(), config.clone(), authenticator.clone()));
+    let candle_handle = executor.spawn(spawn_candle_csv_scraper(executor.clone(), config.clone(), authenticator.clone()));
+    let indicator_handle = executor.spawn(spawn_indicator_csv_scraper(executor.clone(), config.clone(), authenticator.clone()));
 
     // wait for handles
     quote_handle.await?;
@@ -1,42 +1,68 @@
 use std::{future::Future, sync::Arc};
 use std::pin::Pin;
 
-use async_lock::RwLock;
+use async_lock::{Mutex, RwLock};
 use csv_scraper::ScrapeOperation;
 use simple_error::{box_err, SimpleResult};
 use async_executor::Executor;
-use tradingview_common::{TradingViewClientConfig, TradingViewSymbols};
-use tradingview_client::{StatefulMessageProcessor, TradingViewClient};
+use tradingview_common::{Clock, MarketSession, TradingViewClientConfig};
+use crate::config::{Config, DelayedDataPolicy, PricePrecision, SessionFilter};
+use tradingview_client::{StatefulMessageProcessor, TradingViewAuthenticator, TradingViewClient};
+
+// data columns of a row, before the optional session column
+const COLUMNS: usize = 10;
 
 pub struct QuoteScraper {
-    pub symbol: String,
+    pub symbol: String, // file name safe, see Config::file_symbol
     pub session: String,
+    pub delayed_data: DelayedDataPolicy,
+    pub price_precision: PricePrecision,
+    pub session_filter: SessionFilter,
+    pub clock: Clock,
     pub message_processor: Arc<RwLock<StatefulMessageProcessor>>,
     pub client: Arc<TradingViewClient>,
 }
 
 impl QuoteScraper {
-    pub fn new(auth_token: String, symbol: String, session: String) -> Self {
-        let built_symbol = TradingViewSymbols::build_symbol("splits", None, &session, &symbol);
-        let config = TradingViewClientConfig {
+    pub fn new(config: &Config, capture_path: Option<String>, authenticator: Option<Arc<Mutex<TradingViewAuthenticator>>>) -> Self {
+        let symbol = config.file_symbol();
+        let session = config.session.clone();
+        let built_symbol = config.symbol_spec.to_string();
+        let delayed_data = config.delayed_data.clone();
+        let price_precision = config.price_precision.clone();
+        let session_filter = config.session_filter.clone();
+        let client_config = TradingViewClientConfig {
             name: "client".to_string(),
-            auth_token: auth_token.to_string(),
+            auth_token: config.auth_token.clone().unwrap_or_default(),
+            anonymous: config.anonymous,
             chart_symbols: vec![],
             quote_symbols: vec![built_symbol.to_string()],
             indicators: vec![],
             timeframe: None,
             range: None,
+            capture_path,
+            connection: config.connection.clone(),
+        };
+        // rows are stamped with the client's clock, the capture's time when replaying
+        let clock = Clock::default();
+        let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default().with_clock(clock.clone())));
+        let client = TradingViewClient::new(client_config, message_processor.clone()).with_clock(clock.clone());
+        let client = match authenticator {
+            Some(authenticator) => client.with_authenticator(authenticator),
+            None => client,
         };
-        let message_processor = Arc::new(RwLock::new(StatefulMessageProcessor::default()));
-        let client = TradingViewClient::new(config, message_processor.clone());
         let client = Arc::new(client);
-        Self { symbol, session, message_processor, client }
+        Self { symbol, session, delayed_data, price_precision, session_filter, clock, message_processor, client }
     }
 }
 
 impl ScrapeOperation for QuoteScraper {
     fn execute(&self, _executor: Arc<Executor<'static>>) -> Pin<Box<dyn Future<Output = SimpleResult<String>> + Send + 'static>> {
+        let clock = self.clock.clone();
         let stateful_message_processor = self.message_processor.clone();
+        let delayed_data = self.delayed_data.clone();
+        let price_precision = self.price_precision.clone();
+        let session_filter = self.session_filter.clone();
         Box::pin(async move {
             let stateful_message_processor = stateful_message_processor.read().await;
             if let Some(quote_update) = &stateful_message_processor.quote_update {
@@ -47,11 +73,33 @@ impl ScrapeOperation for QuoteScraper {
                 let chp = quote_update.chp.as_ref().ok_or(box_err!("no chp"))?;
 
                 let prev_close = lp - ch;
-                let now = tradingview_common::utilities::now()?;
+                let price_format = quote_update.price_format();
+                let (lp, ch, prev_close) = (
+                    price_precision.format(*lp, price_format.as_ref()),
+                    price_precision.format(*ch, price_format.as_ref()),
+                    price_precision.format(prev_close, price_format.as_ref()),
+                );
+                let now = clock.now()?;
                 let quote_age = (now as i64) - (*lp_time as i64);
+                let (freshness, delay_secs) = match delayed_data.apply(quote_update.freshness().as_ref()) {
+                    Ok(freshness) => freshness,
+                    Err(err) => {
+                        log::debug!("[quote] skipping row: {err}");
+                        return Ok(String::new());
+                    }
+                };
+                // quotes carry the exchange state, no calendar lookup needed
+                let market_session = quote_update.market_session().unwrap_or(MarketSession::Closed);
+                let session = match session_filter.apply_session(market_session) {
+                    Ok(session) => session,
+                    Err(err) => {
+                        log::debug!("[quote] skipping row: {err}");
+                        return Ok(String::new());
+                    }
+                };
 
                 // log
-                let line = format!("{now},{lp_time},{quote_age},{lp:.2},{ch:.2},{chp:.2},{volume:.4},{prev_close:.2}");
+                let line = format!("{now},{lp_time},{quote_age},{lp},{ch},{chp:.2},{volume:.4},{prev_close},{freshness},{delay_secs}{session}");
                 log::info!("[quote] {line}");
 
                 // return
@@ -60,7 +108,7 @@ impl ScrapeOperation for QuoteScraper {
                 log::warn!("no quote update");
                 
                 // log
-                let line = format!(",,,,,,,");
+                let line = session_filter.empty_row(COLUMNS);
                 log::warn!("[quote] {line}");
 
                 // return